@@ -0,0 +1,69 @@
+//! Measures how fast the lexer gets through generated source files of growing size.
+//!
+//! Run with `cargo bench --bench lexer`. The lexer makes a single pass over its input, so
+//! the throughput should stay the same as the files grow.
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use compiler::lexer::Lexer;
+
+/// A file of `items` groups of items, which between them use every kind of token.
+fn generate(items: usize) -> String {
+    let mut source = String::from("//! A generated file.\n\n");
+    for i in 0..items {
+        write!(
+            source,
+            r#"/// Adds up the values of item {i}.
+fn sum_{i}(a: Int, mut b: Int): Int -> {{
+    let total = a + b * 2 - 0xFF_u8 ** 2 ^ 0b1010; // the total
+    /* a /* nested */ comment */
+    if (total >= 1_000 && !(b == 3) || a != 4) {{ b = total / 3 }} else {{ 1.5e3 }};
+    let text = "sum {{total}} of {{a + b}}\t\u{{1F600}}";
+    let raw = r"C:\files";
+    let c = '\n';
+    """
+        multi-line
+        """
+}}
+
+const LIMIT_{i}: F32 = 2.5f32;
+struct Point{i}<T> {{ x: T, y: [T], z: (T, Bool), f: fn(T): T }}
+enum Shape{i} {{ Circle(Float), Square {{ side: Float }}, Empty }}
+match $ x {{ _ }}
+
+"#
+        )
+        .unwrap();
+    }
+
+    source
+}
+
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "the files are nowhere near 2^52 bytes"
+)]
+fn main() {
+    for items in [100, 1_000, 10_000] {
+        let source = generate(items);
+
+        // run for long enough to even out noise
+        let mut runs = 0;
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(1) || runs < 3 {
+            black_box(Lexer::with_trivia(black_box(&source)).count());
+            runs += 1;
+        }
+        let per_run = start.elapsed() / runs;
+
+        println!(
+            "{:>9} bytes: {per_run:>12.3?} per run, {:>7.1} MB/s",
+            source.len(),
+            source.len() as f64 / per_run.as_secs_f64() / 1e6,
+        );
+    }
+}
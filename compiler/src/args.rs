@@ -0,0 +1,328 @@
+use std::{fmt::Display, path::PathBuf};
+
+use anyhow::{Context, anyhow, bail};
+
+pub const USAGE: &str = "\
+usage: patina <command> [options] [files...]
+       patina fmt [--check] [files...]
+       patina --explain <code>
+       patina lsp
+
+commands:
+    lex      print the tokens of each file
+    parse    print the syntax tree of each file
+    check    type check each file
+    run      type check each file and run it (no execution backend yet)
+    fmt      rewrite each file in the canonical layout, or print it when read from stdin
+    lsp      start a language server speaking over stdin and stdout
+
+options:
+    --emit <stages>          also print these comma separated stages: tokens, ast, types
+    --error-format <format>  print diagnostics as `human` readable text (the default)
+                             or as `json`, one object per line
+    --explain <code>         describe the diagnostic with this code, like `E0202`
+    --check                  with `fmt`, list the files that aren't formatted instead of
+                             rewriting them, failing if there are any
+    -h, --help               print this message
+
+Files are read from stdin when none are given, or when a file is `-`.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Lex,
+    Parse,
+    Check,
+    Run,
+}
+
+impl Command {
+    /// The last stage this command needs to run.
+    const fn last_stage(self) -> Stage {
+        match self {
+            Self::Lex => Stage::Tokens,
+            Self::Parse => Stage::Ast,
+            Self::Check | Self::Run => Stage::Types,
+        }
+    }
+
+    /// The stages printed when no `--emit` option is given.
+    fn default_emit(self) -> Vec<Stage> {
+        match self {
+            Self::Lex => vec![Stage::Tokens],
+            Self::Parse => vec![Stage::Ast],
+            Self::Check | Self::Run => Vec::new(),
+        }
+    }
+}
+
+/// An intermediate result of the front end that can be printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Tokens,
+    Ast,
+    Types,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Ast => "ast",
+            Self::Types => "types",
+        }
+        .fmt(f)
+    }
+}
+
+impl TryFrom<&str> for Stage {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "tokens" => Self::Tokens,
+            "ast" => Self::Ast,
+            "types" => Self::Types,
+            other => bail!("unknown stage `{other}`, expected one of: tokens, ast, types"),
+        })
+    }
+}
+
+/// How diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl TryFrom<&str> for ErrorFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "human" => Self::Human,
+            "json" => Self::Json,
+            other => bail!("unknown error format `{other}`, expected `human` or `json`"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Stdin => "<stdin>".into(),
+            Self::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+/// What the command line asks for.
+#[derive(Debug)]
+pub enum Action {
+    Help,
+    /// Describe the diagnostic with this code.
+    Explain(String),
+    /// Run the language server.
+    Lsp,
+    /// Format the inputs, or only check whether they are formatted.
+    Format {
+        check: bool,
+        inputs: Vec<Input>,
+    },
+    Compile(Args),
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub emit: Vec<Stage>,
+    pub error_format: ErrorFormat,
+    pub inputs: Vec<Input>,
+}
+
+impl Args {
+    /// Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Action> {
+        let mut args = args.into_iter();
+
+        let command = match args.next().as_deref() {
+            Some("lex") => Command::Lex,
+            Some("parse") => Command::Parse,
+            Some("check") => Command::Check,
+            Some("run") => Command::Run,
+            Some("help" | "-h" | "--help") => return Ok(Action::Help),
+            Some("lsp") => return Ok(Action::Lsp),
+            Some("fmt") => return parse_format(args),
+            Some("--explain") => {
+                let code = args.next().context("`--explain` needs a diagnostic code")?;
+                return Ok(Action::Explain(code));
+            }
+            Some(other) if other.starts_with("--explain=") => {
+                return Ok(Action::Explain(other["--explain=".len()..].into()));
+            }
+            Some(other) => bail!("unknown command `{other}`"),
+            None => bail!("missing command"),
+        };
+
+        let mut emit = None;
+        let mut error_format = ErrorFormat::Human;
+        let mut inputs = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Action::Help),
+                "--emit" => {
+                    let stages = args.next().context("`--emit` needs a list of stages")?;
+                    emit = Some(parse_stages(&stages)?);
+                }
+                "--error-format" => {
+                    let format = args.next().context("`--error-format` needs a format")?;
+                    error_format = ErrorFormat::try_from(format.as_str())?;
+                }
+                "-" => inputs.push(Input::Stdin),
+                _ => {
+                    if let Some(stages) = arg.strip_prefix("--emit=") {
+                        emit = Some(parse_stages(stages)?);
+                    } else if let Some(format) = arg.strip_prefix("--error-format=") {
+                        error_format = ErrorFormat::try_from(format)?;
+                    } else if arg.starts_with('-') {
+                        bail!("unknown option `{arg}`");
+                    } else {
+                        inputs.push(Input::File(arg.into()));
+                    }
+                }
+            }
+        }
+
+        let emit = emit.unwrap_or_else(|| command.default_emit());
+        if let Some(stage) = emit.iter().find(|stage| **stage > command.last_stage()) {
+            return Err(anyhow!(
+                "`{stage}` can't be emitted by this command, it stops before that stage"
+            ));
+        }
+
+        if inputs.is_empty() {
+            inputs.push(Input::Stdin);
+        }
+
+        Ok(Action::Compile(Self {
+            command,
+            emit,
+            error_format,
+            inputs,
+        }))
+    }
+}
+
+/// Parses the arguments following `fmt`.
+fn parse_format(args: impl Iterator<Item = String>) -> anyhow::Result<Action> {
+    let mut check = false;
+    let mut inputs = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "--check" => check = true,
+            "-" => inputs.push(Input::Stdin),
+            _ if arg.starts_with('-') => bail!("unknown option `{arg}`"),
+            _ => inputs.push(Input::File(arg.into())),
+        }
+    }
+
+    if inputs.is_empty() {
+        inputs.push(Input::Stdin);
+    }
+
+    Ok(Action::Format { check, inputs })
+}
+
+fn parse_stages(stages: &str) -> anyhow::Result<Vec<Stage>> {
+    stages.split(',').map(Stage::try_from).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::ExitCode;
+
+    use super::{Action, Args, Command, ErrorFormat, Input, Stage};
+
+    fn parse(args: &[&str]) -> anyhow::Result<Action> {
+        Args::parse(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    fn compile(args: &[&str]) -> Args {
+        match parse(args) {
+            Ok(Action::Compile(args)) => args,
+            other => panic!("{args:?} parsed as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn inputs() {
+        // stdin is read when no files are given, or when a file is `-`
+        assert_eq!(compile(&["check"]).inputs, [Input::Stdin]);
+        assert_eq!(compile(&["check", "-"]).inputs, [Input::Stdin]);
+
+        let args = compile(&["parse", "a.ptn", "-", "b.ptn"]);
+        assert_eq!(args.command, Command::Parse);
+        assert_eq!(
+            args.inputs,
+            [
+                Input::File("a.ptn".into()),
+                Input::Stdin,
+                Input::File("b.ptn".into()),
+            ]
+        );
+
+        let Ok(Action::Format { check, inputs }) = parse(&["fmt", "--check", "a.ptn", "-"]) else {
+            panic!("`fmt` didn't parse");
+        };
+        assert!(check);
+        assert_eq!(inputs, [Input::File("a.ptn".into()), Input::Stdin]);
+    }
+
+    #[test]
+    fn options() {
+        let args = compile(&["lex", "a.ptn"]);
+        assert_eq!(args.emit, [Stage::Tokens]);
+        assert_eq!(args.error_format, ErrorFormat::Human);
+
+        let args = compile(&["check", "--emit", "ast,types", "--error-format=json"]);
+        assert_eq!(args.emit, [Stage::Ast, Stage::Types]);
+        assert_eq!(args.error_format, ErrorFormat::Json);
+        assert!(matches!(parse(&["check", "-h"]), Ok(Action::Help)));
+        assert!(matches!(
+            parse(&["--explain=E0001"]),
+            Ok(Action::Explain(code)) if code == "E0001"
+        ));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        for (args, message) in [
+            (&["check", "--verbose"][..], "unknown option `--verbose`"),
+            (&["fmt", "-x"], "unknown option `-x`"),
+            (&["build"], "unknown command `build`"),
+            (&[], "missing command"),
+            (&["check", "--emit"], "`--emit` needs a list of stages"),
+            (
+                &["check", "--emit=hir"],
+                "unknown stage `hir`, expected one of: tokens, ast, types",
+            ),
+            (
+                &["lex", "--emit=ast"],
+                "`ast` can't be emitted by this command, it stops before that stage",
+            ),
+        ] {
+            let err = parse(args).expect_err(message);
+            assert_eq!(err.to_string(), message);
+            // invalid arguments are a usage error
+            assert_eq!(crate::usage_error(&err), ExitCode::from(2));
+        }
+    }
+}
@@ -0,0 +1,697 @@
+/// A longer description of one kind of diagnostic, with examples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub text: &'static str,
+}
+
+/// Every diagnostic code, in order. Codes starting with `E00` come from the lexer,
+/// `E01` from the parser and `E02` from the type checker.
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0001",
+        title: "invalid token",
+        text: "\
+The source contains characters that don't form any token.
+
+Erroneous code example:
+
+    const price: Int = $5
+
+`$` has no meaning in Patina. Remove it, or if it is part of a string, put the
+string in double quotes.
+",
+    },
+    Explanation {
+        code: "E0002",
+        title: "invalid escape",
+        text: "\
+A `\\` in a string or char literal starts an escape, but the characters after it
+don't form one.
+
+Erroneous code example:
+
+    const path: Str = \"C:\\files\"
+
+`\\f` is not an escape. To put a backslash in a literal, escape it too:
+
+    const path: Str = \"C:\\\\files\"
+
+The escapes are `\\n` (line feed), `\\r` (carriage return), `\\t` (tab), `\\0` (null),
+`\\\\`, `\\'`, `\\\"`, and `\\{` and `\\}` for braces that don't start an interpolation,
+along with `\\x` followed by two hex digits up to `\\x7F`, and `\\u` followed by the hex
+digits of any Unicode scalar value in braces, like `\\u{1F600}`.
+",
+    },
+    Explanation {
+        code: "E0003",
+        title: "malformed multi-line string",
+        text: "\
+The text of a multi-line string goes on the lines between its opening and closing
+`\"\"\"`, each of which must be on a line with no other text. The indentation of the
+closing `\"\"\"` is removed from every line, so none of them can be indented less.
+
+Erroneous code example:
+
+    const query: Str = \"\"\"SELECT *
+        FROM users
+        \"\"\"
+
+Start the text on the line after the opening delimiter:
+
+    const query: Str = \"\"\"
+        SELECT *
+        FROM users
+        \"\"\"
+
+This is the string \"SELECT *\\nFROM users\".
+",
+    },
+    Explanation {
+        code: "E0004",
+        title: "invalid number literal",
+        text: "\
+A number literal has a digit that its base doesn't allow, a base prefix with no
+digits after it, or a suffix that isn't a type.
+
+Erroneous code example:
+
+    const mask: UInt = 0b1021
+
+Binary numbers, which start with `0b`, can only have the digits `0` and `1`. Octal
+numbers start with `0o` and hexadecimal ones with `0x`:
+
+    const mask: UInt = 0b1011
+
+Any number can have `_` between its digits to make it easier to read, like
+`1_000_000`, and end with a suffix that gives its type: `i8`, `i16`, `i32`, `i64`,
+`u8`, `u16`, `u32`, `u64`, `f32` or `f64`, as in `255u8`.
+",
+    },
+    Explanation {
+        code: "E0005",
+        title: "literal out of range",
+        text: "\
+A number literal is too large for its type. Without a suffix, an integer can be
+at most `18446744073709551615` and a float must be finite.
+
+Erroneous code example:
+
+    const level: U8 = 256u8
+
+`U8` ranges from 0 to 255. Use a wider type:
+
+    const level: U16 = 256u16
+
+A negative literal can reach the minimum of a signed type, so `-128i8` is valid
+even though `128i8` isn't.
+
+An integer without a suffix takes its type from how it is used, and has to fit
+in that type too: `const level: U8 = 256` is out of range just the same.
+",
+    },
+    Explanation {
+        code: "E0006",
+        title: "unterminated literal or comment",
+        text: "\
+A string, char literal or block comment is missing its closing delimiter.
+
+Erroneous code example:
+
+    const greeting: Str = \"hello
+
+Close the string with a `\"`:
+
+    const greeting: Str = \"hello\"
+
+To find where the rest of the code starts, a `\"` string or the text after an
+interpolation is taken to end at the end of its line, and a char literal after its
+first character. Raw strings, multi-line strings and block comments run to the end
+of the file.
+",
+    },
+    Explanation {
+        code: "E0007",
+        title: "invalid char literal",
+        text: "\
+A char literal must contain exactly one character, or one escape standing for a
+character.
+
+Erroneous code example:
+
+    const separator: Char = ''
+
+Put the character in the quotes:
+
+    const separator: Char = ','
+
+Text of any other length is written as a string, in double quotes:
+
+    const separator: Str = \", \"
+",
+    },
+    Explanation {
+        code: "E0008",
+        title: "confusable character",
+        text: "\
+A character in the source looks like an ASCII one but isn't, such as a Cyrillic
+`\u{0430}` that looks like a Latin `a`. This is a warning: an identifier with one in
+it is valid, but a different name from the one it looks like, and most other
+places don't accept such a character at all.
+
+Erroneous code example:
+
+    const \u{0440}\u{0430}th: Str = \"/tmp\"
+
+Type the ASCII character instead:
+
+    const path: Str = \"/tmp\"
+",
+    },
+    Explanation {
+        code: "E0101",
+        title: "unexpected token",
+        text: "\
+A specific token, such as a delimiter or keyword, was required but a different one
+was found.
+
+Erroneous code example:
+
+    fn sum(a, b; c) -> a
+
+Parameters are separated by commas, so after `b` only `,` or `)` can follow:
+
+    fn sum(a, b, c) -> a
+
+The diagnostic lists every token that would have been accepted.
+",
+    },
+    Explanation {
+        code: "E0102",
+        title: "expected a different construct",
+        text: "\
+A token appeared where it can't start or continue the construct being parsed, most
+often an expression.
+
+Erroneous code example:
+
+    fn f(a: Int) -> { let x = a + ; x }
+
+The `+` operator needs a right hand side:
+
+    fn f(a: Int) -> { let x = a + 1; x }
+",
+    },
+    Explanation {
+        code: "E0103",
+        title: "unexpected end of input",
+        text: "\
+The file ended in the middle of an item.
+
+Erroneous code example:
+
+    fn f(a: Int) -> {
+        a + 1
+
+The block is never closed. Add the missing `}`:
+
+    fn f(a: Int) -> {
+        a + 1
+    }
+",
+    },
+    Explanation {
+        code: "E0201",
+        title: "unbound identifier",
+        text: "\
+A name was used that isn't defined anywhere in scope.
+
+Erroneous code example:
+
+    fn f() -> y + 1
+
+Bindings are visible from the item or `let` that defines them to the end of the
+enclosing block. Check the name for typos, or define it first:
+
+    fn f() -> {
+        let y = 2;
+        y + 1
+    }
+",
+    },
+    Explanation {
+        code: "E0202",
+        title: "mismatched types",
+        text: "\
+A value has a different type than the one its context requires.
+
+Erroneous code example:
+
+    fn f(a: Int): Bool -> a
+
+The return type says `f` produces a `Bool`, but its body is the `Int` parameter.
+Either change the annotation or the body:
+
+    fn f(a: Int): Bool -> a > 0
+
+When the expected type comes from an annotation, the diagnostic points at it too.
+",
+    },
+    Explanation {
+        code: "E0203",
+        title: "wrong number of arguments",
+        text: "\
+A function was called with more or fewer arguments than it has parameters.
+
+Erroneous code example:
+
+    fn add(a: Int, b: Int) -> a + b
+    const three: Int = add(1)
+
+Pass exactly one argument for each parameter:
+
+    const three: Int = add(1, 2)
+",
+    },
+    Explanation {
+        code: "E0204",
+        title: "not a numeric type",
+        text: "\
+An arithmetic operator was applied to values that aren't numbers.
+
+Erroneous code example:
+
+    const x: Bool = true + false
+
+`+`, `-`, `*`, `/` and `**` work on `Int`, `UInt` and `Float`, with both operands of
+the same type. Use `&&`, `||` and `^` to combine `Bool`s instead.
+",
+    },
+    Explanation {
+        code: "E0205",
+        title: "not an integer type",
+        text: "\
+A bitwise operator was applied to values that aren't integers.
+
+Erroneous code example:
+
+    const x: Float = 1.5 & 2.5
+
+`&` and `|` only work on `Int` and `UInt`.
+",
+    },
+    Explanation {
+        code: "E0206",
+        title: "value can't be negated",
+        text: "\
+Unary `-` was applied to a value of an unsigned or non-numeric type.
+
+Erroneous code example:
+
+    fn f(a: UInt) -> -a
+
+A `UInt` can't hold negative values. Convert it to an `Int` first, or subtract it
+from zero if that is what was meant.
+",
+    },
+    Explanation {
+        code: "E0207",
+        title: "value is not a function",
+        text: "\
+Something that isn't a function was called.
+
+Erroneous code example:
+
+    const one: Int = 1
+    const two: Int = one(1)
+
+Only functions and lambdas can be called.
+",
+    },
+    Explanation {
+        code: "E0208",
+        title: "no such field",
+        text: "\
+A field was accessed on a value whose type doesn't have it.
+
+Erroneous code example:
+
+    struct Point { x: Int, y: Int }
+    fn f(p: Point) -> p.z
+
+Check the spelling of the field against the struct definition. Values that
+aren't structs have no fields at all.
+",
+    },
+    Explanation {
+        code: "E0209",
+        title: "type can't be inferred",
+        text: "\
+The type of an expression must be known at this point, but nothing determines it
+yet.
+
+Erroneous code example:
+
+    fn f(p) -> p.x
+
+To look up a field, the type checker must already know which struct `p` is.
+Annotate the parameter:
+
+    fn f(p: Point) -> p.x
+",
+    },
+    Explanation {
+        code: "E0210",
+        title: "assignment to an immutable binding",
+        text: "\
+A binding was assigned to after its definition without being declared mutable.
+
+Erroneous code example:
+
+    fn f() -> {
+        let x = 1;
+        x = 2
+    }
+
+Declare the binding with `let mut` to allow assignments:
+
+    fn f() -> {
+        let mut x = 1;
+        x = 2
+    }
+",
+    },
+    Explanation {
+        code: "E0211",
+        title: "infinite type",
+        text: "\
+Inferring a type would require it to contain itself.
+
+Erroneous code example:
+
+    fn f(x) -> x(x)
+
+`x` would have to be a function taking itself as an argument, which has no finite
+type. This usually points to a mistake in how the values are combined.
+",
+    },
+    Explanation {
+        code: "E0212",
+        title: "value can't be embedded in a string",
+        text: "\
+An expression embedded in an interpolated string has a type with no textual form.
+
+Erroneous code example:
+
+    fn f(xs: [Int]) -> \"the values are {xs}\"
+
+Numbers, `Bool`, `Char` and `Str` values can be embedded. Build the text for
+anything else by embedding its parts:
+
+    fn f(xs: [Int]) -> \"the first value is {xs[0]}\"
+",
+    },
+    Explanation {
+        code: "E0213",
+        title: "`_` outside of a call",
+        text: "\
+`_` was used somewhere other than as an argument of a call.
+
+Erroneous code example:
+
+    const inc: fn(Int): Int = _ + 1
+
+A call with `_` in place of some of its arguments is a function of the arguments
+left out, so `sum(1, _)` is the same as `fn(x) -> sum(1, x)`. Anywhere else, write
+the lambda out:
+
+    const inc: fn(Int): Int = fn(x) -> x + 1
+",
+    },
+    Explanation {
+        code: "E0214",
+        title: "unknown struct or enum",
+        text: "\
+A pattern names a struct or enum that isn't defined.
+
+Erroneous code example:
+
+    fn f(s) -> match s { Size::Big => 1, _ => 0 }
+
+Check the spelling of the name, or define the type:
+
+    enum Size { Big, Small }
+    fn f(s) -> match s { Size::Big => 1, _ => 0 }
+",
+    },
+    Explanation {
+        code: "E0215",
+        title: "no such variant",
+        text: "\
+A pattern names a variant that its enum doesn't have.
+
+Erroneous code example:
+
+    enum Size { Big, Small }
+    fn f(s) -> match s { Size::Huge => 1, _ => 0 }
+
+Use one of the variants of the enum:
+
+    enum Size { Big, Small }
+    fn f(s) -> match s { Size::Big => 1, _ => 0 }
+",
+    },
+    Explanation {
+        code: "E0216",
+        title: "wrong number of fields in a pattern",
+        text: "\
+A variant pattern has a different number of fields than the variant.
+
+Erroneous code example:
+
+    enum Shape { Circle(Float), Empty }
+    fn f(s) -> match s { Shape::Circle => 1, _ => 0 }
+
+Match every field of the variant, with `_` for the ones that don't matter:
+
+    enum Shape { Circle(Float), Empty }
+    fn f(s) -> match s { Shape::Circle(_) => 1, _ => 0 }
+",
+    },
+    Explanation {
+        code: "E0217",
+        title: "named fields without braces",
+        text: "\
+A struct, or a variant with named fields, is matched or used as a value as if
+its fields were unnamed.
+
+Erroneous code example:
+
+    struct Point { x: Int, y: Int }
+    fn f(p: Point) -> match p { Point(x, y) => x + y }
+
+Match the fields by name between braces:
+
+    struct Point { x: Int, y: Int }
+    fn f(p: Point) -> match p { Point { x, y } => x + y }
+",
+    },
+    Explanation {
+        code: "E0218",
+        title: "fields missing from a pattern",
+        text: "\
+A struct pattern doesn't mention every field of the struct.
+
+Erroneous code example:
+
+    struct Point { x: Int, y: Int }
+    fn f(p: Point) -> match p { Point { x } => x }
+
+Match the missing fields, or end the pattern with `..` to ignore them:
+
+    struct Point { x: Int, y: Int }
+    fn f(p: Point) -> match p { Point { x, .. } => x }
+",
+    },
+    Explanation {
+        code: "E0219",
+        title: "alternatives bind different names",
+        text: "\
+A name is bound by some alternatives of an or-pattern but not by the others.
+
+Erroneous code example:
+
+    fn f(p: (Int, Int)) -> match p { (x, 0) | (0, y) => 1, _ => 0 }
+
+Whichever alternative matches, the body of the arm can use the names bound by
+the pattern, so every alternative has to bind the same names:
+
+    fn f(p: (Int, Int)) -> match p { (x, 0) | (0, x) => x, _ => 0 }
+",
+    },
+    Explanation {
+        code: "E0220",
+        title: "name bound twice in a pattern",
+        text: "\
+A pattern binds the same name more than once.
+
+Erroneous code example:
+
+    fn f(p: (Int, Int)) -> match p { (x, x) => x }
+
+Give each binding its own name, and compare them in a guard if they are meant to
+be equal:
+
+    fn f(p: (Int, Int)) -> match p { (x, y) if x == y => x, _ => 0 }
+",
+    },
+    Explanation {
+        code: "E0221",
+        title: "misplaced `..` in a pattern",
+        text: "\
+`..` was used outside of an array pattern, or more than once in one.
+
+Erroneous code example:
+
+    fn f(p: (Int, Int)) -> match p { (0, ..) => 1, _ => 0 }
+
+In an array pattern, `..` stands for the elements that the other patterns don't
+match. Elsewhere, match each part with `_`:
+
+    fn f(p: (Int, Int)) -> match p { (0, _) => 1, _ => 0 }
+",
+    },
+    Explanation {
+        code: "E0222",
+        title: "non-exhaustive patterns",
+        text: "\
+A `match` has no arm for some of the values it could be given. The error lists
+patterns for the values left out.
+
+Erroneous code example:
+
+    enum Size { Big, Small }
+    fn f(s: Size) -> match s { Size::Big => 1 }
+
+Add arms for the values left out, or a `_` arm to match all of them. Arms with a
+guard don't count, as the guard may be false:
+
+    enum Size { Big, Small }
+    fn f(s: Size) -> match s { Size::Big => 1, Size::Small => 0 }
+",
+    },
+    Explanation {
+        code: "E0223",
+        title: "empty range pattern",
+        text: "\
+A range pattern starts after it ends, so it matches no values. A range written
+with `..` leaves out its end, so `1..1` is empty too.
+
+Erroneous code example:
+
+    fn f(n: Int) -> match n { 5..=1 => 0, _ => 1 }
+
+Write the smaller bound first:
+
+    fn f(n: Int) -> match n { 1..=5 => 0, _ => 1 }
+",
+    },
+    Explanation {
+        code: "E0224",
+        title: "refutable pattern in a binding",
+        text: "\
+A pattern in a `let` or a parameter list doesn't match some values of its type.
+There is nothing to fall back on for those values, so such a pattern has to
+match every value. The error lists patterns for the values left out.
+
+Erroneous code example:
+
+    enum Size { Big, Small }
+    fn f(s: Size) -> { let Size::Big = s; 1 }
+
+Use a `match` to handle the other values:
+
+    enum Size { Big, Small }
+    fn f(s: Size) -> match s { Size::Big => 1, Size::Small => 0 }
+",
+    },
+    Explanation {
+        code: "E0225",
+        title: "fields missing from a struct literal",
+        text: "\
+A struct literal doesn't give a value for every field of the struct.
+
+Erroneous code example:
+
+    struct Point { x: Int, y: Int }
+    fn f(x: Int) -> Point { x }
+
+Give the missing fields a value, or take them from another value of the struct
+with `..` after the others:
+
+    struct Point { x: Int, y: Int }
+    fn f(x: Int, p: Point) -> Point { x, ..p }
+",
+    },
+    Explanation {
+        code: "E0226",
+        title: "field given more than once",
+        text: "\
+A struct literal gives the same field a value more than once.
+
+Erroneous code example:
+
+    struct Point { x: Int, y: Int }
+    fn f() -> Point { x: 1, y: 2, x: 3 }
+
+Give each field a single value:
+
+    struct Point { x: Int, y: Int }
+    fn f() -> Point { x: 3, y: 2 }
+",
+    },
+    Explanation {
+        code: "E0227",
+        title: "enum variant with fields from another value",
+        text: "\
+A literal of an enum variant takes the fields it leaves out from another value
+with `..`. That value could be any variant of the enum, which may not have those
+fields, so only struct literals can do this.
+
+Erroneous code example:
+
+    enum Shape { Rect { w: Float, h: Float }, Empty }
+    fn f(s: Shape) -> Shape::Rect { w: 1.0, ..s }
+
+Give every field of the variant a value:
+
+    enum Shape { Rect { w: Float, h: Float }, Empty }
+    fn f(h: Float) -> Shape::Rect { w: 1.0, h }
+",
+    },
+    Explanation {
+        code: "E0228",
+        title: "unreachable match arm",
+        text: "\
+A `match` arm can never run, because the arms before it match every value its
+pattern does. This is a warning, as the code still works, but usually the arms
+are in the wrong order or the pattern isn't the one meant.
+
+Erroneous code example:
+
+    fn sign(n: Int) -> match n { _ => 1, 0 => 0 }
+
+Put the more specific arm first:
+
+    fn sign(n: Int) -> match n { 0 => 0, _ => 1 }
+",
+    },
+];
+
+/// Looks up the explanation for `code`, ignoring case.
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
@@ -0,0 +1,65 @@
+use serde_json::{Value, json};
+
+use crate::helpers::Span;
+
+use super::{Diagnostic, Edit, Fix, Label, SourceMap};
+
+impl Diagnostic {
+    /// Converts the diagnostic into a JSON object for other tools to consume, resolving
+    /// its spans against `map`.
+    ///
+    /// Spans are objects holding the byte offsets `start` and `end` along with the
+    /// `line` and `column` of each, counted the same way as [`super::Location`].
+    pub fn to_json(&self, map: &SourceMap<'_>) -> Value {
+        json!({
+            "severity": self.severity.to_string(),
+            "code": self.code,
+            "message": self.message,
+            "file": map.name(),
+            "span": self.span().map(|span| span_json(span, map)),
+            "labels": self.labels.iter().map(|label| label_json(label, map)).collect::<Vec<_>>(),
+            "notes": self.notes,
+            "fixes": self.fixes.iter().map(|fix| fix_json(fix, map)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn span_json(span: Span, map: &SourceMap<'_>) -> Value {
+    let start = map.location(span.start);
+    let end = map.location(span.end);
+
+    json!({
+        "start": span.start,
+        "end": span.end,
+        "line": start.line,
+        "column": start.column,
+        "end_line": end.line,
+        "end_column": end.column,
+    })
+}
+
+fn label_json(label: &Label, map: &SourceMap<'_>) -> Value {
+    json!({
+        "span": span_json(label.span, map),
+        "message": label.message,
+        "primary": label.primary,
+    })
+}
+
+fn fix_json(fix: &Fix, map: &SourceMap<'_>) -> Value {
+    let edits: Vec<_> = fix
+        .edits
+        .iter()
+        .map(|Edit { span, replacement }| {
+            json!({
+                "span": span_json(*span, map),
+                "replacement": replacement,
+            })
+        })
+        .collect();
+
+    json!({
+        "message": fix.message,
+        "edits": edits,
+    })
+}
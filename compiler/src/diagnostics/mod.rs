@@ -0,0 +1,388 @@
+mod codes;
+mod json;
+mod render;
+mod source_map;
+#[cfg(test)]
+mod test;
+
+use std::fmt::Display;
+
+use crate::{
+    Error,
+    helpers::Span,
+    lexer::{ConfusableS, LexError, LiteralError},
+    parser::{ParseError, ParseErrorKind, describe_all},
+    typecheck::{TypeError, TypeErrorS, TypeWarning, TypeWarningS},
+};
+
+pub use codes::{EXPLANATIONS, Explanation, explain};
+pub use source_map::{Location, SourceMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+        }
+        .fmt(f)
+    }
+}
+
+/// A span of source code pointed at by a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// Primary labels mark where the problem is, secondary ones add context.
+    pub primary: bool,
+}
+
+/// A change to the source that would resolve a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub message: String,
+    /// Replacements to make, none of which overlap.
+    pub edits: Vec<Edit>,
+}
+
+/// Replaces the text at `span` with `replacement`. An empty span inserts it instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Span,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The stable code identifying the kind of problem, like `E0001`.
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    #[must_use]
+    pub const fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    #[must_use]
+    pub fn with_label(mut self, span: impl Into<Span>, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span: span.into(),
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn with_secondary(mut self, span: impl Into<Span>, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span: span.into(),
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds a fix that replaces the text at `span` with `replacement`.
+    #[must_use]
+    pub fn with_fix(
+        mut self,
+        message: impl Into<String>,
+        span: impl Into<Span>,
+        replacement: impl Into<String>,
+    ) -> Self {
+        self.fixes.push(Fix {
+            message: message.into(),
+            edits: vec![Edit {
+                span: span.into(),
+                replacement: replacement.into(),
+            }],
+        });
+        self
+    }
+
+    /// The span the diagnostic is reported at: its first primary label, or its first label.
+    pub fn span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .or_else(|| self.labels.first())
+            .map(|label| label.span)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(value: &ParseError) -> Self {
+        let message = value.context_description().map_or_else(
+            || value.kind.to_string(),
+            |context| format!("{} {context}", value.kind),
+        );
+
+        let label = match &value.kind {
+            ParseErrorKind::Mismatched { expected, .. } => {
+                format!("expected {}", describe_all(expected))
+            }
+            ParseErrorKind::Unexpected { expected, .. } => format!("expected {expected}"),
+            ParseErrorKind::Missing => "more input was expected".into(),
+            ParseErrorKind::InvalidLiteral(err) if err.is_escape() => "invalid escape".into(),
+            ParseErrorKind::InvalidLiteral(LiteralError::TextAfterOpening) => {
+                "expected a line break".into()
+            }
+            ParseErrorKind::InvalidLiteral(LiteralError::TextBeforeClosing) => {
+                "expected only indentation before `\"\"\"`".into()
+            }
+            ParseErrorKind::InvalidLiteral(LiteralError::Underindented) => {
+                "not indented enough".into()
+            }
+            ParseErrorKind::InvalidLiteral(LiteralError::InvalidDigit { radix, .. }) => {
+                format!("not a base {radix} digit")
+            }
+            ParseErrorKind::InvalidLiteral(LiteralError::NoDigits) => "expected digits".into(),
+            ParseErrorKind::InvalidLiteral(LiteralError::InvalidSuffix) => "unknown suffix".into(),
+            ParseErrorKind::InvalidLiteral(LiteralError::OutOfRange(suffix)) => {
+                format!("doesn't fit in `{}`", suffix.type_name())
+            }
+            ParseErrorKind::InvalidLiteral(_) => unreachable!("escapes are handled above"),
+            ParseErrorKind::InvalidToken(
+                LexError::UnterminatedString
+                | LexError::UnterminatedChar
+                | LexError::UnterminatedComment,
+            ) => "never closed".into(),
+            ParseErrorKind::InvalidToken(LexError::EmptyChar) => "expected a character".into(),
+            ParseErrorKind::InvalidToken(LexError::MultipleChars) => {
+                "expected a single character".into()
+            }
+            ParseErrorKind::InvalidToken(LexError::StrayCharacter(_)) => {
+                "not part of any token".into()
+            }
+        };
+
+        let mut diagnostic = Self::error(message)
+            .with_code(value.code())
+            .with_label(value.span, label);
+        let note = match value.kind {
+            ParseErrorKind::InvalidLiteral(LiteralError::Unknown(_)) => Some(
+                r#"the valid escapes are `\n`, `\r`, `\t`, `\0`, `\\`, `\'`, `\"`, `\{`, `\}`, `\x7F` and `\u{1F600}`"#.into(),
+            ),
+            ParseErrorKind::InvalidLiteral(LiteralError::InvalidSuffix) => Some(
+                "the suffixes are `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32` and `f64`".into(),
+            ),
+            ParseErrorKind::InvalidLiteral(LiteralError::OutOfRange(suffix)) => {
+                let (min, max) = suffix.range();
+                Some(format!("`{}` ranges from {min} to {max}", suffix.type_name()))
+            }
+            ParseErrorKind::InvalidToken(LexError::MultipleChars) => {
+                Some("strings are written in double quotes".into())
+            }
+            _ => None,
+        };
+        if let Some(note) = note {
+            diagnostic = diagnostic.with_note(note);
+        }
+
+        // a single missing piece of punctuation or keyword can be put in for the user
+        if let ParseErrorKind::Mismatched { expected, .. } = &value.kind
+            && let Some((at, text)) = value.insertion()
+        {
+            diagnostic.with_fix(format!("insert `{}`", expected[0]), at..at, text)
+        } else {
+            diagnostic
+        }
+    }
+}
+
+impl From<&ConfusableS> for Diagnostic {
+    fn from(value: &ConfusableS) -> Self {
+        let looks_like = value.inner.looks_like;
+        Self::warning(value.inner.to_string())
+            .with_code(value.inner.code())
+            .with_label(value.span, format!("this is not `{looks_like}`"))
+            .with_fix(
+                format!("replace it with `{looks_like}`"),
+                value.span,
+                looks_like.to_string(),
+            )
+    }
+}
+
+impl From<&TypeErrorS> for Diagnostic {
+    #[allow(
+        clippy::too_many_lines,
+        reason = "one short arm for each kind of error"
+    )]
+    fn from(value: &TypeErrorS) -> Self {
+        let span = value.span;
+
+        let diagnostic = match &value.inner {
+            TypeError::MismatchedTypes {
+                expected,
+                found,
+                annotation,
+            } => {
+                let diagnostic = Self::error("mismatched types")
+                    .with_label(span, format!("expected `{expected}`, found `{found}`"));
+
+                match annotation {
+                    Some(annotation) => diagnostic
+                        .with_secondary(*annotation, "expected because of this annotation"),
+                    None => diagnostic,
+                }
+            }
+            TypeError::UnboundIdent(_) => {
+                Self::error(value.inner.to_string()).with_label(span, "not found in this scope")
+            }
+            TypeError::WrongArgCount { needed, provided } => {
+                Self::error("wrong number of arguments").with_label(
+                    span,
+                    format!(
+                        "expected {needed} argument{}, found {provided}",
+                        if *needed == 1 { "" } else { "s" }
+                    ),
+                )
+            }
+            TypeError::Mutation(name) => Self::error(value.inner.to_string())
+                .with_label(span, "cannot assign twice")
+                .with_note(format!(
+                    "declare the binding as `let mut {name}` to allow this"
+                )),
+            TypeError::CantInfer => Self::error(value.inner.to_string())
+                .with_label(span, "type must be known here")
+                .with_note("consider adding a type annotation"),
+            TypeError::MisplacedPlaceholder => Self::error(value.inner.to_string())
+                .with_label(span, "not an argument of a call")
+                .with_note(
+                    "write a lambda to use a parameter anywhere else, as in `fn(x) -> x + 1`",
+                ),
+            TypeError::UnknownType(_) => {
+                Self::error(value.inner.to_string()).with_label(span, "not defined")
+            }
+            TypeError::PatternArity {
+                expected, found, ..
+            } => Self::error(value.inner.to_string()).with_label(
+                span,
+                format!(
+                    "expected {expected} field{}, found {found}",
+                    if *expected == 1 { "" } else { "s" }
+                ),
+            ),
+            TypeError::NeedsBraces(path) => Self::error(value.inner.to_string())
+                .with_label(span, "")
+                .with_note(format!("name its fields between braces after `{path}`")),
+            TypeError::MissingFields { .. } => Self::error(value.inner.to_string())
+                .with_label(span, "")
+                .with_note("add `..` to the pattern to ignore the fields left out"),
+            TypeError::OrPatternBindings(name) => Self::error(value.inner.to_string())
+                .with_label(span, format!("this alternative doesn't bind `{name}`")),
+            TypeError::DuplicateBinding(_) => {
+                Self::error(value.inner.to_string()).with_label(span, "bound again here")
+            }
+            TypeError::MisplacedRest => Self::error(value.inner.to_string())
+                .with_label(span, "")
+                .with_note(
+                    "`..` stands for the elements of an array that no other pattern matches",
+                ),
+            TypeError::NonExhaustive { missing } => Self::error(value.inner.to_string())
+                .with_label(span, not_covered(missing))
+                .with_note("add arms for the values left out, or a `_` arm to match all of them"),
+            TypeError::EmptyRange => Self::error(value.inner.to_string())
+                .with_label(span, "")
+                .with_note("the start of a range must come before its end, which `..` leaves out"),
+            TypeError::Refutable { missing } => Self::error(value.inner.to_string())
+                .with_label(span, not_covered(missing))
+                .with_note("use a `match` to handle the values this pattern doesn't match"),
+            TypeError::MissingFieldValues { .. } => Self::error(value.inner.to_string())
+                .with_label(span, "")
+                .with_note("add the missing fields, or take them from another value with `..base`"),
+            TypeError::DuplicateField(_) => {
+                Self::error(value.inner.to_string()).with_label(span, "given again here")
+            }
+            TypeError::VariantUpdate(_) => Self::error(value.inner.to_string())
+                .with_label(span, "")
+                .with_note("other values of the enum may be other variants, without these fields"),
+            TypeError::LiteralOutOfRange(ty) => {
+                let (min, max) = ty.integer_range().expect("literals only overflow integers");
+                Self::error(value.inner.to_string())
+                    .with_label(span, format!("doesn't fit in `{ty}`"))
+                    .with_note(format!("`{ty}` ranges from {min} to {max}"))
+            }
+            TypeError::NotNumeric(_)
+            | TypeError::NotInteger(_)
+            | TypeError::NotSigned(_)
+            | TypeError::NotCallable(_)
+            | TypeError::NotDisplayable(_)
+            | TypeError::NoField { .. }
+            | TypeError::NoVariant { .. }
+            | TypeError::Infinite => Self::error(value.inner.to_string()).with_label(span, ""),
+        };
+
+        diagnostic.with_code(value.inner.code())
+    }
+}
+
+fn not_covered(missing: &[String]) -> String {
+    format!(
+        "pattern{} not covered",
+        if missing.len() == 1 { "" } else { "s" }
+    )
+}
+
+impl From<&TypeWarningS> for Diagnostic {
+    fn from(value: &TypeWarningS) -> Self {
+        match &value.inner {
+            TypeWarning::UnreachableArm => Self::warning(value.inner.to_string())
+                .with_label(value.span, "no value gets to this arm")
+                .with_note("the arms before it match every value this pattern does"),
+        }
+        .with_code(value.inner.code())
+    }
+}
+
+impl Error {
+    /// One diagnostic for each problem this error reports.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Self::Parse(errs) => errs.iter().map(Diagnostic::from).collect(),
+            Self::Type(err) => vec![err.into()],
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt::{Display, Write};
+
+use unicode_width::UnicodeWidthChar;
+
+use super::{Diagnostic, Label, Severity, SourceMap};
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+const TAB_WIDTH: usize = 4;
+
+struct Painter {
+    colored: bool,
+}
+
+impl Painter {
+    fn paint(&self, style: &str, text: impl Display) -> String {
+        if self.colored {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+const fn severity_style(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+        Severity::Note => GREEN,
+    }
+}
+
+const fn label_style(label: &Label, severity: Severity) -> &'static str {
+    if label.primary {
+        severity_style(severity)
+    } else {
+        BLUE
+    }
+}
+
+/// How many columns `text` takes up in a terminal once tabs are expanded, so that wide
+/// characters count twice and combining marks not at all.
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| {
+            if c == '\t' {
+                TAB_WIDTH
+            } else {
+                c.width().unwrap_or(0)
+            }
+        })
+        .sum()
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with a snippet of the source each label points into,
+    /// using ANSI colours if `colored` is set.
+    pub fn render(&self, map: &SourceMap<'_>, colored: bool) -> String {
+        let painter = Painter { colored };
+        let mut out = String::new();
+
+        let severity = self.code.map_or_else(
+            || self.severity.to_string(),
+            |code| format!("{}[{code}]", self.severity),
+        );
+        writeln!(
+            out,
+            "{}{}",
+            painter.paint(severity_style(self.severity), severity),
+            painter.paint(BOLD, format_args!(": {}", self.message))
+        )
+        .unwrap();
+
+        let mut lines: Vec<usize> = self
+            .labels
+            .iter()
+            .map(|label| map.line_index(label.span.start))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+
+        let gutter = lines.last().map_or(0, |last| (last + 1).to_string().len());
+        let pad = " ".repeat(gutter);
+
+        if let Some(span) = self.span() {
+            let location = map.location(span.start);
+            writeln!(
+                out,
+                "{pad}{} {}:{}:{}",
+                painter.paint(BLUE, "-->"),
+                map.name(),
+                location.line,
+                location.column
+            )
+            .unwrap();
+            writeln!(out, "{pad} {}", painter.paint(BLUE, "|")).unwrap();
+        }
+
+        let mut previous = None;
+        for &line in &lines {
+            if previous.is_some_and(|previous| previous + 1 < line) {
+                writeln!(out, "{}", painter.paint(BLUE, "...")).unwrap();
+            }
+            previous = Some(line);
+
+            self.render_line(&mut out, map, line, gutter, &painter);
+        }
+
+        for note in &self.notes {
+            writeln!(
+                out,
+                "{pad} {} {} {note}",
+                painter.paint(BLUE, "="),
+                painter.paint(BOLD, "note:")
+            )
+            .unwrap();
+        }
+        for fix in &self.fixes {
+            writeln!(
+                out,
+                "{pad} {} {} {}",
+                painter.paint(BLUE, "="),
+                painter.paint(BOLD, "help:"),
+                fix.message
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    /// Renders one line of source along with the markers of every label starting on it.
+    fn render_line(
+        &self,
+        out: &mut String,
+        map: &SourceMap<'_>,
+        line: usize,
+        gutter: usize,
+        painter: &Painter,
+    ) {
+        let pad = " ".repeat(gutter);
+        let bar = painter.paint(BLUE, "|");
+        let line_span = map.line_span(line);
+        let text = map.line(line);
+
+        writeln!(
+            out,
+            "{} {bar} {}",
+            painter.paint(BLUE, format_args!("{:>gutter$}", line + 1)),
+            text.replace('\t', &" ".repeat(TAB_WIDTH))
+        )
+        .unwrap();
+
+        let mut labels: Vec<(usize, usize, &Label)> = self
+            .labels
+            .iter()
+            .filter(|label| map.line_index(label.span.start) == line)
+            .map(|label| {
+                let start = label.span.start - line_span.start;
+                let end = label.span.end.clamp(label.span.start, line_span.end) - line_span.start;
+                let column = width(&text[..start]);
+                (column, width(&text[start..end]).max(1), label)
+            })
+            .collect();
+        labels.sort_by_key(|(column, ..)| *column);
+
+        let mut markers = String::new();
+        let mut cursor = 0;
+        for (column, len, label) in &labels {
+            let end = column + len;
+            if end <= cursor {
+                continue;
+            }
+            markers.push_str(&" ".repeat(column.saturating_sub(cursor)));
+
+            let marker = if label.primary { "^" } else { "-" };
+            let len = end - cursor.max(*column);
+            markers.push_str(&painter.paint(label_style(label, self.severity), marker.repeat(len)));
+            cursor = end;
+        }
+
+        let Some(((_, _, last), rest)) = labels.split_last() else {
+            return;
+        };
+
+        write!(out, "{pad} {bar} {markers}").unwrap();
+        if !last.message.is_empty() {
+            write!(
+                out,
+                " {}",
+                painter.paint(label_style(last, self.severity), &last.message)
+            )
+            .unwrap();
+        }
+        out.push('\n');
+
+        for (column, _, label) in rest.iter().rev() {
+            if label.message.is_empty() {
+                continue;
+            }
+            writeln!(
+                out,
+                "{pad} {bar} {}{}",
+                " ".repeat(*column),
+                painter.paint(label_style(label, self.severity), &label.message)
+            )
+            .unwrap();
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{helpers::Span, lexer::BOM};
+
+/// A line and column in a source file, both starting at 1. Columns count chars, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps byte offsets in one source file to line and column numbers.
+pub struct SourceMap<'src> {
+    name: &'src str,
+    source: &'src str,
+    /// The byte offset each line starts at.
+    line_starts: Vec<usize>,
+}
+
+impl<'src> SourceMap<'src> {
+    pub fn new(name: &'src str, source: &'src str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            name,
+            source,
+            line_starts,
+        }
+    }
+
+    pub const fn name(&self) -> &'src str {
+        self.name
+    }
+
+    pub const fn source(&self) -> &'src str {
+        self.source
+    }
+
+    pub const fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The index (starting at 0) of the line containing `offset`.
+    pub fn line_index(&self, offset: usize) -> usize {
+        self.line_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1)
+    }
+
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.source.len());
+        let line = self.line_index(offset);
+        // editors don't show a byte order mark, so it doesn't take up a column
+        let line_start = if line == 0 && self.source.starts_with(BOM) {
+            BOM.len_utf8().min(offset)
+        } else {
+            self.line_starts[line]
+        };
+        let column = self.source[line_start..offset].chars().count() + 1;
+
+        Location {
+            line: line + 1,
+            column,
+        }
+    }
+
+    /// The byte offsets covered by the line at `index`, not including its line terminator.
+    pub fn line_span(&self, index: usize) -> Span {
+        let start = self.line_starts[index];
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.source.len(), |next| next - 1);
+        let end = if self.source[start..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+
+        (start..end).into()
+    }
+
+    /// The text of the line at `index`, not including its line terminator.
+    pub fn line(&self, index: usize) -> &'src str {
+        let span = self.line_span(index);
+        &self.source[span.start..span.end]
+    }
+
+    /// The line index (starting at 0) and the column of `offset` in UTF-16 code units,
+    /// which is how editors speaking the language server protocol count them.
+    pub fn utf16_position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_index(offset);
+        let column = self.source[self.line_starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+
+        (line, column)
+    }
+
+    /// The byte offset of the UTF-16 `column` on the line at `index`, the inverse of
+    /// [`Self::utf16_position`]. Positions past the end of a line or the file are clamped.
+    pub fn utf16_offset(&self, index: usize, column: usize) -> usize {
+        if index >= self.line_count() {
+            return self.source.len();
+        }
+
+        let span = self.line_span(index);
+        let mut units = 0;
+        for (i, c) in self.source[span.start..span.end].char_indices() {
+            if units >= column {
+                return span.start + i;
+            }
+            units += c.len_utf16();
+        }
+        span.end
+    }
+}
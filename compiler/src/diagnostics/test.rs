@@ -0,0 +1,278 @@
+use super::{Diagnostic, EXPLANATIONS, Location, SourceMap, explain};
+use crate::{lexer::confusables, parser::Parser, typecheck::TypeChecker};
+use serde_json::json;
+
+#[test]
+fn source_map_locations() {
+    let map = SourceMap::new("test.ptn", "let a = 1\r\nlet é = \"ü\"\n\nx");
+
+    assert_eq!(map.line_count(), 4);
+    assert_eq!(map.location(0), Location { line: 1, column: 1 });
+    assert_eq!(map.location(4), Location { line: 1, column: 5 });
+    assert_eq!(map.location(11), Location { line: 2, column: 1 });
+    // `=` comes after the two byte `é`
+    assert_eq!(map.location(18), Location { line: 2, column: 7 });
+    assert_eq!(map.location(25), Location { line: 3, column: 1 });
+    assert_eq!(map.location(26), Location { line: 4, column: 1 });
+    assert_eq!(map.location(27), Location { line: 4, column: 2 });
+
+    assert_eq!(map.line(0), "let a = 1");
+    assert_eq!(map.line(1), "let é = \"ü\"");
+    assert_eq!(map.line(2), "");
+}
+
+#[test]
+fn unicode_locations_and_markers() {
+    // the byte order mark doesn't take up a column, but wide characters take up two
+    let source = "\u{FEFF}const 変数: Int = 1\u{FF1B}";
+    let map = SourceMap::new("test.ptn", source);
+    assert_eq!(map.location(3), Location { line: 1, column: 1 });
+    assert_eq!(map.location(9), Location { line: 1, column: 7 });
+    assert_eq!(
+        map.location(24),
+        Location {
+            line: 1,
+            column: 18
+        }
+    );
+
+    let diagnostic = Diagnostic::warning("confusable").with_label(24..27, "this is not `;`");
+    assert_eq!(
+        diagnostic.render(&map, false),
+        "\
+warning: confusable
+ --> test.ptn:1:18
+  |
+1 | \u{FEFF}const 変数: Int = 1\u{FF1B}
+  |                    ^^ this is not `;`
+"
+    );
+}
+
+#[test]
+fn render_labels_and_notes() {
+    let source = "const x: Int = 1\nfn f(a: Int): Bool -> a";
+    let map = SourceMap::new("test.ptn", source);
+
+    let diagnostic = Diagnostic::error("mismatched types")
+        .with_label(39..40, "expected `Bool`, found `Int`")
+        .with_secondary(31..35, "expected because of this annotation")
+        .with_note("a note");
+
+    assert_eq!(
+        diagnostic.render(&map, false),
+        "\
+error: mismatched types
+ --> test.ptn:2:23
+  |
+2 | fn f(a: Int): Bool -> a
+  |               ----    ^ expected `Bool`, found `Int`
+  |               expected because of this annotation
+  = note: a note
+"
+    );
+
+    let diagnostic = Diagnostic::error("two lines")
+        .with_label(6..7, "here")
+        .with_secondary(17..19, "and here");
+
+    assert_eq!(
+        diagnostic.render(&map, false),
+        "\
+error: two lines
+ --> test.ptn:1:7
+  |
+1 | const x: Int = 1
+  |       ^ here
+2 | fn f(a: Int): Bool -> a
+  | -- and here
+"
+    );
+}
+
+#[test]
+fn render_type_errors() {
+    let source = "fn f(a: Int): Bool -> {\n    a\n}";
+    let ast = Parser::new(source).file().unwrap();
+    let err = TypeChecker::new(&ast).check_items(&ast).unwrap_err();
+    let map = SourceMap::new("test.ptn", source);
+
+    assert_eq!(
+        Diagnostic::from(&err).render(&map, false),
+        "\
+error[E0202]: mismatched types
+ --> test.ptn:1:23
+  |
+1 | fn f(a: Int): Bool -> {
+  |               ----    ^ expected `Bool`, found `Int`
+  |               expected because of this annotation
+"
+    );
+
+    let source = "fn f(x: U8) -> x + 1000";
+    let ast = Parser::new(source).file().unwrap();
+    let err = TypeChecker::new(&ast).check_items(&ast).unwrap_err();
+    let map = SourceMap::new("test.ptn", source);
+    assert_eq!(
+        Diagnostic::from(&err).render(&map, false),
+        "\
+error[E0005]: literal out of range for `U8`
+ --> test.ptn:1:20
+  |
+1 | fn f(x: U8) -> x + 1000
+  |                    ^^^^ doesn't fit in `U8`
+  = note: `U8` ranges from 0 to 255
+"
+    );
+}
+
+#[test]
+fn render_type_warnings() {
+    let source = "fn f(b: Bool) -> match b { _ => 0, true => 1 }";
+    let ast = Parser::new(source).file().unwrap();
+    let mut checker = TypeChecker::new(&ast);
+    checker.check_items(&ast).unwrap();
+    let map = SourceMap::new("test.ptn", source);
+
+    assert_eq!(
+        Diagnostic::from(&checker.warnings()[0]).render(&map, false),
+        "\
+warning[E0228]: unreachable match arm
+ --> test.ptn:1:36
+  |
+1 | fn f(b: Bool) -> match b { _ => 0, true => 1 }
+  |                                    ^^^^ no value gets to this arm
+  = note: the arms before it match every value this pattern does
+"
+    );
+}
+
+#[test]
+fn json_output() {
+    let source = "fn f() -> 1\nconst x Int = 1";
+    let errors = Parser::new(source).file().unwrap_err();
+    let map = SourceMap::new("test.ptn", source);
+    let diagnostic = Diagnostic::from(&errors[0]);
+
+    let span = json!({
+        "start": 20,
+        "end": 23,
+        "line": 2,
+        "column": 9,
+        "end_line": 2,
+        "end_column": 12,
+    });
+    let insert_at = json!({
+        "start": 19,
+        "end": 19,
+        "line": 2,
+        "column": 8,
+        "end_line": 2,
+        "end_column": 8,
+    });
+    assert_eq!(
+        diagnostic.to_json(&map),
+        json!({
+            "severity": "error",
+            "code": "E0101",
+            "message": "expected `:`, found identifier in const `x`",
+            "file": "test.ptn",
+            "span": span,
+            "labels": [{ "span": span, "message": "expected `:`", "primary": true }],
+            "notes": [],
+            "fixes": [{
+                "message": "insert `:`",
+                "edits": [{ "span": insert_at, "replacement": ":" }],
+            }],
+        })
+    );
+
+    assert_eq!(
+        diagnostic.render(&map, false),
+        "\
+error[E0101]: expected `:`, found identifier in const `x`
+ --> test.ptn:2:9
+  |
+2 | const x Int = 1
+  |         ^^^ expected `:`
+  = help: insert `:`
+"
+    );
+}
+
+#[test]
+fn explanation_examples() {
+    let codes: Vec<_> = EXPLANATIONS
+        .iter()
+        .map(|explanation| explanation.code)
+        .collect();
+    assert!(codes.is_sorted(), "codes should be listed in order");
+    assert!(codes.windows(2).all(|pair| pair[0] != pair[1]));
+
+    // the first example of each explanation must produce the diagnostic it explains
+    for explanation in EXPLANATIONS {
+        let (_, rest) = explanation
+            .text
+            .split_once("Erroneous code example:\n\n")
+            .unwrap();
+        let example: Vec<_> = rest
+            .lines()
+            .take_while(|line| line.starts_with("    "))
+            .map(|line| &line[4..])
+            .collect();
+
+        let source = example.join("\n");
+        let mut diagnostics: Vec<_> = confusables(&source).iter().map(Diagnostic::from).collect();
+        match crate::compile(&source) {
+            Ok(program) => diagnostics.extend(program.warnings.iter().map(Diagnostic::from)),
+            Err(err) => diagnostics.extend(err.diagnostics()),
+        }
+        assert_eq!(
+            diagnostics.first().and_then(|diagnostic| diagnostic.code),
+            Some(explanation.code),
+            "{}: {diagnostics:?}",
+            explanation.code
+        );
+    }
+
+    assert_eq!(explain("e0202").unwrap().title, "mismatched types");
+    // warnings have codes too
+    let confusable = Diagnostic::from(&confusables("\u{0430}")[0]);
+    assert_eq!(
+        explain(confusable.code.unwrap()).unwrap().title,
+        "confusable character"
+    );
+    assert_eq!(explain("E9999"), None);
+}
+
+#[test]
+fn insertion_fixes() {
+    // a missing token is put in where it is usually written
+    for (source, fixed) in [
+        ("const x Int = 1", Some("const x: Int = 1")),
+        (
+            "fn f() -> { let a 1; a }",
+            Some("fn f() -> { let a = 1; a }"),
+        ),
+        ("fn f(a: Int): Int a", Some("fn f(a: Int): Int -> a")),
+        ("struct P x: Int }", Some("struct P { x: Int }")),
+        // but not when another token was written in its place
+        ("const a = 1", None),
+        ("fn f() -> { let a | a = x; a }", None),
+        ("fn f(x) -> if x 1 else 2", None),
+        ("const a: Int = match x { P { .., y } => 1 }", None),
+    ] {
+        let errors = Parser::new(source).file().unwrap_err();
+        let diagnostic = Diagnostic::from(&errors[0]);
+        let applied = diagnostic.fixes.first().map(|fix| {
+            let edit = &fix.edits[0];
+            [
+                &source[..edit.span.start],
+                &edit.replacement,
+                &source[edit.span.end..],
+            ]
+            .concat()
+        });
+        assert_eq!(applied.as_deref(), fixed, "{source}");
+    }
+}
@@ -0,0 +1,154 @@
+//! Layout documents, which describe the ways source can be laid out so that the best one
+//! for the line width can be picked when they are rendered.
+
+/// Spaces per level of indentation.
+const INDENT: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Text(String),
+    /// A space if the enclosing group fits on one line, a line break otherwise.
+    Line,
+    /// Nothing if the enclosing group fits on one line, a line break otherwise.
+    SoftLine,
+    /// A line break, which doesn't fit on one line with anything else.
+    HardLine,
+    /// Stops the enclosing groups from fitting on one line, without printing anything.
+    BreakParent,
+    /// Text that is only printed if the enclosing group is broken over several lines.
+    IfBreak(&'static str),
+    /// Indents the lines broken within the document by one more level.
+    Nest(Box<Self>),
+    /// Lays out the document on one line if it fits, breaking every line in it otherwise.
+    Group(Box<Self>),
+    Concat(Vec<Self>),
+}
+
+pub fn text(text: impl Into<String>) -> Doc {
+    Doc::Text(text.into())
+}
+
+pub fn nest(doc: impl Into<Doc>) -> Doc {
+    Doc::Nest(Box::new(doc.into()))
+}
+
+pub fn group(doc: impl Into<Doc>) -> Doc {
+    Doc::Group(Box::new(doc.into()))
+}
+
+impl From<Vec<Self>> for Doc {
+    fn from(value: Vec<Self>) -> Self {
+        Self::Concat(value)
+    }
+}
+
+impl From<&str> for Doc {
+    fn from(value: &str) -> Self {
+        text(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Lays out `doc`, keeping lines within `width` columns where it can.
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    // indentation is only written once something follows it, so blank lines stay empty
+    let mut pending_indent = None;
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                if let Some(indent) = pending_indent.take() {
+                    out.extend(std::iter::repeat_n(' ', indent * INDENT));
+                    column = indent * INDENT;
+                }
+                out.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line if mode == Mode::Flat => {
+                out.push(' ');
+                column += 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push('\n');
+                pending_indent = Some(indent);
+            }
+            Doc::BreakParent => {}
+            Doc::IfBreak(text) => {
+                if mode == Mode::Break {
+                    out.push_str(text);
+                    column += text.chars().count();
+                }
+            }
+            Doc::Nest(doc) => stack.push((indent + 1, mode, doc)),
+            Doc::Group(doc) => {
+                let mode = if mode == Mode::Flat || fits(width.saturating_sub(column), doc, &stack)
+                {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((indent, mode, doc));
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+        }
+    }
+
+    out
+}
+
+/// Whether `doc` fits on one line in `width` columns, along with whatever follows it up
+/// to the next line break in `rest`.
+fn fits(width: usize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut width = width;
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![(Mode::Flat, doc)];
+
+    loop {
+        let Some((mode, doc)) = stack.pop().or_else(|| {
+            let &(_, mode, doc) = rest.next()?;
+            Some((mode, doc))
+        }) else {
+            return true;
+        };
+
+        let text = match doc {
+            Doc::Text(text) => text.as_str(),
+            Doc::Line if mode == Mode::Flat => " ",
+            Doc::IfBreak(text) if mode == Mode::Break => text,
+            Doc::SoftLine if mode == Mode::Flat => "",
+            Doc::IfBreak(_) => "",
+            // a line break in what follows ends the line, but one in `doc` means it
+            // can't be on one line at all
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return mode == Mode::Break,
+            Doc::BreakParent => {
+                if mode == Mode::Flat {
+                    return false;
+                }
+                ""
+            }
+            Doc::Nest(doc) | Doc::Group(doc) => {
+                stack.push((mode, doc));
+                ""
+            }
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+                ""
+            }
+        };
+
+        let len = text.chars().count();
+        if len > width {
+            return false;
+        }
+        width -= len;
+    }
+}
@@ -0,0 +1,859 @@
+//! Prints syntax trees back out as source code in the canonical layout.
+//!
+//! Layout is decided in two steps: the tree is first turned into a [`Doc`] describing
+//! where lines may be broken, which is then rendered with as few breaks as fit the line
+//! width. Comments aren't part of the tree, so they are picked out of the tokens and put
+//! back in before the item, statement or list element that followed them.
+
+mod doc;
+#[cfg(test)]
+mod test;
+
+use std::collections::VecDeque;
+
+use doc::{Doc, group, nest, text};
+
+use crate::{
+    helpers::Span,
+    lexer::{Lexer, TokenType},
+    parser::{
+        ParseError,
+        ast::{
+            Ast, Binding, BindingS, Bop, Expr, ExprS, FieldInitS, FieldPatternS, FieldS, Item,
+            ItemS, MatchArmS, Path, Pattern, PatternS, Type, TypeS, Unop, Variant, VariantS,
+        },
+    },
+};
+
+/// The width lines are kept within where possible.
+pub const MAX_WIDTH: usize = 100;
+
+/// Formats `source`, which must be free of syntax errors.
+///
+/// # Errors
+/// Returns every syntax error in `source`.
+pub fn format(source: &str) -> Result<String, Vec<ParseError>> {
+    format_with_width(source, MAX_WIDTH)
+}
+
+fn format_with_width(source: &str, width: usize) -> Result<String, Vec<ParseError>> {
+    let ast = crate::parse(source)?;
+
+    let mut formatter = Formatter {
+        source,
+        comments: comments(source),
+    };
+    let doc = formatter.file(&ast);
+
+    Ok(doc::render(&doc, width))
+}
+
+/// A comment in the source, doc comments included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comment {
+    /// The text of the comment, delimiters included.
+    text: String,
+    start: usize,
+    /// Whether there is code before the comment on its line.
+    trailing: bool,
+}
+
+/// Every comment in `source`, in order.
+fn comments(source: &str) -> VecDeque<Comment> {
+    let mut comments = VecDeque::new();
+    // whether there has been code since the start of the line
+    let mut code = false;
+
+    for token in Lexer::with_trivia(source) {
+        match token.inner {
+            TokenType::Comment | TokenType::DocComment | TokenType::InnerDocComment => {
+                comments.push_back(Comment {
+                    text: source[token.span.start..token.span.end].trim_end().into(),
+                    start: token.span.start,
+                    trailing: code,
+                });
+            }
+            TokenType::Whitespace => {
+                code &= !source[token.span.start..token.span.end].contains('\n');
+            }
+            _ => code = true,
+        }
+    }
+
+    comments
+}
+
+/// Whether there is an empty line right before the line `offset` is on.
+fn blank_line_before(source: &str, offset: usize) -> bool {
+    let before = source[..offset].trim_end_matches([' ', '\t']);
+    before
+        .strip_suffix('\n')
+        .is_some_and(|before| before.trim_end_matches([' ', '\t', '\r']).ends_with('\n'))
+}
+
+struct Formatter<'src> {
+    source: &'src str,
+    /// Comments that haven't been printed yet.
+    comments: VecDeque<Comment>,
+}
+
+impl Formatter<'_> {
+    fn file(&mut self, ast: &Ast) -> Doc {
+        let mut docs = Vec::new();
+
+        for (i, item) in ast.iter().enumerate() {
+            let separator = if i == 0 {
+                Doc::Concat(Vec::new())
+            } else {
+                Doc::HardLine
+            };
+            let (comments, separator) = self.leading(item.span.start, separator, i == 0, true);
+            docs.extend([comments, separator, self.item(item)]);
+        }
+
+        // an empty file stays empty rather than getting a line break
+        let printed = !ast.is_empty() || !self.comments.is_empty();
+        let separator = if ast.is_empty() {
+            Doc::Concat(Vec::new())
+        } else {
+            Doc::HardLine
+        };
+        let (comments, _) = self.leading(self.source.len(), separator, ast.is_empty(), false);
+        docs.push(comments);
+        if printed {
+            docs.push(Doc::HardLine);
+        }
+
+        docs.into()
+    }
+
+    /// Lays out the comments before `offset` that haven't been printed yet, returning them
+    /// along with the line break to put between them and whatever is at `offset`.
+    ///
+    /// `separator` is the line break that would come before `offset` without any
+    /// comments, and `first` is whether nothing comes before it in the enclosing sequence.
+    /// A comment that followed code on its line stays at the end of that line, the others
+    /// get lines of their own. Blank lines before comments are kept, as are those before
+    /// `offset` if `keep_blank` is set, though several in a row are collapsed into one.
+    fn leading(
+        &mut self,
+        offset: usize,
+        separator: Doc,
+        first: bool,
+        keep_blank: bool,
+    ) -> (Doc, Doc) {
+        let mut docs = Vec::new();
+        let mut separator = separator;
+        let mut first = first;
+        let mut own_line = false;
+
+        while let Some(comment) = self.comments.front()
+            && comment.start < offset
+        {
+            let comment = self.comments.pop_front().unwrap();
+
+            if comment.trailing && !own_line {
+                docs.push(text(format!(" {}", comment.text)));
+            } else {
+                docs.push(separator);
+                if !first && blank_line_before(self.source, comment.start) {
+                    docs.push(Doc::HardLine);
+                }
+                docs.push(text(comment.text));
+                separator = Doc::HardLine;
+                first = false;
+                own_line = true;
+            }
+            // whatever follows has to go on the next line
+            docs.push(Doc::BreakParent);
+        }
+
+        if keep_blank && !first && blank_line_before(self.source, offset) {
+            separator = vec![separator, Doc::HardLine].into();
+        }
+
+        (docs.into(), separator)
+    }
+
+    fn item(&mut self, item: &ItemS) -> Doc {
+        match &item.inner {
+            Item::Const {
+                name, ty, value, ..
+            } => vec![
+                text(format!("const {name}: {} = ", type_(ty))),
+                self.expr(value),
+            ]
+            .into(),
+            Item::Function {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => vec![
+                text(format!("fn {name}")),
+                self.signature(params, return_type.as_ref(), body.span.start, PARENS),
+                text(" -> "),
+                self.expr(body),
+            ]
+            .into(),
+            Item::Struct {
+                name,
+                generic_params,
+                fields,
+                ..
+            } => vec![
+                text(format!("struct {name}{} ", generic_params_(generic_params))),
+                self.fields(fields, item.span.end),
+            ]
+            .into(),
+            Item::Enum {
+                name,
+                generic_params,
+                variants,
+                ..
+            } => {
+                let variants: Vec<_> = variants
+                    .iter()
+                    .map(|variant| (variant.span, variant))
+                    .collect();
+                vec![
+                    text(format!("enum {name}{} ", generic_params_(generic_params))),
+                    self.list(&variants, Self::variant, BRACES, item.span.end),
+                ]
+                .into()
+            }
+            Item::Error => self.verbatim(item.span),
+        }
+    }
+
+    fn variant(&mut self, variant: &VariantS) -> Doc {
+        match &variant.inner {
+            Variant::Unit(name) => text(name),
+            Variant::Tuple(name, types) => text(format!("{name}({})", types_(types))),
+            Variant::Struct(name, fields) => vec![
+                text(format!("{name} ")),
+                self.fields(fields, variant.span.end),
+            ]
+            .into(),
+        }
+    }
+
+    /// A `{ name: Type, ... }` list, which ends at `end`.
+    fn fields(&mut self, fields: &[FieldS], end: usize) -> Doc {
+        let fields: Vec<_> = fields.iter().map(|field| (field.span, field)).collect();
+        self.list(
+            &fields,
+            |_, field| text(format!("{}: {}", field.inner.name, type_(&field.inner.ty))),
+            BRACES,
+            end,
+        )
+    }
+
+    /// The parameters and return type of a function or lambda, whose body starts at
+    /// `body_start`, with the parameters between `delimiters`.
+    fn signature(
+        &mut self,
+        params: &[BindingS],
+        return_type: Option<&TypeS>,
+        body_start: usize,
+        delimiters: Delimiters,
+    ) -> Doc {
+        let params: Vec<_> = params.iter().map(|param| (param.span, param)).collect();
+        let end = return_type.map_or(body_start, |ty| ty.span.start);
+
+        let mut docs = vec![self.list(&params, Self::binding, delimiters, end)];
+        if let Some(ty) = return_type {
+            docs.push(text(format!(": {}", type_(ty))));
+        }
+        docs.into()
+    }
+
+    /// A comma separated list, which is broken into one element per line if it doesn't
+    /// fit on one or if it has comments in it. `end` is where the list ends in the source.
+    fn list<T>(
+        &mut self,
+        elements: &[(Span, &T)],
+        mut element: impl FnMut(&mut Self, &T) -> Doc,
+        delimiters: Delimiters,
+        end: usize,
+    ) -> Doc {
+        let Delimiters {
+            open,
+            close,
+            padding,
+            single_comma,
+        } = delimiters;
+        let padding = if padding { Doc::Line } else { Doc::SoftLine };
+
+        if elements.is_empty() && self.comments.front().is_none_or(|c| c.start >= end) {
+            return text(format!("{open}{close}"));
+        }
+
+        let mut docs = Vec::new();
+        for (i, (span, value)) in elements.iter().enumerate() {
+            let separator = if i == 0 { padding.clone() } else { Doc::Line };
+            let (comments, separator) = self.leading(span.start, separator, i == 0, true);
+            docs.extend([comments, separator, element(self, value)]);
+
+            docs.push(
+                if i + 1 < elements.len() || single_comma && elements.len() == 1 {
+                    text(",")
+                } else {
+                    Doc::IfBreak(",")
+                },
+            );
+        }
+        let (comments, separator) = self.leading(end, padding, elements.is_empty(), false);
+        docs.push(comments);
+
+        group(vec![text(open), nest(docs), separator, text(close)])
+    }
+
+    fn expr(&mut self, expr: &ExprS) -> Doc {
+        match &expr.inner {
+            Expr::Ident(name) => text(name),
+            Expr::Placeholder => text("_"),
+            Expr::Int(..)
+            | Expr::Float(..)
+            | Expr::Str(_)
+            | Expr::Interpolation(_)
+            | Expr::Char(_) => self.literal(expr.span),
+            Expr::Error => self.verbatim(expr.span),
+            Expr::Bool(value) => text(value.to_string()),
+            Expr::Array(exprs) => self.exprs(exprs, BRACKETS, expr.span.end),
+            Expr::Tuple(exprs) => self.exprs(exprs, TUPLE, expr.span.end),
+            Expr::FnCall { fun, args } => vec![
+                self.operand(fun, Precedence::Postfix),
+                self.exprs(args, PARENS, expr.span.end),
+            ]
+            .into(),
+            Expr::BinaryOp { .. } => self.binary(expr),
+            Expr::UnaryOp { op, expr } => {
+                let op = match op {
+                    Unop::Not => "!",
+                    Unop::Neg => "-",
+                };
+                vec![text(op), self.operand(expr, Precedence::Prefix)].into()
+            }
+            Expr::Index { arr, index } => vec![
+                self.operand(arr, Precedence::Postfix),
+                text("["),
+                self.expr(index),
+                text("]"),
+            ]
+            .into(),
+            Expr::FieldAccess { base, field } => {
+                // `1.x` would be lexed as a float
+                let base = if matches!(base.inner, Expr::Int(..) | Expr::Float(..)) {
+                    vec![text("("), self.expr(base), text(")")].into()
+                } else {
+                    self.operand(base, Precedence::Postfix)
+                };
+                vec![base, text(format!(".{}", field.inner))].into()
+            }
+            Expr::If { cond, th, el } => {
+                let mut docs = vec![text("if ("), self.expr(cond), text(") ")];
+
+                // an `else` after an inner `if` without one would be taken as its own
+                let dangling = el.is_some() && matches!(th.inner, Expr::If { el: None, .. });
+                docs.push(if dangling {
+                    vec![text("("), self.expr(th), text(")")].into()
+                } else {
+                    self.expr(th)
+                });
+
+                if let Some(el) = el {
+                    docs.push(text(" else "));
+                    docs.push(self.expr(el));
+                }
+                docs.into()
+            }
+            Expr::Let { binding, value } => vec![
+                text("let "),
+                self.binding(binding),
+                text(" = "),
+                self.expr(value),
+            ]
+            .into(),
+            Expr::Assign { ident, value } => {
+                vec![text(format!("{} = ", ident.inner)), self.expr(value)].into()
+            }
+            Expr::Lambda {
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                // closures are kept between their pipes
+                let closure = Lexer::new(&self.source[expr.span.start..expr.span.end])
+                    .find(|token| token.inner != TokenType::LParen)
+                    .is_some_and(|token| matches!(token.inner, TokenType::Pipe | TokenType::Or));
+                let (keyword, delimiters) = if closure { ("", PIPES) } else { ("fn", PARENS) };
+
+                vec![
+                    text(keyword),
+                    self.signature(params, return_type.as_ref(), body.span.start, delimiters),
+                    text(" -> "),
+                    self.expr(body),
+                ]
+                .into()
+            }
+            Expr::Block { exprs, trailing } => self.block(exprs, *trailing, expr.span.end),
+            Expr::Match { scrutinee, arms } => vec![
+                text("match "),
+                self.scrutinee(scrutinee),
+                text(" "),
+                self.arms(arms, expr.span.end),
+            ]
+            .into(),
+            Expr::Path(path) => text(path.to_string()),
+            Expr::Struct { path, fields, base } => {
+                self.struct_literal(path, fields, base.as_deref(), expr.span.end)
+            }
+        }
+    }
+
+    /// A struct literal, which ends at `end`.
+    fn struct_literal(
+        &mut self,
+        path: &Path,
+        fields: &[FieldInitS],
+        base: Option<&ExprS>,
+        end: usize,
+    ) -> Doc {
+        let mut fields: Vec<_> = fields.iter().map(Some).collect();
+        if base.is_some() {
+            fields.push(None);
+        }
+        // `..base` comes after the fields
+        let elements: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let span = field.map_or_else(|| base.unwrap().span, |field| field.span);
+                (span, field)
+            })
+            .collect();
+        vec![
+            text(format!("{path} ")),
+            self.list(
+                &elements,
+                |this, field| this.field_init(*field, base),
+                BRACES,
+                end,
+            ),
+        ]
+        .into()
+    }
+
+    /// A field of a struct literal, or `..base` after them if `field` is `None`.
+    fn field_init(&mut self, field: Option<&FieldInitS>, base: Option<&ExprS>) -> Doc {
+        let Some(field) = field else {
+            return vec![text(".."), self.expr(base.unwrap())].into();
+        };
+
+        match &field.inner.value.inner {
+            // `x: x` is written as just `x`
+            Expr::Ident(ident) if *ident == field.inner.name => text(ident),
+            _ => vec![
+                text(format!("{}: ", field.inner.name)),
+                self.expr(&field.inner.value),
+            ]
+            .into(),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[ExprS], delimiters: Delimiters, end: usize) -> Doc {
+        let exprs: Vec<_> = exprs.iter().map(|expr| (expr.span, expr)).collect();
+        self.list(&exprs, Self::expr, delimiters, end)
+    }
+
+    /// A block with one statement per line, which ends at `end`.
+    fn block(&mut self, exprs: &[ExprS], trailing: bool, end: usize) -> Doc {
+        if exprs.is_empty() && self.comments.front().is_none_or(|c| c.start >= end) {
+            return text("{}");
+        }
+
+        let mut docs = Vec::new();
+        for (i, expr) in exprs.iter().enumerate() {
+            let (comments, separator) = self.leading(expr.span.start, Doc::HardLine, i == 0, true);
+            docs.extend([comments, separator, self.expr(expr)]);
+
+            if i + 1 < exprs.len() || !trailing {
+                docs.push(text(";"));
+            }
+        }
+        let (comments, _) = self.leading(end, Doc::HardLine, exprs.is_empty(), false);
+        docs.push(comments);
+
+        vec![text("{"), nest(docs), Doc::HardLine, text("}")].into()
+    }
+
+    fn scrutinee(&mut self, scrutinee: &ExprS) -> Doc {
+        // a struct literal would take the braces of the arms as its own
+        if bare_struct_literal(scrutinee) {
+            vec![text("("), self.expr(scrutinee), text(")")].into()
+        } else {
+            self.expr(scrutinee)
+        }
+    }
+
+    /// The arms of a `match`, one per line, which end at `end`.
+    fn arms(&mut self, arms: &[MatchArmS], end: usize) -> Doc {
+        if arms.is_empty() && self.comments.front().is_none_or(|c| c.start >= end) {
+            return text("{}");
+        }
+
+        let mut docs = Vec::new();
+        for (i, arm) in arms.iter().enumerate() {
+            let (comments, separator) = self.leading(arm.span.start, Doc::HardLine, i == 0, true);
+            docs.extend([comments, separator, self.pattern(&arm.inner.pattern)]);
+
+            if let Some(guard) = &arm.inner.guard {
+                docs.extend([text(" if "), self.expr(guard)]);
+            }
+            docs.extend([text(" => "), self.expr(&arm.inner.body)]);
+
+            if !matches!(arm.inner.body.inner, Expr::Block { .. }) {
+                docs.push(text(","));
+            }
+        }
+        let (comments, _) = self.leading(end, Doc::HardLine, arms.is_empty(), false);
+        docs.push(comments);
+
+        vec![text("{"), nest(docs), Doc::HardLine, text("}")].into()
+    }
+
+    fn binding(&mut self, binding: &BindingS) -> Doc {
+        let mut docs = match &binding.inner {
+            Binding::Var { mutable, ident, .. } => vec![text(if *mutable {
+                format!("mut {ident}")
+            } else {
+                ident.clone()
+            })],
+            // alternatives need parentheses here, like in a closure's parameters
+            Binding::Pattern { pattern, .. } if matches!(pattern.inner, Pattern::Or(_)) => {
+                vec![text("("), self.pattern(pattern), text(")")]
+            }
+            Binding::Pattern { pattern, .. } => vec![self.pattern(pattern)],
+        };
+        if let Some(ty) = binding.inner.type_annotation() {
+            docs.push(text(format!(": {}", type_(ty))));
+        }
+        docs.into()
+    }
+
+    fn pattern(&mut self, pattern: &PatternS) -> Doc {
+        let end = pattern.span.end;
+
+        match &pattern.inner {
+            Pattern::Wildcard => text("_"),
+            Pattern::Rest => text(".."),
+            Pattern::Binding {
+                mutable,
+                ident,
+                subpattern,
+            } => {
+                let mut docs = vec![text(if *mutable {
+                    format!("mut {ident}")
+                } else {
+                    ident.clone()
+                })];
+                match subpattern.as_deref() {
+                    // the alternatives would otherwise be taken as alternatives to this
+                    Some(
+                        subpattern @ PatternS {
+                            inner: Pattern::Or(_),
+                            ..
+                        },
+                    ) => docs.extend([text(" @ ("), self.pattern(subpattern), text(")")]),
+                    Some(subpattern) => docs.extend([text(" @ "), self.pattern(subpattern)]),
+                    None => {}
+                }
+                docs.into()
+            }
+            Pattern::Literal(literal) => self.expr(literal),
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => vec![
+                self.expr(start),
+                text(if *inclusive { "..=" } else { ".." }),
+                self.expr(end),
+            ]
+            .into(),
+            Pattern::Tuple(elements) => self.patterns(elements, TUPLE, end),
+            Pattern::Array(elements) => self.patterns(elements, BRACKETS, end),
+            Pattern::Struct { path, fields, rest } => {
+                let mut fields: Vec<_> = fields.iter().map(Some).collect();
+                if *rest {
+                    fields.push(None);
+                }
+                // `..` is all that is left before the closing brace
+                let elements: Vec<_> = fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.map_or(Span::from(end - 1..end), |field| field.span),
+                            field,
+                        )
+                    })
+                    .collect();
+                vec![
+                    text(format!("{path} ")),
+                    self.list(
+                        &elements,
+                        |this, field| this.field_pattern(*field),
+                        BRACES,
+                        end,
+                    ),
+                ]
+                .into()
+            }
+            Pattern::TupleVariant { path, elements } => {
+                vec![text(path.to_string()), self.patterns(elements, PARENS, end)].into()
+            }
+            Pattern::Path(path) => text(path.to_string()),
+            Pattern::Or(alternatives) => {
+                let mut docs = Vec::new();
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        docs.push(text(" | "));
+                    }
+                    docs.push(self.pattern(alternative));
+                }
+                docs.into()
+            }
+        }
+    }
+
+    fn patterns(&mut self, patterns: &[PatternS], delimiters: Delimiters, end: usize) -> Doc {
+        let patterns: Vec<_> = patterns
+            .iter()
+            .map(|pattern| (pattern.span, pattern))
+            .collect();
+        self.list(&patterns, Self::pattern, delimiters, end)
+    }
+
+    /// A field in a struct pattern, or the `..` at the end of one for `None`.
+    fn field_pattern(&mut self, field: Option<&FieldPatternS>) -> Doc {
+        let Some(field) = field else {
+            return text("..");
+        };
+
+        match &field.inner.pattern.inner {
+            // `x: x` is written as just `x`
+            Pattern::Binding {
+                ident,
+                subpattern: None,
+                ..
+            } if *ident == field.inner.name => self.pattern(&field.inner.pattern),
+            _ => vec![
+                text(format!("{}: ", field.inner.name)),
+                self.pattern(&field.inner.pattern),
+            ]
+            .into(),
+        }
+    }
+
+    /// A chain of binary operators of the same precedence, which is broken before each
+    /// operator if it doesn't fit on one line.
+    fn binary(&mut self, expr: &ExprS) -> Doc {
+        // the operands of a left associative chain are nested down the left hand side
+        let mut chain = Vec::new();
+        let mut first = expr;
+        while let Expr::BinaryOp { op, lhs, rhs } = &first.inner {
+            chain.push((*op, rhs));
+            first = lhs;
+
+            let same_precedence = matches!(
+                &lhs.inner,
+                Expr::BinaryOp { op: inner, .. } if inner.binding_power() == op.binding_power()
+            );
+            if !same_precedence || needs_parens_lhs(*op, lhs) {
+                break;
+            }
+        }
+        chain.reverse();
+
+        let (op, _) = chain[0];
+        let mut docs = vec![self.operand(first, Precedence::Lhs(op))];
+        let mut rest = Vec::new();
+        for (op, rhs) in chain {
+            rest.push(Doc::Line);
+            rest.push(text(format!("{} ", bop(op))));
+            rest.push(self.operand(rhs, Precedence::Rhs(op)));
+        }
+        docs.push(nest(rest));
+
+        group(docs)
+    }
+
+    /// `expr` in a position that binds as tightly as `precedence`, parenthesized if it
+    /// would otherwise be parsed differently there.
+    fn operand(&mut self, expr: &ExprS, precedence: Precedence) -> Doc {
+        let parens = match (&expr.inner, precedence) {
+            // the first of these extend as far to the right as they can
+            (Expr::If { .. } | Expr::Let { .. } | Expr::Assign { .. } | Expr::Lambda { .. }, _)
+            | (Expr::BinaryOp { .. }, Precedence::Postfix | Precedence::Prefix)
+            | (Expr::UnaryOp { .. }, Precedence::Postfix) => true,
+            (Expr::BinaryOp { .. }, Precedence::Lhs(op)) => needs_parens_lhs(op, expr),
+            (Expr::BinaryOp { op: inner, .. }, Precedence::Rhs(op)) => {
+                inner.binding_power().0 < op.binding_power().1
+            }
+            _ => false,
+        };
+
+        if parens {
+            vec![text("("), self.expr(expr), text(")")].into()
+        } else {
+            self.expr(expr)
+        }
+    }
+
+    /// The literal at `span` as it was written, which keeps its escapes and the like.
+    /// A literal as written, which for an interpolated string includes the expressions in
+    /// it, along with any comments between them.
+    fn literal(&mut self, span: Span) -> Doc {
+        // the span of a parenthesized expression includes the parentheses
+        let tokens: Vec<_> = Lexer::new(&self.source[span.start..span.end]).collect();
+        let parens = tokens
+            .iter()
+            .take_while(|token| token.inner == TokenType::LParen)
+            .count();
+        // the last token is the end of the input
+        let (first, last) = (tokens[parens], tokens[tokens.len() - 2 - parens]);
+        let span = Span::from(span.start + first.span.start..span.start + last.span.end);
+
+        self.comments
+            .retain(|comment| !(span.start..span.end).contains(&comment.start));
+        self.verbatim(span)
+    }
+
+    /// The source text at `span`, as it was.
+    fn verbatim(&self, span: Span) -> Doc {
+        text(&self.source[span.start..span.end])
+    }
+}
+
+/// The delimiters around a list and how it is laid out on one line.
+#[derive(Debug, Clone, Copy)]
+struct Delimiters {
+    open: &'static str,
+    close: &'static str,
+    /// Whether there are spaces inside the delimiters.
+    padding: bool,
+    /// Whether a single element is followed by a comma, as in a one element tuple.
+    single_comma: bool,
+}
+
+const PARENS: Delimiters = Delimiters {
+    open: "(",
+    close: ")",
+    padding: false,
+    single_comma: false,
+};
+const TUPLE: Delimiters = Delimiters {
+    single_comma: true,
+    ..PARENS
+};
+const BRACKETS: Delimiters = Delimiters {
+    open: "[",
+    close: "]",
+    ..PARENS
+};
+const PIPES: Delimiters = Delimiters {
+    open: "|",
+    close: "|",
+    ..PARENS
+};
+const BRACES: Delimiters = Delimiters {
+    open: "{",
+    close: "}",
+    padding: true,
+    single_comma: false,
+};
+
+/// How tightly the position an expression is printed in binds.
+#[derive(Debug, Clone, Copy)]
+enum Precedence {
+    /// The left hand side of a binary operator.
+    Lhs(Bop),
+    /// The right hand side of a binary operator.
+    Rhs(Bop),
+    /// The operand of a unary operator.
+    Prefix,
+    /// The base of a call, index or field access.
+    Postfix,
+}
+
+/// Whether `expr` has a struct literal in it that isn't between delimiters, which can't
+/// be the scrutinee of a `match` as it is.
+fn bare_struct_literal(expr: &ExprS) -> bool {
+    match &expr.inner {
+        Expr::Struct { .. } => true,
+        Expr::FnCall { fun: expr, .. }
+        | Expr::UnaryOp { expr, .. }
+        | Expr::Index { arr: expr, .. }
+        | Expr::FieldAccess { base: expr, .. }
+        | Expr::Let { value: expr, .. }
+        | Expr::Assign { value: expr, .. }
+        | Expr::Lambda { body: expr, .. } => bare_struct_literal(expr),
+        Expr::BinaryOp { lhs, rhs, .. } => bare_struct_literal(lhs) || bare_struct_literal(rhs),
+        Expr::If { th, el, .. } => {
+            bare_struct_literal(th) || el.as_deref().is_some_and(bare_struct_literal)
+        }
+        _ => false,
+    }
+}
+
+const fn needs_parens_lhs(op: Bop, lhs: &ExprS) -> bool {
+    match &lhs.inner {
+        Expr::BinaryOp { op: inner, .. } => op.binding_power().0 >= inner.binding_power().1,
+        _ => false,
+    }
+}
+
+const fn bop(op: Bop) -> &'static str {
+    match op {
+        Bop::Add => "+",
+        Bop::Sub => "-",
+        Bop::Mul => "*",
+        Bop::Div => "/",
+        Bop::Exp => "**",
+        Bop::And => "&&",
+        Bop::Or => "||",
+        Bop::Xor => "^",
+        Bop::BOr => "|",
+        Bop::BAnd => "&",
+        Bop::Gt => ">",
+        Bop::Lt => "<",
+        Bop::Eqq => "==",
+        Bop::Neq => "!=",
+        Bop::Geq => ">=",
+        Bop::Leq => "<=",
+    }
+}
+
+fn type_(ty: &TypeS) -> String {
+    match &ty.inner {
+        Type::Named { name, generics } if generics.is_empty() => name.clone(),
+        Type::Named { name, generics } => format!("{name}<{}>", types_(generics)),
+        Type::Array(ty) => format!("[{}]", type_(ty)),
+        Type::Tuple(types) => format!("({})", types_(types)),
+        Type::Fn { params, result } => format!("fn({}): {}", types_(params), type_(result)),
+    }
+}
+
+fn types_(types: &[TypeS]) -> String {
+    types.iter().map(type_).collect::<Vec<_>>().join(", ")
+}
+
+fn generic_params_(params: &[String]) -> String {
+    if params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", params.join(", "))
+    }
+}
@@ -0,0 +1,267 @@
+use super::{format, format_with_width};
+
+/// Formats `source`, checking that formatting the result again doesn't change it.
+fn fmt(source: &str) -> String {
+    let formatted = format(source).unwrap();
+    assert_eq!(format(&formatted).unwrap(), formatted, "not idempotent");
+    formatted
+}
+
+#[test]
+fn format_items() {
+    assert_eq!(
+        fmt(
+            "fn  sum(a,b)->a+b\nconst x:Int=-  12\nstruct Point<T>{x:T,y:T,}\n\n\n\nenum E{A,B(Int,[Str]),C{f:fn(Int):Int}}"
+        ),
+        "\
+fn sum(a, b) -> a + b
+const x: Int = -12
+struct Point<T> { x: T, y: T }
+
+enum E { A, B(Int, [Str]), C { f: fn(Int): Int } }
+"
+    );
+    assert_eq!(fmt("struct Unit {}"), "struct Unit {}\n");
+    assert_eq!(fmt(""), "");
+}
+
+#[test]
+fn format_blocks() {
+    assert_eq!(
+        fmt(
+            "fn f(mut a: Int): Int -> { let b = a; a = b + 1; if (a > b) { a } else if (a < b) {b} else {} ; {} }"
+        ),
+        "\
+fn f(mut a: Int): Int -> {
+    let b = a;
+    a = b + 1;
+    if (a > b) {
+        a
+    } else if (a < b) {
+        b
+    } else {};
+    {}
+}
+"
+    );
+    assert_eq!(
+        fmt("fn f() -> { 1; 2; }"),
+        "fn f() -> {\n    1;\n    2;\n}\n"
+    );
+}
+
+#[test]
+fn format_parentheses() {
+    // only the parentheses that are needed to keep the meaning are kept
+    assert_eq!(
+        fmt("const x: Int = ((a + b) * (c - (d - e))) + (a * b) - -(f(x)) ** (2 ** 3)"),
+        "const x: Int = (a + b) * (c - (d - e)) + a * b - -f(x) ** 2 ** 3\n"
+    );
+    assert_eq!(
+        fmt("const x: Int = ((a ** b) ** c) + (-a).b + (1).c + (fn(x) -> x)(1)[0]"),
+        "const x: Int = (a ** b) ** c + (-a).b + (1).c + (fn(x) -> x)(1)[0]\n"
+    );
+    assert_eq!(fmt("const t: (Int) = (1,)"), "const t: (Int) = (1,)\n");
+    assert_eq!(
+        fmt("const f: fn(Int): Int = (|a:Int|:Int->a|1)"),
+        "const f: fn(Int): Int = |a: Int|: Int -> a | 1\n"
+    );
+    assert_eq!(
+        fmt("fn f(a: Bool) -> a || (|| -> a)() || (fn() -> a)()"),
+        "fn f(a: Bool) -> a || (|| -> a)() || (fn() -> a)()\n"
+    );
+    assert_eq!(
+        fmt("const inc: fn(Int): Int = sum( 1,_ )"),
+        "const inc: fn(Int): Int = sum(1, _)\n"
+    );
+    assert_eq!(
+        fmt("fn f() -> if (a) (if (b) c) else d"),
+        "fn f() -> if (a) (if (b) c) else d\n"
+    );
+}
+
+#[test]
+fn format_strings() {
+    // the text of a multi-line string is relative to its closing delimiter, which stays
+    // where it was, so string literals are kept as written
+    assert_eq!(
+        fmt("fn f() -> {let s = r#\"\\\"#;\n  \"\"\"\n    a\n  \"\"\" }"),
+        "fn f() -> {\n    let s = r#\"\\\"#;\n    \"\"\"\n    a\n  \"\"\"\n}\n"
+    );
+    assert_eq!(
+        fmt("const s: Str = (\"a {b  /* c */} {(d)}\") // e"),
+        "const s: Str = \"a {b  /* c */} {(d)}\" // e\n"
+    );
+}
+
+#[test]
+fn format_long_lines() {
+    assert_eq!(
+        format_with_width(
+            "fn function(first: Int, second: Int) -> call(first, second) + other_call(second)",
+            30
+        )
+        .unwrap(),
+        "\
+fn function(
+    first: Int,
+    second: Int,
+) -> call(first, second)
+    + other_call(second)
+"
+    );
+    assert_eq!(
+        format_with_width("const xs: [Int] = [one, two, three, [four, five]]", 30).unwrap(),
+        "\
+const xs: [Int] = [
+    one,
+    two,
+    three,
+    [four, five],
+]
+"
+    );
+    // lists that fit on one line are joined back up
+    assert_eq!(
+        fmt("const xs: [Int] = [\n    1,\n    2,\n]"),
+        "const xs: [Int] = [1, 2]\n"
+    );
+}
+
+#[test]
+fn format_comments() {
+    assert_eq!(
+        fmt("\
+// a file
+
+// about f
+fn f() -> { // the body
+    let a = 1;    // one
+
+    // then
+    g(a,   // first
+      // second
+      b)
+    // last
+}
+const c: Int = 1 // trailing
+// the end
+"),
+        "\
+// a file
+
+// about f
+fn f() -> { // the body
+    let a = 1; // one
+
+    // then
+    g(
+        a, // first
+        // second
+        b,
+    )
+    // last
+}
+const c: Int = 1 // trailing
+// the end
+"
+    );
+    assert_eq!(fmt("// only a comment\n"), "// only a comment\n");
+    assert_eq!(
+        fmt("//! file\n\n///  doc\nfn  f() -> {\n/* a /* b */ */\n1 }\n"),
+        "//! file\n\n///  doc\nfn f() -> {\n    /* a /* b */ */\n    1\n}\n"
+    );
+}
+
+#[test]
+fn format_match() {
+    assert_eq!(
+        fmt(
+            "fn f(s)->match s{Shape::Circle( r )=>r,Shape::Rect{w:w,mut h,..}|Shape::Square{w,h:h@_,}if w>0.0=>{w*h} // area\n(a,-1)=>a , 0 .. 10|20..= -1=>1,[first,rest@..]=>first,n@(1|2)=>n,_=>0}"
+        ),
+        "\
+fn f(s) -> match s {
+    Shape::Circle(r) => r,
+    Shape::Rect { w, mut h, .. } | Shape::Square { w, h: h @ _ } if w > 0.0 => {
+        w * h
+    } // area
+    (a, -1) => a,
+    0..10 | 20..=-1 => 1,
+    [first, rest @ ..] => first,
+    n @ (1 | 2) => n,
+    _ => 0,
+}
+"
+    );
+    assert_eq!(
+        fmt("const x: Int = match y {}"),
+        "const x: Int = match y {}\n"
+    );
+    assert_eq!(
+        format_with_width(
+            "const p: Int = match p { Point { first, second, .. } => 1 }",
+            30
+        )
+        .unwrap(),
+        "\
+const p: Int = match p {
+    Point {
+        first,
+        second,
+        ..,
+    } => 1,
+}
+"
+    );
+}
+
+#[test]
+fn format_destructuring() {
+    assert_eq!(
+        fmt(
+            "fn f(( a,_ ):(Int,Bool),Point{x,..}:Point)->{let [first,..]=xs;let ((a,b)|(b,a))=p;|(l,r)|->l}"
+        ),
+        "\
+fn f((a, _): (Int, Bool), Point { x, .. }: Point) -> {
+    let [first, ..] = xs;
+    let ((a, b) | (b, a)) = p;
+    |(l, r)| -> l
+}
+"
+    );
+}
+
+#[test]
+fn format_struct_literals() {
+    assert_eq!(
+        fmt("fn f(p)->[Point{x:1,y:y,..p},Shape::Circle( 3.0 ),Shape::Empty,Unit{ }]"),
+        "fn f(p) -> [Point { x: 1, y, ..p }, Shape::Circle(3.0), Shape::Empty, Unit {}]\n"
+    );
+    // a struct literal before the arms of a `match` keeps its parentheses
+    assert_eq!(
+        fmt("fn f()->match (Point{ x:1 }).x{_=>0}"),
+        "fn f() -> match (Point { x: 1 }.x) {\n    _ => 0,\n}\n"
+    );
+    assert_eq!(
+        fmt("fn f()->match g(Point{ x:1 }){_=>0}"),
+        "fn f() -> match g(Point { x: 1 }) {\n    _ => 0,\n}\n"
+    );
+    assert_eq!(
+        fmt("fn f(x)->Rect{width:x*2,height:function_name(x),\
+             depth:other_long_function_name(x,x),..x}"),
+        "\
+fn f(x) -> Rect {
+    width: x * 2,
+    height: function_name(x),
+    depth: other_long_function_name(x, x),
+    ..x,
+}
+"
+    );
+}
+
+#[test]
+fn format_errors() {
+    let errs = format("fn f( -> 1").unwrap_err();
+    assert_eq!(errs.len(), 1);
+}
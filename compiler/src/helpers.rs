@@ -58,7 +58,10 @@ impl<T> Spanned<T> {
 
 impl<T> Spanned<T> {
     pub fn span(inner: T, span: impl Into<Span>) -> Self {
-        Self { inner, span: span.into() }
+        Self {
+            inner,
+            span: span.into(),
+        }
     }
 }
 
@@ -68,6 +71,22 @@ pub struct Span {
     pub end: usize,
 }
 
+impl Span {
+    /// Whether `offset` lies within the span or right at its end, where a cursor just
+    /// after the last character would be.
+    pub const fn touches(self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+
+    pub const fn len(self) -> usize {
+        self.end - self.start
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+}
+
 impl From<Range<usize>> for Span {
     fn from(value: Range<usize>) -> Self {
         Self {
@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use super::{Token, scanner};
+
+/// A token that is malformed as a whole, as opposed to having malformed contents like a
+/// [`LiteralError`](super::LiteralError).
+///
+/// The lexer still gives such a token the type it was meant to have, ending it where the
+/// rest of the input makes sense again, so that parsing can go on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexError {
+    /// A string that isn't closed. A `"` string ends at the end of its line, while raw
+    /// and multi-line strings run to the end of the input.
+    UnterminatedString,
+    /// A char literal without its closing `'`, which ends after its first character.
+    UnterminatedChar,
+    /// A block comment that is still open at the end of the input.
+    UnterminatedComment,
+    /// `''`, a char literal with nothing in it.
+    EmptyChar,
+    /// A char literal with more than one character in it, like `'ab'`.
+    MultipleChars,
+    /// A character that doesn't start any token, which is a token of its own.
+    StrayCharacter(char),
+}
+
+impl LexError {
+    /// Whether the token is missing its closing delimiter.
+    pub const fn is_unterminated(self) -> bool {
+        matches!(
+            self,
+            Self::UnterminatedString | Self::UnterminatedChar | Self::UnterminatedComment
+        )
+    }
+
+    /// The diagnostic code of the error.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::StrayCharacter(_) => "E0001",
+            Self::UnterminatedString | Self::UnterminatedChar | Self::UnterminatedComment => {
+                "E0006"
+            }
+            Self::EmptyChar | Self::MultipleChars => "E0007",
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnterminatedString => "unterminated string".fmt(f),
+            Self::UnterminatedChar => "unterminated char literal".fmt(f),
+            Self::UnterminatedComment => "unterminated block comment".fmt(f),
+            Self::EmptyChar => "empty char literal".fmt(f),
+            Self::MultipleChars => "char literal with more than one character".fmt(f),
+            Self::StrayCharacter(c) => write!(f, "stray character `{}`", c.escape_debug()),
+        }
+    }
+}
+
+/// What is wrong with `token` as a whole, if anything. The error covers the whole token.
+pub fn lex_error(token: Token, source: &str) -> Option<LexError> {
+    scanner::error(token.inner, &source[token.span.start..])
+}
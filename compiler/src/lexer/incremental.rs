@@ -0,0 +1,95 @@
+//! Re-lexing a source after an edit, starting from the last point before it that the
+//! edit can't affect and stopping once the lexer is back in step with the old tokens.
+
+use std::ops::Range;
+
+use crate::helpers::Span;
+
+use super::{LexError, Lexer, Token, TokenType, lex_error, track_interpolations};
+
+/// The tokens that change when a source is edited, as found by [`relex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relexed {
+    /// The indices of the old tokens that are replaced.
+    pub replaced: Range<usize>,
+    /// The tokens that replace them, spanned in the edited source.
+    pub tokens: Vec<Token>,
+}
+
+/// Re-lexes as much of `new` as changed when the text at `edit` in `old` was replaced to
+/// make it. `tokens` are the tokens of `old`, trivia included, as produced by
+/// [`Lexer::with_trivia`].
+///
+/// The old tokens before the replaced ones stay as they are, and the ones after them
+/// only move by the difference in length between the sources.
+pub fn relex(tokens: &[Token], old: &str, new: &str, edit: Span) -> Relexed {
+    // where the text after the edit starts in `new`
+    let inserted_end = new.len() - (old.len() - edit.end);
+
+    // a token can look as far as the end of its line to find where it ends, and a `"`
+    // string that is never closed looks for its `"` all the way to the end of the input
+    let mut restart = old[..edit.start].rfind(['\n', '\r']).map_or(0, |i| i + 1);
+    let unclosed = tokens
+        .iter()
+        .take_while(|token| token.span.start < restart)
+        .find(|&&token| {
+            matches!(token.inner, TokenType::StringLit | TokenType::StringEnd)
+                && old
+                    .as_bytes()
+                    .get(token.span.end)
+                    .is_none_or(|&byte| byte == b'\n' || byte == b'\r')
+                && lex_error(token, old) == Some(LexError::UnterminatedString)
+        });
+    if let Some(token) = unclosed {
+        restart = token.span.start;
+    }
+
+    // the token ending right at the restart might only have stopped there because of
+    // what came next, which the edit may have changed
+    let first = tokens.partition_point(|token| token.span.end < restart);
+
+    let mut lexer = Lexer::with_trivia(new);
+    lexer.pos = tokens[first].span.start;
+    for token in &tokens[..first] {
+        track_interpolations(&mut lexer.interpolations, token.inner);
+    }
+    let mut old_interpolations = lexer.interpolations.clone();
+
+    let mut relexed = Vec::new();
+    let mut old_index = first;
+    loop {
+        // past the edit, lexing in the same state from where an old token started gives
+        // the same tokens as before
+        if lexer.pos >= inserted_end {
+            let at = lexer.pos - inserted_end + edit.end;
+            // the old `Eof` is at the end of `old`, which `at` never goes past
+            while tokens[old_index].span.start < at {
+                track_interpolations(&mut old_interpolations, tokens[old_index].inner);
+                old_index += 1;
+            }
+
+            if tokens[old_index].span.start == at && old_interpolations == lexer.interpolations {
+                break;
+            }
+        }
+
+        let Some(token) = lexer.next() else {
+            old_index = tokens.len();
+            break;
+        };
+        relexed.push(token);
+    }
+
+    // tokens before the edit that came out the same aren't replaced
+    let same = relexed
+        .iter()
+        .zip(&tokens[first..old_index])
+        .take_while(|(new, old)| new == old && old.span.end <= edit.start)
+        .count();
+    relexed.drain(..same);
+
+    Relexed {
+        replaced: first + same..old_index,
+        tokens: relexed,
+    }
+}
@@ -0,0 +1,449 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{helpers::Span, span};
+
+use super::{LexError, Token, TokenType, lex_error};
+
+span! {LiteralError as LiteralErrorS}
+/// A problem with the contents of a literal token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralError {
+    /// `\` followed by a character that has no escape.
+    Unknown(char),
+    /// `\x` not followed by two hex digits.
+    MalformedHex,
+    /// `\x` above `\x7F`, which isn't ASCII.
+    HexOutOfRange,
+    /// `\u` not followed by one to six hex digits in braces.
+    MalformedUnicode,
+    /// `\u{...}` of a number that isn't a Unicode scalar value.
+    InvalidUnicode(u32),
+    /// Text after the opening `"""` of a multi-line string, on the same line.
+    TextAfterOpening,
+    /// Text before the closing `"""` of a multi-line string, on the same line.
+    TextBeforeClosing,
+    /// A line of a multi-line string that isn't indented as far as the closing `"""`.
+    Underindented,
+    /// A digit that is too large for the base of its number, like the `2` in `0b102`.
+    InvalidDigit { digit: char, radix: u32 },
+    /// A base prefix like `0x` that no digits follow.
+    NoDigits,
+    /// Text after a number that isn't one of the type suffixes.
+    InvalidSuffix,
+    /// A number too large for the type it is given by its suffix, or for `UInt` and
+    /// `Float` if it has none.
+    OutOfRange(NumericSuffix),
+}
+
+impl LiteralError {
+    /// Whether this is a bad escape, rather than a badly laid out literal.
+    pub const fn is_escape(self) -> bool {
+        matches!(
+            self,
+            Self::Unknown(_)
+                | Self::MalformedHex
+                | Self::HexOutOfRange
+                | Self::MalformedUnicode
+                | Self::InvalidUnicode(_)
+        )
+    }
+
+    /// The diagnostic code of the error.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::Unknown(_)
+            | Self::MalformedHex
+            | Self::HexOutOfRange
+            | Self::MalformedUnicode
+            | Self::InvalidUnicode(_) => "E0002",
+            Self::TextAfterOpening | Self::TextBeforeClosing | Self::Underindented => "E0003",
+            Self::InvalidDigit { .. } | Self::NoDigits | Self::InvalidSuffix => "E0004",
+            Self::OutOfRange(_) => "E0005",
+        }
+    }
+}
+
+impl Display for LiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown(c) => write!(f, "unknown character escape `\\{}`", c.escape_debug()),
+            Self::MalformedHex => "`\\x` must be followed by two hex digits".fmt(f),
+            Self::HexOutOfRange => "`\\x` escapes can only go up to `\\x7F`".fmt(f),
+            Self::MalformedUnicode => {
+                "`\\u` must be followed by one to six hex digits in braces".fmt(f)
+            }
+            Self::InvalidUnicode(value) => {
+                write!(f, "`\\u{{{value:X}}}` is not a Unicode scalar value")
+            }
+            Self::TextAfterOpening => {
+                "the text of a multi-line string must start on the line after `\"\"\"`".fmt(f)
+            }
+            Self::TextBeforeClosing => {
+                "the closing `\"\"\"` of a multi-line string must be on a line of its own".fmt(f)
+            }
+            Self::Underindented => {
+                "line is indented less than the closing `\"\"\"` of its multi-line string".fmt(f)
+            }
+            Self::InvalidDigit { digit, radix } => {
+                write!(f, "invalid digit `{digit}` in a base {radix} number")
+            }
+            Self::NoDigits => "number has no digits after its base prefix".fmt(f),
+            Self::InvalidSuffix => "invalid suffix for a number literal".fmt(f),
+            Self::OutOfRange(suffix) => {
+                write!(f, "literal out of range for `{}`", suffix.type_name())
+            }
+        }
+    }
+}
+
+/// A suffix that gives a number literal its type, like the `u8` in `10u8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl NumericSuffix {
+    const ALL: [(&str, Self); 10] = [
+        ("i8", Self::I8),
+        ("i16", Self::I16),
+        ("i32", Self::I32),
+        ("i64", Self::I64),
+        ("u8", Self::U8),
+        ("u16", Self::U16),
+        ("u32", Self::U32),
+        ("u64", Self::U64),
+        ("f32", Self::F32),
+        ("f64", Self::F64),
+    ];
+
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(text, _)| *text == suffix)
+            .map(|&(_, suffix)| suffix)
+    }
+
+    pub const fn suffix(self) -> &'static str {
+        Self::ALL[self as usize].0
+    }
+
+    /// The name of the type in annotations.
+    pub const fn type_name(self) -> &'static str {
+        match self {
+            Self::I8 => "I8",
+            Self::I16 => "I16",
+            Self::I32 => "I32",
+            Self::I64 => "Int",
+            Self::U8 => "U8",
+            Self::U16 => "U16",
+            Self::U32 => "U32",
+            Self::U64 => "UInt",
+            Self::F32 => "F32",
+            Self::F64 => "Float",
+        }
+    }
+
+    pub const fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// The smallest and largest value of the type, as text for diagnostics.
+    pub const fn range(self) -> (&'static str, &'static str) {
+        match self {
+            Self::I8 => ("-128", "127"),
+            Self::I16 => ("-32768", "32767"),
+            Self::I32 => ("-2147483648", "2147483647"),
+            Self::I64 => ("-9223372036854775808", "9223372036854775807"),
+            Self::U8 => ("0", "255"),
+            Self::U16 => ("0", "65535"),
+            Self::U32 => ("0", "4294967295"),
+            Self::U64 => ("0", "18446744073709551615"),
+            Self::F32 => ("-3.40282347e38", "3.40282347e38"),
+            Self::F64 => ("-1.7976931348623157e308", "1.7976931348623157e308"),
+        }
+    }
+
+    /// The largest magnitude an integer of the type can have, which is one more for
+    /// negative numbers of a signed type.
+    const fn max_magnitude(self, negated: bool) -> u64 {
+        let max = match self {
+            Self::I8 => i8::MAX as u64,
+            Self::I16 => i16::MAX as u64,
+            Self::I32 => i32::MAX as u64,
+            Self::I64 => i64::MAX as u64,
+            Self::U8 => u8::MAX as u64,
+            Self::U16 => u16::MAX as u64,
+            Self::U32 => u32::MAX as u64,
+            Self::U64 | Self::F32 | Self::F64 => u64::MAX,
+        };
+
+        if negated && matches!(self, Self::I8 | Self::I16 | Self::I32 | Self::I64) {
+            max + 1
+        } else {
+            max
+        }
+    }
+}
+
+/// The value of a number literal, and the type its suffix gives it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(u64, Option<NumericSuffix>),
+    Float(f64, Option<NumericSuffix>),
+}
+
+/// The value of the number literal `token`, along with its invalid parts. `negated` is
+/// whether a `-` directly precedes the literal, which lets signed types reach their
+/// minimum.
+///
+/// An integer with a float suffix, like `1f32`, is a float.
+pub fn number_value(token: Token, source: &str, negated: bool) -> (Number, Vec<LiteralErrorS>) {
+    let start = token.span.start;
+    let text = &source[start..token.span.end];
+
+    match token.inner {
+        TokenType::IntLit => integer(text, start, negated),
+        TokenType::FloatLit => {
+            let (digits, suffix) = text.split_at(text.find('f').unwrap_or(text.len()));
+            float(digits, NumericSuffix::from_suffix(suffix), token.span)
+        }
+        token => unreachable!("{token} is not a number literal"),
+    }
+}
+
+fn integer(text: &str, offset: usize, negated: bool) -> (Number, Vec<LiteralErrorS>) {
+    let span = Span::from(offset..offset + text.len());
+    let mut errors = Vec::new();
+
+    let (radix, prefix_len) = match text.get(..2) {
+        Some("0x") => (16, 2),
+        Some("0o") => (8, 2),
+        Some("0b") => (2, 2),
+        _ => (10, 0),
+    };
+    let body = &text[prefix_len..];
+    let digits_len = body
+        .find(|c: char| !(c.is_ascii_digit() || c == '_' || (radix == 16 && c.is_ascii_hexdigit())))
+        .unwrap_or(body.len());
+    let (digits, suffix_text) = body.split_at(digits_len);
+
+    let suffix = match NumericSuffix::from_suffix(suffix_text) {
+        _ if suffix_text.is_empty() => None,
+        Some(suffix) if !suffix.is_float() || radix == 10 => Some(suffix),
+        _ => {
+            let suffix_start = span.end - suffix_text.len();
+            errors.push(LiteralError::InvalidSuffix.spanned(suffix_start..span.end));
+            None
+        }
+    };
+
+    if !digits.chars().any(|c| c != '_') {
+        errors.push(LiteralError::NoDigits.spanned(span));
+    }
+
+    if let Some(suffix) = suffix
+        && suffix.is_float()
+    {
+        return float(digits, Some(suffix), span);
+    }
+
+    let mut value = Some(0_u64);
+    for (i, digit) in digits.char_indices().filter(|&(_, c)| c != '_') {
+        if let Some(digit_value) = digit.to_digit(radix) {
+            value = value
+                .and_then(|value| value.checked_mul(u64::from(radix)))
+                .and_then(|value| value.checked_add(u64::from(digit_value)));
+        } else {
+            let digit_start = offset + prefix_len + i;
+            errors.push(
+                LiteralError::InvalidDigit { digit, radix }.spanned(digit_start..digit_start + 1),
+            );
+        }
+    }
+
+    let suffix_or_default = suffix.unwrap_or(NumericSuffix::U64);
+    let value = match value {
+        Some(value) if value <= suffix_or_default.max_magnitude(negated) => value,
+        _ => {
+            errors.push(LiteralError::OutOfRange(suffix_or_default).spanned(span));
+            0
+        }
+    };
+
+    (Number::Int(value, suffix), errors)
+}
+
+fn float(digits: &str, suffix: Option<NumericSuffix>, span: Span) -> (Number, Vec<LiteralErrorS>) {
+    let mut errors = Vec::new();
+    let value = f64::from_str(&digits.replace('_', "")).unwrap_or(0.0);
+
+    let suffix_or_default = suffix.unwrap_or(NumericSuffix::F64);
+    let in_range = match suffix_or_default {
+        NumericSuffix::F32 => value <= f64::from(f32::MAX),
+        _ => value.is_finite(),
+    };
+    if !in_range {
+        errors.push(LiteralError::OutOfRange(suffix_or_default).spanned(span));
+    }
+
+    (Number::Float(value, suffix), errors)
+}
+
+/// The text of the string or char literal `token`, along with its invalid parts.
+pub fn string_value(token: Token, source: &str) -> (String, Vec<LiteralErrorS>) {
+    let (start, end) = (token.span.start, token.span.end);
+    let text = &source[start..end];
+    // the length of the closing delimiter, which an unterminated literal doesn't have
+    let unterminated = lex_error(token, source).is_some_and(LexError::is_unterminated);
+    let closing = |len: usize| if unterminated { 0 } else { len };
+
+    match token.inner {
+        TokenType::StringLit
+        | TokenType::StringStart
+        | TokenType::StringMiddle
+        | TokenType::StringEnd
+        | TokenType::CharLit => unescape(&text[1..text.len() - closing(1)], start + 1),
+        TokenType::RawStringLit => {
+            let hashes = text[1..].len() - text[1..].trim_start_matches('#').len();
+            (
+                text[hashes + 2..text.len() - closing(hashes + 1)].into(),
+                Vec::new(),
+            )
+        }
+        TokenType::MultiLineStringLit => multi_line(&text[3..text.len() - closing(3)], start + 3),
+        token => unreachable!("{token} is not a string literal"),
+    }
+}
+
+/// Decodes the `body` of a multi-line string, which starts at `offset` in the source.
+///
+/// The lines between the delimiters are the text of the string. The indentation of the
+/// closing delimiter is removed from the start of each of them, and whitespace only
+/// lines are kept as empty lines.
+fn multi_line(body: &str, offset: usize) -> (String, Vec<LiteralErrorS>) {
+    let mut errors = Vec::new();
+
+    let Some(first_break) = body.find('\n') else {
+        let error = LiteralError::TextAfterOpening.spanned(offset..offset + body.len());
+        return (body.into(), vec![error]);
+    };
+    if !body[..first_break].trim().is_empty() {
+        errors.push(LiteralError::TextAfterOpening.spanned(offset..offset + first_break));
+    }
+
+    let content = &body[first_break + 1..];
+    let content_offset = offset + first_break + 1;
+    let (lines, closing) = content.rsplit_once('\n').unwrap_or(("", content));
+    let indent = &closing[..closing.len() - closing.trim_start().len()];
+    if indent.len() < closing.len() {
+        let start = content_offset + content.len() - closing.len();
+        errors.push(LiteralError::TextBeforeClosing.spanned(start..start + closing.len()));
+    }
+    if content.len() == closing.len() {
+        return (String::new(), errors);
+    }
+
+    let mut decoded = Vec::new();
+    let mut line_offset = content_offset;
+    for raw_line in lines.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        if let Some(rest) = line.strip_prefix(indent) {
+            let (text, line_errors) = unescape(rest, line_offset + indent.len());
+            decoded.push(text);
+            errors.extend(line_errors);
+        } else if line.trim().is_empty() {
+            decoded.push(String::new());
+        } else {
+            let text = line.trim_start();
+            errors.push(LiteralError::Underindented.spanned(line_offset..line_offset + line.len()));
+            decoded.push(unescape(text, line_offset + line.len() - text.len()).0);
+        }
+        line_offset += raw_line.len() + 1;
+    }
+
+    (decoded.join("\n"), errors)
+}
+
+/// Decodes the escapes in `body`, the text between the quotes of a string or char
+/// literal, which starts at `offset` in the source.
+///
+/// Every invalid escape is returned with its span, and stands for U+FFFD in the decoded
+/// text.
+pub fn unescape(body: &str, offset: usize) -> (String, Vec<LiteralErrorS>) {
+    let mut decoded = String::with_capacity(body.len());
+    let mut errors = Vec::new();
+    let mut chars = body.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+
+        let Some((_, escape)) = chars.next() else {
+            // only a literal that is never closed can end on a backslash
+            decoded.push('\\');
+            break;
+        };
+
+        let result = match escape {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            '\\' | '\'' | '"' | '{' | '}' => Ok(escape),
+            'x' => {
+                let digits: String = (0..2)
+                    .map_while(|_| chars.next_if(|(_, c)| c.is_ascii_hexdigit()))
+                    .map(|(_, c)| c)
+                    .collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(value) if digits.len() == 2 && value <= 0x7F => Ok(char::from(value)),
+                    Ok(_) if digits.len() == 2 => Err(LiteralError::HexOutOfRange),
+                    _ => Err(LiteralError::MalformedHex),
+                }
+            }
+            'u' => unicode(&mut chars),
+            other => Err(LiteralError::Unknown(other)),
+        };
+
+        match result {
+            Ok(c) => decoded.push(c),
+            Err(err) => {
+                let end = chars.peek().map_or(body.len(), |&(end, _)| end);
+                errors.push(err.spanned(offset + start..offset + end));
+                decoded.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+    }
+
+    (decoded, errors)
+}
+
+/// Decodes the `{...}` after a `\u`, consuming as much of it as there is.
+fn unicode(
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+) -> Result<char, LiteralError> {
+    if chars.next_if(|&(_, c)| c == '{').is_none() {
+        return Err(LiteralError::MalformedUnicode);
+    }
+
+    let digits: String = std::iter::from_fn(|| chars.next_if(|(_, c)| c.is_ascii_hexdigit()))
+        .map(|(_, c)| c)
+        .collect();
+    if chars.next_if(|&(_, c)| c == '}').is_none() || digits.is_empty() || digits.len() > 6 {
+        return Err(LiteralError::MalformedUnicode);
+    }
+
+    let value = u32::from_str_radix(&digits, 16).unwrap();
+    char::from_u32(value).ok_or(LiteralError::InvalidUnicode(value))
+}
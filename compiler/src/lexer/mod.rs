@@ -1,88 +1,113 @@
-mod rules;
+mod error;
+mod incremental;
+mod literal;
+mod scanner;
 #[cfg(test)]
 mod test;
 mod token;
+mod unicode;
 
+pub use error::{LexError, lex_error};
+pub use incremental::{Relexed, relex};
+pub use literal::{
+    LiteralError, LiteralErrorS, Number, NumericSuffix, number_value, string_value, unescape,
+};
 pub use token::{Token, TokenType};
+pub use unicode::{BOM, Confusable, ConfusableS, confusables, ident_name};
 
 pub struct Lexer<'input> {
     input: &'input str,
     pos: usize,
     eof: bool,
+    /// Whether whitespace and comments are produced as tokens, rather than skipped.
+    trivia: bool,
+    /// How many `{` are open in each string interpolation being lexed, innermost last.
+    interpolations: Vec<usize>,
 }
 
 impl Iterator for Lexer<'_> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
-            if self.eof {
-                return None;
+        loop {
+            if self.pos >= self.input.len() {
+                if self.eof {
+                    return None;
+                }
+                self.eof = true;
+                return Some(TokenType::Eof.spanned(self.pos..self.pos));
+            }
+
+            let token = self.next_token(&self.input[self.pos..]);
+            if self.trivia || !token.inner.is_trivia() {
+                return Some(token);
             }
-            self.eof = true;
-            Some(TokenType::Eof.spanned(self.pos..self.pos))
-        } else {
-            Some(self.next_token(&self.input[self.pos..]))
         }
     }
 }
 
 impl<'input> Lexer<'input> {
+    /// A lexer producing the tokens of `input`, skipping whitespace and comments.
     pub const fn new(input: &'input str) -> Self {
         Self {
             input,
             pos: 0,
             eof: false,
+            trivia: false,
+            interpolations: Vec::new(),
         }
     }
 
-    pub fn next_token(&mut self, input: &str) -> Token {
-        self.valid_token(input)
-            .unwrap_or_else(|| self.invalid_token(input))
+    /// A lexer producing every token of `input`, including whitespace and comments, so
+    /// that their text adds up to the whole input.
+    pub const fn with_trivia(input: &'input str) -> Self {
+        let mut lexer = Self::new(input);
+        lexer.trivia = true;
+        lexer
     }
 
-    /// Returns `None` if the lexer cannot find a token at the start of `input`.
-    fn valid_token(&mut self, input: &str) -> Option<Token> {
-        if input.starts_with("//") {
-            self.pos += input
-                .char_indices()
-                .find(|(_, c)| *c == '\n')
-                .expect("expected newline to terminate comment")
-                .0;
-            self.next()
-        } else if input.chars().next().unwrap().is_whitespace() {
-            self.pos += input
-                .char_indices()
-                .take_while(|(_, c)| c.is_whitespace())
-                .last()
-                .unwrap()
-                .0
-                + 1;
-            self.next()
+    pub fn next_token(&mut self, input: &str) -> Token {
+        let (token, len) = if self.pos == 0 && input.starts_with(BOM) {
+            (TokenType::Whitespace, BOM.len_utf8())
         } else {
-            let (token, len) = rules::RULES
-                .iter()
-                .rev()
-                .filter_map(|rule| rule(input))
-                .max_by_key(|&(_, len)| len)?;
+            self.token_at(input)
+                .unwrap_or_else(|| (TokenType::Error, Self::stray_len(input)))
+        };
+        let token = token.spanned(self.pos..self.pos + len);
+        self.pos += len;
+        track_interpolations(&mut self.interpolations, token.inner);
 
-            let token = token.spanned(self.pos..self.pos + len);
-            self.pos += len;
+        token
+    }
 
-            Some(token)
+    /// The type and length of the token at the start of `input`, or `None` if the lexer
+    /// cannot find one there.
+    fn token_at(&self, input: &str) -> Option<(TokenType, usize)> {
+        if self.interpolations.last() == Some(&0) && input.starts_with('}') {
+            // the end of an interpolation, which carries on with the rest of the string
+            Some(scanner::string_continuation(input))
+        } else {
+            scanner::token(input)
         }
     }
 
-    /// Always "succeeds", because it creates an error `TokenType`.
-    fn invalid_token(&mut self, input: &str) -> Token {
-        let start = self.pos;
-        let len = input
-            .char_indices()
-            .map(|(pos, _)| pos)
-            .find(|pos| self.valid_token(&input[*pos..]).is_some())
-            .unwrap_or(input.len());
+    /// The length of the stray character at the start of `input`, which is an error token
+    /// of its own so that it can be reported and skipped on its own.
+    fn stray_len(input: &str) -> usize {
+        input.chars().next().map_or(0, char::len_utf8)
+    }
+}
 
-        self.pos = start + len;
-        TokenType::Error.spanned(start..self.pos)
+/// Updates the number of `{` open in each string interpolation being lexed, innermost
+/// last, for a `token` that was just lexed.
+fn track_interpolations(interpolations: &mut Vec<usize>, token: TokenType) {
+    match (token, interpolations.last_mut()) {
+        (TokenType::StringStart, _) => interpolations.push(0),
+        (TokenType::StringEnd, _) => {
+            interpolations.pop();
+        }
+        (TokenType::LBrace, Some(depth)) => *depth += 1,
+        (TokenType::RBrace, Some(depth)) => *depth -= 1,
+        _ => {}
     }
 }
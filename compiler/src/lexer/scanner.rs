@@ -0,0 +1,434 @@
+//! Finds the token at the start of some input in a single pass, deciding what it can be
+//! from its first byte and reading no further than the end of it.
+//!
+//! A token that is malformed as a whole, like a string that is never closed, still gets
+//! the type it was meant to have. [`error`] finds out what is wrong with it.
+
+use super::{
+    LexError, TokenType as T,
+    unicode::{is_ident_continue, is_ident_start},
+};
+
+/// The type and length of the token at the start of `input`, or `None` if none starts
+/// there. `input` must not be empty.
+///
+/// Where tokens overlap the longest one is taken, so `letter` is an identifier rather
+/// than `let` followed by `ter`.
+pub(super) fn token(input: &str) -> Option<(T, usize)> {
+    let bytes = input.as_bytes();
+    let followed_by = |byte: u8| bytes.get(1) == Some(&byte);
+
+    let token = match bytes[0] {
+        b'0'..=b'9' => number(bytes),
+        b'.' if bytes.get(1).is_some_and(u8::is_ascii_digit) => (T::FloatLit, float(bytes)?),
+        b'"' if input.starts_with(r#"""""#) => (T::MultiLineStringLit, multi_line_string(bytes).0),
+        b'"' => match string(&bytes[1..]) {
+            Some((len, true)) => (T::StringStart, len + 1),
+            Some((len, false)) => (T::StringLit, len + 1),
+            None => (T::StringLit, line_len(bytes)),
+        },
+        b'\'' => (T::CharLit, char_literal(input).0),
+        b'r' => raw_string(bytes).map_or_else(|| word(input), |(len, _)| (T::RawStringLit, len)),
+        b'a'..=b'z' | b'A'..=b'Z' | b'_' => word(input),
+        b'/' if followed_by(b'/') => line_comment(bytes),
+        b'/' if followed_by(b'*') => (T::Comment, block_comment(bytes).0),
+        b'-' if followed_by(b'>') => (T::Arrow, 2),
+        b'=' if followed_by(b'>') => (T::FatArrow, 2),
+        b'=' if followed_by(b'=') => (T::Eqq, 2),
+        b':' if followed_by(b':') => (T::ColonColon, 2),
+        b'.' if input.starts_with("..=") => (T::DotDotEq, 3),
+        b'.' if followed_by(b'.') => (T::DotDot, 2),
+        b'!' if followed_by(b'=') => (T::Neq, 2),
+        b'*' if followed_by(b'*') => (T::Exponent, 2),
+        b'&' if followed_by(b'&') => (T::And, 2),
+        b'|' if followed_by(b'|') => (T::Or, 2),
+        b'<' if followed_by(b'=') => (T::Leq, 2),
+        b'>' if followed_by(b'=') => (T::Geq, 2),
+        b'[' => (T::LBracket, 1),
+        b']' => (T::RBracket, 1),
+        b'{' => (T::LBrace, 1),
+        b'}' => (T::RBrace, 1),
+        b'(' => (T::LParen, 1),
+        b')' => (T::RParen, 1),
+        b'=' => (T::Eq, 1),
+        b'&' => (T::Ampersand, 1),
+        b'|' => (T::Pipe, 1),
+        b'!' => (T::Bang, 1),
+        b'^' => (T::Xor, 1),
+        b'<' => (T::LAngle, 1),
+        b'>' => (T::RAngle, 1),
+        b'+' => (T::Plus, 1),
+        b'-' => (T::Minus, 1),
+        b'*' => (T::Times, 1),
+        b'/' => (T::FSlash, 1),
+        b'\\' => (T::BSlash, 1),
+        b'.' => (T::Dot, 1),
+        b'@' => (T::At, 1),
+        b',' => (T::Comma, 1),
+        b':' => (T::Colon, 1),
+        b';' => (T::Semicolon, 1),
+        _ if input.starts_with(char::is_whitespace) => {
+            let len = input
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(input.len());
+            (T::Whitespace, len)
+        }
+        _ if input.starts_with(is_ident_start) => word(input),
+        _ => return None,
+    };
+
+    Some(token)
+}
+
+/// What is wrong with the token of type `token` at the start of `input`, if anything.
+pub(super) fn error(token: T, input: &str) -> Option<LexError> {
+    let bytes = input.as_bytes();
+
+    match token {
+        // both start with a single delimiter, `"` or the `}` that ends an interpolation
+        T::StringLit | T::StringEnd => string(&bytes[1..])
+            .is_none()
+            .then_some(LexError::UnterminatedString),
+        T::MultiLineStringLit => multi_line_string(bytes).1,
+        T::RawStringLit => raw_string(bytes)?.1,
+        T::CharLit => char_literal(input).1,
+        T::Comment if input.starts_with("/*") => block_comment(bytes).1,
+        T::Error => input.chars().next().map(LexError::StrayCharacter),
+        _ => None,
+    }
+}
+
+/// The length of the line at the start of `bytes`, without its line break.
+fn line_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .position(|&byte| byte == b'\n' || byte == b'\r')
+        .unwrap_or(bytes.len())
+}
+
+/// How many bytes from `start` on are ASCII digits or `_`.
+fn digits(bytes: &[u8], start: usize) -> usize {
+    bytes[start.min(bytes.len())..]
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_digit() || byte == b'_')
+        .count()
+}
+
+/// A number, which is a float if it has a fractional part or an exponent.
+///
+/// An integer in any base is followed by anything that could be meant as a suffix.
+/// Digits that are too large for the base and unknown suffixes are reported when the
+/// value is read.
+fn number(bytes: &[u8]) -> (T, usize) {
+    let (prefix, is_digit): (usize, fn(&u8) -> bool) = match bytes.get(..2) {
+        Some(b"0x") => (2, u8::is_ascii_hexdigit),
+        Some(b"0o" | b"0b") => (2, u8::is_ascii_digit),
+        _ => (0, u8::is_ascii_digit),
+    };
+    let mut int = prefix
+        + bytes[prefix..]
+            .iter()
+            .take_while(|&byte| is_digit(byte) || *byte == b'_')
+            .count();
+    if bytes
+        .get(int)
+        .is_some_and(|&byte| byte.is_ascii_alphabetic() || byte == b'_')
+    {
+        int += 1 + word_len(&bytes[int + 1..]);
+    }
+
+    // so that `1e10` is a float rather than `1` with a suffix
+    match float(bytes) {
+        Some(float) if float >= int => (T::FloatLit, float),
+        _ => (T::IntLit, int),
+    }
+}
+
+/// The length of the float at the start of `bytes`, which starts with a digit or a `.`.
+/// It needs a fractional part or an exponent, and may end with `f32` or `f64`.
+fn float(bytes: &[u8]) -> Option<usize> {
+    let mut len = digits(bytes, 0);
+
+    if bytes.get(len) == Some(&b'.') {
+        let fraction = len + 1;
+        // the fraction may only be left out after a whole part, as in `1.`, and not before
+        // another `.`, so that `1..` is `1` followed by `..`
+        if !bytes.get(fraction).is_some_and(u8::is_ascii_digit)
+            && (len == 0 || bytes.get(fraction) == Some(&b'.'))
+        {
+            return None;
+        }
+        len = fraction;
+        if bytes.get(len).is_some_and(u8::is_ascii_digit) {
+            len += digits(bytes, len);
+        }
+        len = exponent(bytes, len).unwrap_or(len);
+    } else {
+        len = exponent(bytes, len)?;
+    }
+
+    if matches!(bytes.get(len..len + 3), Some(b"f32" | b"f64")) {
+        len += 3;
+    }
+
+    Some(len)
+}
+
+/// The end of the exponent that starts at `start`, like `e10` or `E-3`, if there is one.
+fn exponent(bytes: &[u8], start: usize) -> Option<usize> {
+    if !matches!(bytes.get(start), Some(b'e' | b'E')) {
+        return None;
+    }
+
+    let mut len = start + 1;
+    if matches!(bytes.get(len), Some(b'+' | b'-')) {
+        len += 1;
+    }
+    len += bytes[len.min(bytes.len())..]
+        .iter()
+        .take_while(|&&byte| byte == b'_')
+        .count();
+
+    bytes
+        .get(len)
+        .is_some_and(u8::is_ascii_digit)
+        .then(|| len + digits(bytes, len))
+}
+
+/// How many bytes at the start of `bytes` can continue an identifier.
+fn word_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_alphanumeric() || byte == b'_')
+        .count()
+}
+
+/// An identifier, or a keyword if the whole word is one.
+fn word(input: &str) -> (T, usize) {
+    let first = input.chars().next().map_or(0, char::len_utf8);
+    let len = input[first..]
+        .find(|c: char| !is_ident_continue(c))
+        .map_or(input.len(), |len| first + len);
+
+    let token = match &input[..len] {
+        "_" => T::Underscore,
+        "let" => T::Let,
+        "mut" => T::Mut,
+        "const" => T::Const,
+        "fn" => T::Fn,
+        "struct" => T::Struct,
+        "enum" => T::Enum,
+        "if" => T::If,
+        "else" => T::Else,
+        "match" => T::Match,
+        "true" => T::True,
+        "false" => T::False,
+        _ => T::Ident,
+    };
+
+    (token, len)
+}
+
+/// The text of a string after the delimiter before it, up to and including the `"` that
+/// ends the string or the `{` that starts an interpolation, and which of the two it was.
+fn string(bytes: &[u8]) -> Option<(usize, bool)> {
+    let mut pos = 0;
+    while let Some(&byte) = bytes.get(pos) {
+        match byte {
+            b'\\' => pos = skip_escape(bytes, pos + 1),
+            b'"' => return Some((pos + 1, false)),
+            b'{' => return Some((pos + 1, true)),
+            _ => pos += 1,
+        }
+    }
+
+    None
+}
+
+/// Where the rest of an escape ends, given where it starts after its `\\`. The braces
+/// of a `\\u{...}` escape are part of it, so they don't start an interpolation.
+fn skip_escape(bytes: &[u8], start: usize) -> usize {
+    if bytes.get(start..start + 2) != Some(b"u{") {
+        // a multi-byte character after the `\\` only matters for its first byte
+        return start + 1;
+    }
+
+    let mut pos = start + 2;
+    while bytes
+        .get(pos)
+        .is_some_and(|&byte| byte != b'}' && byte != b'"')
+    {
+        pos += 1;
+    }
+    if bytes.get(pos) == Some(&b'}') {
+        pos += 1;
+    }
+
+    pos
+}
+
+/// The rest of a string after the `}` that ends an interpolation in it, up to the next
+/// interpolation or the end of the string. A string that is never closed ends at the end
+/// of the line.
+pub(super) fn string_continuation(input: &str) -> (T, usize) {
+    let bytes = input.as_bytes();
+    match string(&bytes[1..]) {
+        Some((len, true)) => (T::StringMiddle, len + 1),
+        Some((len, false)) => (T::StringEnd, len + 1),
+        None => (T::StringEnd, line_len(bytes)),
+    }
+}
+
+/// A multi-line string, from `"""` up to the next `"""` that isn't escaped, or the end
+/// of the input if there is none.
+fn multi_line_string(bytes: &[u8]) -> (usize, Option<LexError>) {
+    let mut pos = 3;
+    while pos < bytes.len() {
+        if bytes[pos] == b'\\' {
+            pos += 2;
+        } else if bytes[pos..].starts_with(br#"""""#) {
+            return (pos + 3, None);
+        } else {
+            pos += 1;
+        }
+    }
+
+    (bytes.len(), Some(LexError::UnterminatedString))
+}
+
+/// A raw string, `r"..."`, which may have any number of `#`s around the quotes so that
+/// it can contain `"` followed by fewer of them.
+fn raw_string(bytes: &[u8]) -> Option<(usize, Option<LexError>)> {
+    let hashes = bytes[1..].iter().take_while(|&&byte| byte == b'#').count();
+    let body = 1 + hashes;
+    if bytes.get(body) != Some(&b'"') {
+        return None;
+    }
+
+    let closed = (body + 1..bytes.len()).find(|&pos| {
+        bytes[pos] == b'"'
+            && bytes[pos + 1..].len() >= hashes
+            && bytes[pos + 1..pos + 1 + hashes]
+                .iter()
+                .all(|&byte| byte == b'#')
+    });
+
+    // one that is never closed runs to the end of the input
+    Some(
+        closed.map_or((bytes.len(), Some(LexError::UnterminatedString)), |pos| {
+            (pos + 1 + hashes, None)
+        }),
+    )
+}
+
+/// A char literal, which is malformed if it isn't [`one_char`] in quotes.
+///
+/// One with more than one character ends at the next `'` on its line. If there is none,
+/// it is unterminated and ends after its first character.
+fn char_literal(input: &str) -> (usize, Option<LexError>) {
+    if let Some(len) = one_char(input) {
+        return (len, None);
+    }
+
+    let bytes = input.as_bytes();
+    if bytes.get(1) == Some(&b'\'') {
+        return (2, Some(LexError::EmptyChar));
+    }
+
+    let mut pos = 1;
+    while let Some(&byte) = bytes.get(pos)
+        && byte != b'\n'
+    {
+        match byte {
+            b'\\' if bytes.get(pos + 1).is_some_and(|&byte| byte != b'\n') => pos += 2,
+            b'\'' => return (pos + 1, Some(LexError::MultipleChars)),
+            _ => pos += 1,
+        }
+    }
+
+    // the character after the quote, or both characters of a simple escape
+    let on_line = |c: &char| !matches!(c, '\n' | '\r');
+    let len = match input[1..].chars().next().filter(on_line) {
+        None => 0,
+        Some('\\') => {
+            1 + input[2..]
+                .chars()
+                .next()
+                .filter(on_line)
+                .map_or(0, char::len_utf8)
+        }
+        Some(c) => c.len_utf8(),
+    };
+    (1 + len, Some(LexError::UnterminatedChar))
+}
+
+/// The length of a well-formed char literal: one character, or a `\\` followed by the
+/// rest of an escape, in single quotes.
+fn one_char(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+
+    if bytes.get(1) == Some(&b'\\') {
+        let escape = bytes[2..]
+            .iter()
+            .take_while(|&&byte| byte != b'\'' && byte != b'\n')
+            .count();
+        return if escape > 0 && bytes.get(2 + escape) == Some(&b'\'') {
+            Some(2 + escape + 1)
+        } else if bytes.get(2..4) == Some(b"''") {
+            Some(4)
+        } else {
+            None
+        };
+    }
+
+    let c = input[1..]
+        .chars()
+        .next()
+        .filter(|&c| c != '\'' && c != '\n')?;
+    let len = 1 + c.len_utf8();
+    (bytes.get(len) == Some(&b'\'')).then_some(len + 1)
+}
+
+/// A `//` comment, which runs to the end of its line. `///` starts a doc comment, but
+/// `////` is just a comment, and `//!` documents the file it is in.
+fn line_comment(bytes: &[u8]) -> (T, usize) {
+    let token = if bytes.starts_with(b"///") && !bytes.starts_with(b"////") {
+        T::DocComment
+    } else if bytes.starts_with(b"//!") {
+        T::InnerDocComment
+    } else {
+        T::Comment
+    };
+
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .unwrap_or(bytes.len());
+    (token, len)
+}
+
+/// The block comment at the start of `bytes`, which may contain other block comments.
+/// One that is never closed runs to the end of the input.
+fn block_comment(bytes: &[u8]) -> (usize, Option<LexError>) {
+    let mut depth = 0;
+    let mut pos = 0;
+
+    while pos + 1 < bytes.len() {
+        match &bytes[pos..pos + 2] {
+            b"/*" => {
+                depth += 1;
+                pos += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                pos += 2;
+                if depth == 0 {
+                    return (pos, None);
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+
+    (bytes.len(), Some(LexError::UnterminatedComment))
+}
@@ -1,4 +1,6 @@
-use super::{Lexer, Token, TokenType as T};
+use crate::helpers::Span;
+
+use super::{LexError, Lexer, Relexed, Token, TokenType as T, confusables, lex_error, relex};
 
 macro_rules! count {
     () => (0usize);
@@ -43,15 +45,18 @@ fn single_char_tokens() {
 
 #[test]
 fn unknown_input() {
-    let mut lexer = Lexer::new("{$$$$$$$+");
+    // each stray character is a token of its own
+    let mut lexer = Lexer::new("{$$$+");
     let tokens = tokenize(&mut lexer);
     assert_tokens!(
         tokens,
         [
             T::LBrace.spanned(0..1),
-            T::Error.spanned(1..8),
-            T::Plus.spanned(8..9),
-            T::Eof.spanned(9..9),
+            T::Error.spanned(1..2),
+            T::Error.spanned(2..3),
+            T::Error.spanned(3..4),
+            T::Plus.spanned(4..5),
+            T::Eof.spanned(5..5),
         ]
     );
 }
@@ -76,7 +81,7 @@ fn single_char_tokens_with_whitespace() {
 
 #[test]
 fn maybe_multiple_char_tokens() {
-    let mut lexer = Lexer::new("&&=<=_!=||**->");
+    let mut lexer = Lexer::new("&&=<=_!=||**->=>::..@..=");
     let tokens = tokenize(&mut lexer);
     assert_tokens!(
         tokens,
@@ -89,7 +94,12 @@ fn maybe_multiple_char_tokens() {
             T::Or.spanned(8..10),
             T::Exponent.spanned(10..12),
             T::Arrow.spanned(12..14),
-            T::Eof.spanned(14..14),
+            T::FatArrow.spanned(14..16),
+            T::ColonColon.spanned(16..18),
+            T::DotDot.spanned(18..20),
+            T::At.spanned(20..21),
+            T::DotDotEq.spanned(21..24),
+            T::Eof.spanned(24..24),
         ]
     );
 }
@@ -162,7 +172,7 @@ fn function() {
             }
         }
     "#;
-    let mut lexer = Lexer::new(&input);
+    let mut lexer = Lexer::new(input);
     let tokens = tokenize(&mut lexer);
     assert_tokens!(
         tokens,
@@ -246,3 +256,468 @@ fn function() {
         ]
     );
 }
+
+#[test]
+fn trivia() {
+    let input = "let x // note\n\t= 1 // at the end";
+    let mut lexer = Lexer::with_trivia(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::Let.spanned(0..3),
+            T::Whitespace.spanned(3..4),
+            T::Ident.spanned(4..5),
+            T::Whitespace.spanned(5..6),
+            T::Comment.spanned(6..13),
+            T::Whitespace.spanned(13..15),
+            T::Eq.spanned(15..16),
+            T::Whitespace.spanned(16..17),
+            T::IntLit.spanned(17..18),
+            T::Whitespace.spanned(18..19),
+            T::Comment.spanned(19..32),
+            T::Eof.spanned(32..32),
+        ]
+    );
+
+    // without trivia, a comment can still end the input
+    let mut lexer = Lexer::new(input);
+    assert_eq!(tokenize(&mut lexer).len(), 5);
+}
+
+#[test]
+fn block_comments() {
+    let input = "a /* one /* two */ still one */ b /*/ unclosed";
+    let mut lexer = Lexer::with_trivia(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::Ident.spanned(0..1),
+            T::Whitespace.spanned(1..2),
+            T::Comment.spanned(2..31),
+            T::Whitespace.spanned(31..32),
+            T::Ident.spanned(32..33),
+            T::Whitespace.spanned(33..34),
+            T::Comment.spanned(34..46),
+            T::Eof.spanned(46..46),
+        ]
+    );
+
+    let mut lexer = Lexer::new("1 /**/ / 2");
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::IntLit.spanned(0..1),
+            T::FSlash.spanned(7..8),
+            T::IntLit.spanned(9..10),
+            T::Eof.spanned(10..10),
+        ]
+    );
+}
+
+#[test]
+fn doc_comments() {
+    let input = "//! file\n/// item\n//// plain\n// plain";
+    let mut lexer = Lexer::with_trivia(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::InnerDocComment.spanned(0..8),
+            T::Whitespace.spanned(8..9),
+            T::DocComment.spanned(9..17),
+            T::Whitespace.spanned(17..18),
+            T::Comment.spanned(18..28),
+            T::Whitespace.spanned(28..29),
+            T::Comment.spanned(29..37),
+            T::Eof.spanned(37..37),
+        ]
+    );
+}
+
+#[test]
+fn escapes_stay_in_literals() {
+    // invalid escapes are reported by the parser, the literal is still one token
+    let mut lexer = Lexer::new(r#""a\"\q" '\u{1F600}' '\''"#);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::StringLit.spanned(0..7),
+            T::CharLit.spanned(8..19),
+            T::CharLit.spanned(20..24),
+            T::Eof.spanned(24..24),
+        ]
+    );
+}
+
+#[test]
+fn raw_and_multi_line_strings() {
+    let input = r###"r"\d+" r#"say "hi""# r##"a"#b"## r """
+  "quoted" \"""
+  """ r#"open"###;
+    let mut lexer = Lexer::new(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::RawStringLit.spanned(0..6),
+            T::RawStringLit.spanned(7..20),
+            T::RawStringLit.spanned(21..32),
+            T::Ident.spanned(33..34),
+            T::MultiLineStringLit.spanned(35..60),
+            T::RawStringLit.spanned(61..68),
+            T::Eof.spanned(68..68),
+        ]
+    );
+}
+
+#[test]
+fn longest_match() {
+    let input = "letter let _a _ r#x r\"a\" 1e10x 1.e5 1.5f3 x.0 -> - § 1";
+    let mut lexer = Lexer::new(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::Ident.spanned(0..6),
+            T::Let.spanned(7..10),
+            T::Ident.spanned(11..13),
+            T::Underscore.spanned(14..15),
+            T::Ident.spanned(16..17),
+            T::Error.spanned(17..18),
+            T::Ident.spanned(18..19),
+            T::RawStringLit.spanned(20..24),
+            T::IntLit.spanned(25..30),
+            T::FloatLit.spanned(31..35),
+            T::FloatLit.spanned(36..39),
+            T::Ident.spanned(39..41),
+            T::Ident.spanned(42..43),
+            T::FloatLit.spanned(43..45),
+            T::Arrow.spanned(46..48),
+            T::Minus.spanned(49..50),
+            T::Error.spanned(51..53),
+            T::IntLit.spanned(54..55),
+            T::Eof.spanned(55..55),
+        ]
+    );
+}
+
+#[test]
+fn unicode_identifiers() {
+    // spans are in bytes, so they grow by more than one for each multi-byte character
+    let input = "naïve 変数 _x1 e\u{301}t 🦀 ℕ0";
+    let mut lexer = Lexer::new(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::Ident.spanned(0..6),
+            T::Ident.spanned(7..13),
+            T::Ident.spanned(14..17),
+            // the combining accent continues the identifier
+            T::Ident.spanned(18..22),
+            T::Error.spanned(23..27),
+            T::Ident.spanned(28..32),
+            T::Eof.spanned(32..32),
+        ]
+    );
+
+    // a byte order mark is whitespace at the start of a file, and invalid anywhere else
+    let mut lexer = Lexer::with_trivia("\u{FEFF}let \u{FEFF}");
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::Whitespace.spanned(0..3),
+            T::Let.spanned(3..6),
+            T::Whitespace.spanned(6..7),
+            T::Error.spanned(7..10),
+            T::Eof.spanned(10..10),
+        ]
+    );
+}
+
+#[test]
+fn confusable_characters() {
+    let input = "const раth = 1\u{FF1B} переменная \u{201C}hi\u{201D}";
+    let found: Vec<_> = confusables(input)
+        .iter()
+        .map(|c| {
+            (
+                c.inner.found,
+                c.inner.looks_like,
+                &input[c.span.start..c.span.end],
+            )
+        })
+        .collect();
+    // identifiers that wouldn't look ASCII anyway are left alone
+    assert_eq!(
+        found,
+        [
+            ('р', 'p', "р"),
+            ('а', 'a', "а"),
+            ('\u{FF1B}', ';', "\u{FF1B}"),
+            ('\u{201C}', '"', "\u{201C}"),
+            ('\u{201D}', '"', "\u{201D}"),
+        ]
+    );
+}
+
+#[test]
+fn number_literals() {
+    let input = "0xFF 0o17 0b1010_0101 1_000_000 10u8 1.5f32 1e10 2.5E-3 0b102 0x 3abc 1f32";
+    let mut lexer = Lexer::new(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::IntLit.spanned(0..4),
+            T::IntLit.spanned(5..9),
+            T::IntLit.spanned(10..21),
+            T::IntLit.spanned(22..31),
+            T::IntLit.spanned(32..36),
+            T::FloatLit.spanned(37..43),
+            T::FloatLit.spanned(44..48),
+            T::FloatLit.spanned(49..55),
+            // invalid digits and suffixes are reported by the parser
+            T::IntLit.spanned(56..61),
+            T::IntLit.spanned(62..64),
+            T::IntLit.spanned(65..69),
+            T::IntLit.spanned(70..74),
+            T::Eof.spanned(74..74),
+        ]
+    );
+
+    // a `.` followed by another isn't the end of a float
+    let tokens: Vec<_> = Lexer::new("1..2.").collect();
+    assert_tokens!(
+        tokens,
+        [
+            T::IntLit.spanned(0..1),
+            T::DotDot.spanned(1..3),
+            T::FloatLit.spanned(3..5),
+            T::Eof.spanned(5..5),
+        ]
+    );
+}
+
+#[test]
+fn interpolation() {
+    let input = r#""a {b + "{c}"} \{d} {{e: 1}}}" }"#;
+    let mut lexer = Lexer::new(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::StringStart.spanned(0..4),
+            T::Ident.spanned(4..5),
+            T::Plus.spanned(6..7),
+            T::StringStart.spanned(8..10),
+            T::Ident.spanned(10..11),
+            T::StringEnd.spanned(11..13),
+            T::StringMiddle.spanned(13..21),
+            T::LBrace.spanned(21..22),
+            T::Ident.spanned(22..23),
+            T::Colon.spanned(23..24),
+            T::IntLit.spanned(25..26),
+            T::RBrace.spanned(26..27),
+            T::StringEnd.spanned(27..30),
+            // outside of the string, `}` is a brace again
+            T::RBrace.spanned(31..32),
+            T::Eof.spanned(32..32),
+        ]
+    );
+
+    // the braces of a unicode escape are part of the text
+    let mut lexer = Lexer::new(r#""\u{41} {x}""#);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::StringStart.spanned(0..9),
+            T::Ident.spanned(9..10),
+            T::StringEnd.spanned(10..12),
+            T::Eof.spanned(12..12),
+        ]
+    );
+}
+
+#[test]
+fn malformed_tokens() {
+    let input = "x = '' 'ab' 'c + 1\n$ \"open\n/* open";
+    let mut lexer = Lexer::new(input);
+    let tokens = tokenize(&mut lexer);
+    assert_tokens!(
+        tokens,
+        [
+            T::Ident.spanned(0..1),
+            T::Eq.spanned(2..3),
+            T::CharLit.spanned(4..6),
+            T::CharLit.spanned(7..11),
+            // an unclosed char literal ends after its first character
+            T::CharLit.spanned(12..14),
+            T::Plus.spanned(15..16),
+            T::IntLit.spanned(17..18),
+            T::Error.spanned(19..20),
+            // an unclosed string ends at the end of its line
+            T::StringLit.spanned(21..26),
+            T::Eof.spanned(34..34),
+        ]
+    );
+
+    let errors: Vec<_> = tokens
+        .iter()
+        .filter_map(|&token| Some((lex_error(token, input)?, token.span.start)))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (LexError::EmptyChar, 4),
+            (LexError::MultipleChars, 7),
+            (LexError::UnterminatedChar, 12),
+            (LexError::StrayCharacter('$'), 19),
+            (LexError::UnterminatedString, 21),
+        ]
+    );
+
+    // an unclosed block comment runs to the end of the input
+    let tokens: Vec<_> = Lexer::with_trivia(input).collect();
+    let comment = tokens[tokens.len() - 2];
+    assert_eq!(comment, T::Comment.spanned(27..34));
+    assert_eq!(
+        lex_error(comment, input),
+        Some(LexError::UnterminatedComment)
+    );
+
+    // as does the rest of a string after an interpolation, at the end of its line
+    let input = "\"a {b} c\nd";
+    let tokens: Vec<_> = Lexer::new(input).collect();
+    assert_eq!(tokens[2], T::StringEnd.spanned(5..8));
+    assert_eq!(
+        lex_error(tokens[2], input),
+        Some(LexError::UnterminatedString)
+    );
+
+    // `patina lex` reports each of them, the unclosed comment included
+    let input = "x = '' 'ab' 'c + 1\n$ \"open\n/* open";
+    let Err(errors) = crate::lex(input) else {
+        panic!("{input:?} lexed without errors");
+    };
+    let spans: Vec<_> = errors
+        .iter()
+        .map(|err| (err.code(), err.span.start))
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            ("E0007", 4),
+            ("E0007", 7),
+            ("E0006", 12),
+            ("E0001", 19),
+            ("E0006", 21),
+            ("E0006", 27),
+        ]
+    );
+    let input = "x = 1 // fine";
+    assert_eq!(crate::lex(input), Ok(crate::tokenize(input)));
+}
+
+/// Re-lexes `old` after replacing the text at `edit` with `replacement`, checking that
+/// the result is the same as lexing the edited source from scratch.
+fn relex_checked(old: &str, edit: Span, replacement: &str) -> Relexed {
+    let new = [&old[..edit.start], replacement, &old[edit.end..]].concat();
+    let old_tokens: Vec<_> = Lexer::with_trivia(old).collect();
+    let relexed = relex(&old_tokens, old, &new, edit);
+
+    let moved = new.len().wrapping_sub(old.len());
+    let mut tokens = old_tokens[..relexed.replaced.start].to_vec();
+    tokens.extend(&relexed.tokens);
+    tokens.extend(old_tokens[relexed.replaced.end..].iter().map(|token| {
+        let span = token.span.start.wrapping_add(moved)..token.span.end.wrapping_add(moved);
+        token.inner.spanned(span)
+    }));
+    let expected: Vec<_> = Lexer::with_trivia(&new).collect();
+    assert_eq!(tokens, expected, "relexing {old:?} into {new:?}");
+
+    relexed
+}
+
+#[test]
+fn relexing() {
+    let input = "a = 1\nbc = 2 + 3\nd = 4";
+
+    // only the tokens the edit touches are lexed again
+    let relexed = relex_checked(input, (6..8).into(), "xyz");
+    assert_eq!(relexed.replaced, 6..7);
+    assert_eq!(relexed.tokens, [T::Ident.spanned(6..9)]);
+    let relexed = relex_checked(input, (15..15).into(), "0");
+    assert_eq!(relexed.replaced, 14..15);
+    assert_eq!(relexed.tokens, [T::IntLit.spanned(15..17)]);
+
+    // splitting and joining tokens
+    let relexed = relex_checked(input, (7..7).into(), " ");
+    assert_eq!(relexed.replaced, 6..7);
+    assert_eq!(relexed.tokens.len(), 3);
+    relex_checked(input, (5..6).into(), "");
+    relex_checked(input, (13..14).into(), "/");
+    relex_checked(input, (13..14).into(), "//");
+
+    // an opened string or comment takes in what comes after it
+    relex_checked(input, (9..9).into(), "\"");
+    relex_checked(input, (9..9).into(), "\"\"\"");
+    relex_checked(input, (9..9).into(), "/*");
+    relex_checked("a = \"b\nc = \"d\"", (6..6).into(), "\"");
+    relex_checked("a = \"b\nc = \"d\"", (4..5).into(), "");
+    relex_checked("a = \"b\nc = d", (12..12).into(), "\"");
+    relex_checked("a = 1 /* b */ c", (8..8).into(), "*/");
+
+    // edits inside interpolations
+    let input = "s = \"a {b + \"c {d}\"} e\"\nf";
+    relex_checked(input, (9..10).into(), "- x");
+    relex_checked(input, (17..18).into(), "");
+    relex_checked(input, (18..18).into(), "}");
+    relex_checked(input, (20..21).into(), "");
+    relex_checked(input, (4..5).into(), "");
+    relex_checked(input, (24..25).into(), "");
+}
+
+#[test]
+fn relexing_many_edits() {
+    const SNIPPETS: &[&str] = &[
+        "", " ", "\n", "x", "12", "\"", "\"\"\"", "'", "{", "}", "/*", "*/", "//", "r#\"", "\"#",
+        "\\", "$", "é", "+ 1",
+    ];
+    let mut source = String::from(
+        "fn main() -> {\n    let s = \"a {b} c\" // note\n    'x' + 1.5e3 /* block */\n}\n",
+    );
+
+    // a fixed pseudo-random sequence of edits, each made to the result of the last
+    let mut state = 0x2545_f491_u32;
+    let mut next = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as usize % bound
+    };
+    for _ in 0..500 {
+        let boundaries: Vec<_> = source
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([source.len()])
+            .collect();
+        let start = boundaries[next(boundaries.len())];
+        let end = boundaries[next(boundaries.len())].max(start).min(start + 4);
+        let end = if source.is_char_boundary(end) {
+            end
+        } else {
+            start
+        };
+        let replacement = SNIPPETS[next(SNIPPETS.len())];
+
+        relex_checked(&source, (start..end).into(), replacement);
+        source.replace_range(start..end, replacement);
+    }
+}
@@ -18,6 +18,17 @@ pub enum TokenType {
     IntLit,
     FloatLit,
     StringLit,
+    /// `"...{`, the start of a string up to its first interpolation.
+    StringStart,
+    /// `}...{`, the part of a string between two interpolations.
+    StringMiddle,
+    /// `}..."`, the end of a string after its last interpolation.
+    StringEnd,
+    /// `r"..."` or `r#"..."#`, in which `\\` is just a backslash.
+    RawStringLit,
+    /// `"""` followed by lines of text and another `"""`, which the text is indented
+    /// relative to.
+    MultiLineStringLit,
     CharLit,
     // Delimiters
     LParen,
@@ -39,11 +50,21 @@ pub enum TokenType {
     FSlash,
     BSlash,
     Dot,
+    /// `..`, the rest of a list in a pattern, or a range that leaves out its end.
+    DotDot,
+    /// `..=`, a range pattern that includes its end.
+    DotDotEq,
     Comma,
     Colon,
+    /// `::`, between an enum and one of its variants.
+    ColonColon,
     Semicolon,
     Underscore,
     Arrow,
+    /// `=>`, between a `match` arm's pattern and its body.
+    FatArrow,
+    /// `@`, between a name and the pattern it binds.
+    At,
     // Operators
     Exponent,
     And,
@@ -65,12 +86,60 @@ pub enum TokenType {
     Match,
     True,
     False,
+    // Trivia
+    Whitespace,
+    /// A `//` line comment or a `/* */` block comment.
+    Comment,
+    /// A `///` comment documenting the item after it.
+    DocComment,
+    /// A `//!` comment documenting the file it is in.
+    InnerDocComment,
     // Misc
     Ident,
     Error,
     Eof,
 }
 
+impl TokenType {
+    /// Whether every token of this type has the same text, which is how it is displayed.
+    pub const fn is_fixed(self) -> bool {
+        !matches!(
+            self,
+            Self::IntLit
+                | Self::FloatLit
+                | Self::StringLit
+                | Self::StringStart
+                | Self::StringMiddle
+                | Self::StringEnd
+                | Self::RawStringLit
+                | Self::MultiLineStringLit
+                | Self::CharLit
+                | Self::Whitespace
+                | Self::Comment
+                | Self::DocComment
+                | Self::InnerDocComment
+                | Self::Ident
+                | Self::Error
+                | Self::Eof
+        )
+    }
+
+    /// Whether this is whitespace or a comment, which only matter to tools that need to
+    /// reproduce the source exactly.
+    pub const fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            Self::Whitespace | Self::Comment | Self::DocComment | Self::InnerDocComment
+        )
+    }
+
+    /// Whether the parser passes over tokens of this type without them being part of
+    /// the syntax, which is the case for trivia and stray characters.
+    pub const fn is_skipped(self) -> bool {
+        self.is_trivia() || matches!(self, Self::Error)
+    }
+}
+
 impl Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -80,6 +149,11 @@ impl Display for TokenType {
                 Self::IntLit => "int literal",
                 Self::FloatLit => "float literal",
                 Self::StringLit => "string literal",
+                Self::StringStart => "string start",
+                Self::StringMiddle => "string middle",
+                Self::StringEnd => "string end",
+                Self::RawStringLit => "raw string literal",
+                Self::MultiLineStringLit => "multi-line string literal",
                 Self::CharLit => "char literal",
                 Self::LParen => "(",
                 Self::RParen => ")",
@@ -99,11 +173,16 @@ impl Display for TokenType {
                 Self::FSlash => "/",
                 Self::BSlash => "\\",
                 Self::Dot => ".",
+                Self::DotDot => "..",
+                Self::DotDotEq => "..=",
                 Self::Comma => ",",
                 Self::Colon => ":",
+                Self::ColonColon => "::",
                 Self::Semicolon => ";",
                 Self::Underscore => "_",
                 Self::Arrow => "->",
+                Self::FatArrow => "=>",
+                Self::At => "@",
                 Self::Exponent => "**",
                 Self::And => "&&",
                 Self::Or => "||",
@@ -123,6 +202,10 @@ impl Display for TokenType {
                 Self::Match => "match",
                 Self::True => "true",
                 Self::False => "false",
+                Self::Whitespace => "whitespace",
+                Self::Comment => "comment",
+                Self::DocComment => "doc comment",
+                Self::InnerDocComment => "inner doc comment",
                 Self::Ident => "identifier",
                 Self::Error => "ERROR",
                 Self::Eof => "eof",
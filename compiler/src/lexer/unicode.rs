@@ -0,0 +1,186 @@
+use std::fmt::Display;
+
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick};
+
+use crate::span;
+
+use super::{Lexer, TokenType};
+
+/// The byte order mark, which is skipped as whitespace at the start of a file.
+pub const BOM: char = '\u{FEFF}';
+
+/// Whether `c` can start an identifier, which is the case for `_` and anything Unicode
+/// counts as `XID_Start`, mostly letters.
+pub fn is_ident_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+/// Whether `c` can appear in an identifier after its first character, which is the
+/// case for anything Unicode counts as `XID_Continue`, like letters, digits and
+/// combining marks.
+pub fn is_ident_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// The name `text`, an identifier token, stands for. Identifiers are compared in NFC,
+/// so `é` written as one character and as `e` followed by a combining accent are the
+/// same name.
+pub fn ident_name(text: &str) -> String {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        text.into()
+    } else {
+        text.nfc().collect()
+    }
+}
+
+span! {Confusable as ConfusableS}
+/// A character that looks like an ASCII one, which it could be mistaken for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confusable {
+    pub found: char,
+    /// The Unicode name of `found`.
+    pub name: &'static str,
+    pub looks_like: char,
+}
+
+impl Confusable {
+    /// The diagnostic code of the warning.
+    pub const fn code(self) -> &'static str {
+        "E0008"
+    }
+}
+
+impl Display for Confusable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` ({}, U+{:04X}) looks like `{}`, but is a different character",
+            self.found,
+            self.name,
+            u32::from(self.found),
+            self.looks_like
+        )
+    }
+}
+
+/// Characters that are easily mistaken for ASCII ones, with their names and the
+/// character each looks like. Fullwidth forms are handled separately.
+const CONFUSABLES: &[(char, &str, char)] = &[
+    ('\u{0430}', "Cyrillic small letter a", 'a'),
+    ('\u{0441}', "Cyrillic small letter es", 'c'),
+    ('\u{0501}', "Cyrillic small letter komi de", 'd'),
+    ('\u{0435}', "Cyrillic small letter ie", 'e'),
+    ('\u{04BB}', "Cyrillic small letter shha", 'h'),
+    (
+        '\u{0456}',
+        "Cyrillic small letter Byelorussian-Ukrainian i",
+        'i',
+    ),
+    ('\u{0131}', "Latin small letter dotless i", 'i'),
+    ('\u{0458}', "Cyrillic small letter je", 'j'),
+    ('\u{043E}', "Cyrillic small letter o", 'o'),
+    ('\u{03BF}', "Greek small letter omicron", 'o'),
+    ('\u{0440}', "Cyrillic small letter er", 'p'),
+    ('\u{03C1}', "Greek small letter rho", 'p'),
+    ('\u{0455}', "Cyrillic small letter dze", 's'),
+    ('\u{03BD}', "Greek small letter nu", 'v'),
+    ('\u{0445}', "Cyrillic small letter ha", 'x'),
+    ('\u{0443}', "Cyrillic small letter u", 'y'),
+    ('\u{0410}', "Cyrillic capital letter a", 'A'),
+    ('\u{0391}', "Greek capital letter alpha", 'A'),
+    ('\u{0412}', "Cyrillic capital letter ve", 'B'),
+    ('\u{0392}', "Greek capital letter beta", 'B'),
+    ('\u{0421}', "Cyrillic capital letter es", 'C'),
+    ('\u{0415}', "Cyrillic capital letter ie", 'E'),
+    ('\u{0395}', "Greek capital letter epsilon", 'E'),
+    ('\u{041D}', "Cyrillic capital letter en", 'H'),
+    ('\u{0397}', "Greek capital letter eta", 'H'),
+    (
+        '\u{0406}',
+        "Cyrillic capital letter Byelorussian-Ukrainian i",
+        'I',
+    ),
+    ('\u{0399}', "Greek capital letter iota", 'I'),
+    ('\u{041A}', "Cyrillic capital letter ka", 'K'),
+    ('\u{039A}', "Greek capital letter kappa", 'K'),
+    ('\u{041C}', "Cyrillic capital letter em", 'M'),
+    ('\u{039C}', "Greek capital letter mu", 'M'),
+    ('\u{039D}', "Greek capital letter nu", 'N'),
+    ('\u{041E}', "Cyrillic capital letter o", 'O'),
+    ('\u{039F}', "Greek capital letter omicron", 'O'),
+    ('\u{0420}', "Cyrillic capital letter er", 'P'),
+    ('\u{03A1}', "Greek capital letter rho", 'P'),
+    ('\u{0422}', "Cyrillic capital letter te", 'T'),
+    ('\u{03A4}', "Greek capital letter tau", 'T'),
+    ('\u{0425}', "Cyrillic capital letter ha", 'X'),
+    ('\u{03A7}', "Greek capital letter chi", 'X'),
+    ('\u{0396}', "Greek capital letter zeta", 'Z'),
+    ('\u{037E}', "Greek question mark", ';'),
+    ('\u{2010}', "hyphen", '-'),
+    ('\u{2011}', "non-breaking hyphen", '-'),
+    ('\u{2012}', "figure dash", '-'),
+    ('\u{2013}', "en dash", '-'),
+    ('\u{2014}', "em dash", '-'),
+    ('\u{2212}', "minus sign", '-'),
+    ('\u{2018}', "left single quotation mark", '\''),
+    ('\u{2019}', "right single quotation mark", '\''),
+    ('\u{201C}', "left double quotation mark", '"'),
+    ('\u{201D}', "right double quotation mark", '"'),
+    ('\u{201A}', "single low-9 quotation mark", ','),
+    ('\u{0589}', "Armenian full stop", ':'),
+    ('\u{02F8}', "modifier letter raised colon", ':'),
+    ('\u{01C3}', "Latin letter retroflex click", '!'),
+    ('\u{02C2}', "modifier letter left arrowhead", '<'),
+    ('\u{02C3}', "modifier letter right arrowhead", '>'),
+    ('\u{2217}', "asterisk operator", '*'),
+    ('\u{2215}', "division slash", '/'),
+    ('\u{2044}', "fraction slash", '/'),
+];
+
+/// The confusable `c` is, if it is one.
+fn confusable(c: char) -> Option<Confusable> {
+    if let Some(&(found, name, looks_like)) = CONFUSABLES.iter().find(|(found, ..)| *found == c) {
+        return Some(Confusable {
+            found,
+            name,
+            looks_like,
+        });
+    }
+
+    // the fullwidth forms of `!` to `~`
+    let looks_like = char::from_u32(u32::from(c).checked_sub(0xFEE0)?)?;
+    ('!'..='~').contains(&looks_like).then_some(Confusable {
+        found: c,
+        name: "fullwidth form",
+        looks_like,
+    })
+}
+
+/// Every character in `source` that looks like an ASCII one where it could be mistaken
+/// for it: in identifiers that would look all ASCII, and in invalid text.
+pub fn confusables(source: &str) -> Vec<ConfusableS> {
+    let mut found = Vec::new();
+
+    for token in Lexer::new(source) {
+        let text = &source[token.span.start..token.span.end];
+        let looks_ascii = match token.inner {
+            TokenType::Ident => text
+                .chars()
+                .all(|c| c.is_ascii() || confusable(c).is_some()),
+            TokenType::Error => true,
+            _ => false,
+        };
+        if !looks_ascii {
+            continue;
+        }
+
+        for (i, c) in text.char_indices() {
+            if let Some(confusable) = confusable(c) {
+                let start = token.span.start + i;
+                found.push(confusable.spanned(start..start + c.len_utf8()));
+            }
+        }
+    }
+
+    found
+}
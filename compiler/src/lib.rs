@@ -0,0 +1,157 @@
+//! The Patina compiler front end.
+//!
+//! Source text flows through the [`lexer`], [`parser`] and [`typecheck`] stages in that
+//! order. Each stage can be driven directly, or the functions at the root of the crate
+//! can be used to run them back to back.
+
+use std::{error, fmt::Display};
+
+use lexer::{Lexer, Token};
+use parser::{ParseError, ParseErrorKind, Parser, ast::Ast, cst::Node};
+use typecheck::{Type, TypeChecker, TypeErrorS, TypeResult, TypeWarningS};
+
+pub mod diagnostics;
+pub mod format;
+pub mod helpers;
+pub mod lexer;
+pub mod lsp;
+pub mod parser;
+pub mod typecheck;
+
+/// The diagnostics that stopped a source file from compiling.
+#[derive(Debug)]
+pub enum Error {
+    /// Every syntax error in the file, of which there is at least one.
+    Parse(Vec<ParseError>),
+    Type(TypeErrorS),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(errs) => {
+                for (i, err) in errs.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    err.fmt(f)?;
+                }
+                Ok(())
+            }
+            Self::Type(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Self::Parse(vec![value])
+    }
+}
+
+impl From<Vec<ParseError>> for Error {
+    fn from(value: Vec<ParseError>) -> Self {
+        Self::Parse(value)
+    }
+}
+
+impl From<TypeErrorS> for Error {
+    fn from(value: TypeErrorS) -> Self {
+        Self::Type(value)
+    }
+}
+
+/// A parsed and type checked source file.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub ast: Ast,
+    /// The type of each item in `ast`, in the same order.
+    pub types: Vec<Type>,
+    /// Whatever type checks but is likely a mistake, like an unreachable `match` arm.
+    pub warnings: Vec<TypeWarningS>,
+}
+
+/// Splits `source` into tokens, ending with a single `Eof` token.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    Lexer::new(source).collect()
+}
+
+/// Like [`tokenize`], but keeps whitespace and comments as tokens too.
+pub fn tokenize_with_trivia(source: &str) -> Vec<Token> {
+    Lexer::with_trivia(source).collect()
+}
+
+/// Like [`tokenize`], but fails if any token is malformed as a whole.
+///
+/// # Errors
+/// Returns an error for each malformed token, comments included, as [`parse`] reports them.
+pub fn lex(source: &str) -> Result<Vec<Token>, Vec<ParseError>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for token in Lexer::with_trivia(source) {
+        if let Some(err) = lexer::lex_error(token, source) {
+            errors.push(ParseError {
+                kind: ParseErrorKind::InvalidToken(err),
+                span: token.span,
+                context: Vec::new(),
+                missing_at: None,
+            });
+        }
+        if !token.inner.is_trivia() {
+            tokens.push(token);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses `source` as a sequence of items.
+///
+/// # Errors
+/// Returns every syntax error in `source`.
+pub fn parse(source: &str) -> Result<Ast, Vec<ParseError>> {
+    Parser::new(source).file()
+}
+
+/// Parses as much of `source` as possible, returning a tree in which anything that failed
+/// to parse is replaced by an error node, along with every syntax error found.
+pub fn parse_partial(source: &str) -> (Ast, Vec<ParseError>) {
+    Parser::new(source).partial_file()
+}
+
+/// Parses `source` into a concrete syntax tree, which holds every token of `source`,
+/// whitespace and comments included, along with every syntax error found.
+pub fn parse_tree(source: &str) -> (Node, Vec<ParseError>) {
+    Parser::new(source).syntax_tree()
+}
+
+/// Type checks every item of `ast`, returning the type of each one in order.
+///
+/// # Errors
+/// Returns the first type error in `ast`.
+pub fn check(ast: &Ast) -> TypeResult<Vec<Type>> {
+    TypeChecker::new(ast).check_items(ast)
+}
+
+/// Runs every front end stage over `source`.
+///
+/// # Errors
+/// Returns every syntax error in `source`, or the first type error if there are none.
+pub fn compile(source: &str) -> Result<Program, Error> {
+    let ast = parse(source)?;
+    let mut checker = TypeChecker::new(&ast);
+    let types = checker.check_items(&ast)?;
+    let warnings = checker.warnings().to_vec();
+
+    Ok(Program {
+        ast,
+        types,
+        warnings,
+    })
+}
@@ -0,0 +1,485 @@
+use crate::{
+    diagnostics::Diagnostic,
+    helpers::Span,
+    lexer::{Lexer, TokenType, confusables},
+    parser::{
+        ParsedFile,
+        ast::{
+            Ast, Binding, BindingS, Expr, ExprS, Item, ItemS, Pattern, PatternS, StringPart,
+            Variant,
+        },
+    },
+    typecheck::{Type, TypeChecker},
+};
+
+/// The kinds of names that symbols and completions are reported for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Constant,
+    Struct,
+    Enum,
+    Field,
+    Variant,
+    Variable,
+    Keyword,
+}
+
+/// A named part of a document, for its outline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The whole definition.
+    pub span: Span,
+    /// Just the name in the definition.
+    pub name_span: Span,
+    pub children: Vec<Self>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: SymbolKind,
+}
+
+const KEYWORDS: &[&str] = &[
+    "const", "else", "enum", "false", "fn", "if", "let", "match", "mut", "struct", "true",
+];
+
+/// Everything the language server knows about one version of a document.
+pub struct Analysis {
+    /// The document's source and syntax tree, kept to re-parse it incrementally.
+    file: ParsedFile,
+    ast: Ast,
+    diagnostics: Vec<Diagnostic>,
+    /// The type of each item in `ast`, if it could be checked.
+    item_types: Vec<Option<Type>>,
+    span_types: Vec<(Span, Type)>,
+}
+
+impl Analysis {
+    pub fn new(source: String) -> Self {
+        Self::from_file(ParsedFile::new(source))
+    }
+
+    /// Analyses a document that is already parsed.
+    pub fn from_file(file: ParsedFile) -> Self {
+        let ast = file.ast();
+        let parse_errors = file.errors();
+        let mut diagnostics: Vec<Diagnostic> = parse_errors.iter().map(Diagnostic::from).collect();
+
+        // items are checked one at a time so that an error in one doesn't hide the types,
+        // or the errors, of the others
+        let mut checker = TypeChecker::new(&ast);
+        let item_types = ast
+            .iter()
+            .map(|item| match checker.check_item(item) {
+                Ok(ty) => Some(checker.resolve(&ty)),
+                Err(err) => {
+                    // type errors in a file with syntax errors are mostly noise
+                    if parse_errors.is_empty() {
+                        diagnostics.push((&err).into());
+                    }
+                    None
+                }
+            })
+            .collect();
+        if let Err(err) = checker.check_deferred()
+            && parse_errors.is_empty()
+        {
+            diagnostics.push((&err).into());
+        }
+        let span_types = checker.span_types();
+        diagnostics.extend(checker.warnings().iter().map(Diagnostic::from));
+        diagnostics.extend(confusables(file.source()).iter().map(Diagnostic::from));
+
+        Self {
+            file,
+            ast,
+            diagnostics,
+            item_types,
+            span_types,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        self.file.source()
+    }
+
+    /// The parsed document, to be edited and analysed again.
+    pub fn into_file(self) -> ParsedFile {
+        self.file
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// A description of what is at `offset`, along with the span it describes.
+    pub fn hover(&self, offset: usize) -> Option<(Span, String)> {
+        for (item, ty) in self.ast.iter().zip(&self.item_types) {
+            let name_span = name_span(self.file.source(), item.span);
+            if !name_span.touches(offset) {
+                continue;
+            }
+
+            let keyword = match &item.inner {
+                Item::Const { .. } => "const",
+                Item::Function { .. } => "fn",
+                Item::Struct { .. } => "struct",
+                Item::Enum { .. } => "enum",
+                Item::Error => return None,
+            };
+            let text = match (&item.inner, ty) {
+                (Item::Struct { .. } | Item::Enum { .. }, Some(ty)) => format!("{keyword} {ty}"),
+                (_, Some(ty)) => format!("{keyword} {}: {ty}", item.inner.name()),
+                (_, None) => format!("{keyword} {}", item.inner.name()),
+            };
+            return Some((name_span, text));
+        }
+
+        let lookup = self.lookup(offset);
+        let (span, name) = lookup.innermost?;
+        let (_, ty) = self.span_types.iter().rev().find(|(s, _)| *s == span)?;
+
+        let text = name.map_or_else(|| ty.to_string(), |name| format!("{name}: {ty}"));
+        Some((span, text))
+    }
+
+    /// The span of the name defining the identifier at `offset`.
+    pub fn definition(&self, offset: usize) -> Option<Span> {
+        self.lookup(offset).reference?.1
+    }
+
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.ast
+            .iter()
+            .filter_map(|item| {
+                let children = match &item.inner {
+                    Item::Struct { fields, .. } => fields
+                        .iter()
+                        .map(|field| self.symbol(&field.inner.name, SymbolKind::Field, field.span))
+                        .collect(),
+                    Item::Enum { variants, .. } => variants
+                        .iter()
+                        .map(|variant| {
+                            let (Variant::Unit(name)
+                            | Variant::Tuple(name, _)
+                            | Variant::Struct(name, _)) = &variant.inner;
+                            self.symbol(name, SymbolKind::Variant, variant.span)
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                Some(Symbol {
+                    children,
+                    ..self.symbol(item.inner.name(), item_kind(&item.inner)?, item.span)
+                })
+            })
+            .collect()
+    }
+
+    fn symbol(&self, name: &str, kind: SymbolKind, span: Span) -> Symbol {
+        Symbol {
+            name: name.into(),
+            kind,
+            span,
+            name_span: name_span(self.file.source(), span),
+            children: Vec::new(),
+        }
+    }
+
+    /// Keywords and the names in scope at `offset`.
+    pub fn completions(&self, offset: usize) -> Vec<Completion> {
+        let lookup = self.lookup(offset);
+
+        let mut completions: Vec<Completion> = Vec::new();
+        for (name, _, kind) in lookup.visible.iter().rev() {
+            // inner bindings shadow outer ones
+            if !completions
+                .iter()
+                .any(|completion| completion.label == *name)
+            {
+                completions.push(Completion {
+                    label: name.clone(),
+                    kind: *kind,
+                });
+            }
+        }
+        completions.extend(KEYWORDS.iter().map(|keyword| Completion {
+            label: (*keyword).into(),
+            kind: SymbolKind::Keyword,
+        }));
+
+        completions
+    }
+
+    fn lookup(&self, offset: usize) -> Lookup<'_> {
+        let mut lookup = Lookup {
+            source: self.file.source(),
+            offset,
+            scope: Vec::new(),
+            visible: Vec::new(),
+            innermost: None,
+            reference: None,
+        };
+
+        for item in &self.ast {
+            let Some(kind) = item_kind(&item.inner) else {
+                continue;
+            };
+            lookup.scope.push((
+                item.inner.name().into(),
+                name_span(self.file.source(), item.span),
+                kind,
+            ));
+        }
+        lookup.visible = lookup.scope.clone();
+
+        for item in &self.ast {
+            lookup.item(item);
+        }
+
+        lookup
+    }
+}
+
+const fn item_kind(item: &Item) -> Option<SymbolKind> {
+    match item {
+        Item::Const { .. } => Some(SymbolKind::Constant),
+        Item::Function { .. } => Some(SymbolKind::Function),
+        Item::Struct { .. } => Some(SymbolKind::Struct),
+        Item::Enum { .. } => Some(SymbolKind::Enum),
+        Item::Error => None,
+    }
+}
+
+/// The span of the first identifier within `span`, which is the name of the item,
+/// binding, field or variant it covers.
+fn name_span(source: &str, span: Span) -> Span {
+    Lexer::new(&source[span.start..span.end])
+        .find(|token| token.inner == TokenType::Ident)
+        .map_or(span, |token| {
+            (span.start + token.span.start..span.start + token.span.end).into()
+        })
+}
+
+/// Walks the syntax tree towards an offset, keeping track of the names in scope.
+struct Lookup<'src> {
+    source: &'src str,
+    offset: usize,
+    /// Names in scope at the current point of the walk, innermost last, with the span
+    /// of the name that defines each.
+    scope: Vec<(String, Span, SymbolKind)>,
+    /// The names in scope at the innermost node touching the offset.
+    visible: Vec<(String, Span, SymbolKind)>,
+    /// The span of the innermost expression or binding touching the offset, and the
+    /// name it refers to or defines, if any.
+    innermost: Option<(Span, Option<String>)>,
+    /// An identifier touching the offset, and the span of its definition if it has one.
+    reference: Option<(Span, Option<Span>)>,
+}
+
+impl Lookup<'_> {
+    fn resolve(&self, name: &str) -> Option<Span> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(candidate, ..)| candidate == name)
+            .map(|(_, span, _)| *span)
+    }
+
+    fn refer(&mut self, name: &str, span: Span) {
+        if span.touches(self.offset) {
+            self.reference = Some((span, self.resolve(name)));
+        }
+    }
+
+    /// Walks `f` in a new scope, which is dropped afterwards.
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        let len = self.scope.len();
+        f(self);
+        self.scope.truncate(len);
+    }
+
+    fn item(&mut self, item: &ItemS) {
+        if !item.span.touches(self.offset) {
+            return;
+        }
+
+        match &item.inner {
+            Item::Const { value, .. } => self.expr(value),
+            Item::Function { params, body, .. } => self.scoped(|this| {
+                for param in params {
+                    this.binding(param);
+                }
+                this.expr(body);
+            }),
+            Item::Struct { .. } | Item::Enum { .. } | Item::Error => {}
+        }
+    }
+
+    fn binding(&mut self, binding: &BindingS) {
+        match &binding.inner {
+            Binding::Var { ident, .. } => self.define(ident, binding.span),
+            Binding::Pattern { pattern, .. } => self.pattern(pattern),
+        }
+    }
+
+    /// Brings the variable `ident` defined at `span` into scope.
+    fn define(&mut self, ident: &str, span: Span) {
+        if span.touches(self.offset) {
+            self.innermost = Some((span, Some(ident.to_owned())));
+        }
+        self.scope.push((
+            ident.to_owned(),
+            name_span(self.source, span),
+            SymbolKind::Variable,
+        ));
+    }
+
+    fn pattern(&mut self, pattern: &PatternS) {
+        match &pattern.inner {
+            Pattern::Wildcard
+            | Pattern::Rest
+            | Pattern::Literal(_)
+            | Pattern::Range { .. }
+            | Pattern::Path(_) => {}
+            Pattern::Binding {
+                ident, subpattern, ..
+            } => {
+                self.define(ident, pattern.span);
+                if let Some(subpattern) = subpattern {
+                    self.pattern(subpattern);
+                }
+            }
+            Pattern::Tuple(elements)
+            | Pattern::Array(elements)
+            | Pattern::TupleVariant { elements, .. } => {
+                for element in elements {
+                    self.pattern(element);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for field in fields {
+                    self.pattern(&field.inner.pattern);
+                }
+            }
+            Pattern::Or(alternatives) => {
+                // every alternative binds the same names, which are defined by the first
+                let (first, others) = alternatives.split_first().unwrap();
+                self.pattern(first);
+                let len = self.scope.len();
+                for alternative in others {
+                    self.pattern(alternative);
+                    self.scope.truncate(len);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ExprS) {
+        if !expr.span.touches(self.offset) {
+            // the binding is still in scope for whatever follows
+            if let Expr::Let { binding, .. } = &expr.inner {
+                self.binding(binding);
+            }
+            return;
+        }
+
+        self.visible = self.scope.clone();
+        self.innermost = Some((expr.span, None));
+
+        match &expr.inner {
+            Expr::Ident(name) => {
+                self.innermost = Some((expr.span, Some(name.clone())));
+                self.refer(name, expr.span);
+            }
+            Expr::Int(..)
+            | Expr::Float(..)
+            | Expr::Str(_)
+            | Expr::Char(_)
+            | Expr::Bool(_)
+            | Expr::Placeholder
+            | Expr::Path(_)
+            | Expr::Error => {}
+            Expr::Array(exprs) | Expr::Tuple(exprs) => exprs.iter().for_each(|e| self.expr(e)),
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Expr(e) = part {
+                        self.expr(e);
+                    }
+                }
+            }
+            Expr::FnCall { fun, args } => {
+                self.expr(fun);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::BinaryOp { lhs, rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::UnaryOp { expr, .. } => self.expr(expr),
+            Expr::Index { arr, index } => {
+                self.expr(arr);
+                self.expr(index);
+            }
+            Expr::FieldAccess { base, .. } => self.expr(base),
+            Expr::If { cond, th, el } => {
+                self.expr(cond);
+                self.expr(th);
+                if let Some(el) = el {
+                    self.expr(el);
+                }
+            }
+            Expr::Let { binding, value } => {
+                self.expr(value);
+                self.binding(binding);
+            }
+            Expr::Assign { ident, value } => {
+                self.refer(&ident.inner, ident.span);
+                self.expr(value);
+            }
+            Expr::Lambda { params, body, .. } => self.scoped(|this| {
+                for param in params {
+                    this.binding(param);
+                }
+                this.expr(body);
+            }),
+            Expr::Match { scrutinee, arms } => {
+                self.expr(scrutinee);
+                for arm in arms {
+                    self.scoped(|this| {
+                        this.pattern(&arm.inner.pattern);
+                        if let Some(guard) = &arm.inner.guard {
+                            this.expr(guard);
+                        }
+                        this.expr(&arm.inner.body);
+                    });
+                }
+            }
+            Expr::Struct { fields, base, .. } => {
+                for field in fields {
+                    self.expr(&field.inner.value);
+                }
+                if let Some(base) = base {
+                    self.expr(base);
+                }
+            }
+            Expr::Block { exprs, .. } => self.scoped(|this| {
+                for e in exprs {
+                    this.expr(e);
+
+                    // between statements, whatever came before is in scope
+                    if e.span.end < this.offset {
+                        this.visible = this.scope.clone();
+                    }
+                }
+            }),
+        }
+    }
+}
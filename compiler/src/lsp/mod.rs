@@ -0,0 +1,398 @@
+//! A language server for Patina, speaking the language server protocol over stdio.
+//!
+//! Documents are re-analysed whenever they change: edits are re-parsed incrementally
+//! with error recovery, the whole document is type checked item by item, and the result
+//! answers every request until the next change.
+
+mod analysis;
+mod rpc;
+#[cfg(test)]
+mod test;
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    iter,
+};
+
+use serde_json::{Value, json};
+
+use crate::{
+    diagnostics::{Diagnostic, Severity, SourceMap},
+    helpers::Span,
+    parser::ParsedFile,
+};
+
+pub use analysis::{Analysis, Completion, Symbol, SymbolKind};
+
+// error codes defined by JSON-RPC and the protocol
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Why a request failed, sent back to the client in place of a result.
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl ResponseError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+}
+
+/// Serves requests read from `input` until the client exits, writing responses and
+/// notifications to `output`.
+///
+/// Returns whether the client shut the server down before exiting, as it should have.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<bool> {
+    let mut server = Server::default();
+
+    while let Some(content) = rpc::read_message(&mut input)? {
+        let replies = match serde_json::from_slice(&content) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error_response(
+                &Value::Null,
+                &ResponseError {
+                    code: PARSE_ERROR,
+                    message: err.to_string(),
+                },
+            )],
+        };
+
+        for reply in &replies {
+            rpc::write_message(&mut output, reply)?;
+        }
+
+        if server.exited {
+            break;
+        }
+    }
+
+    Ok(server.shut_down)
+}
+
+#[derive(Default)]
+struct Server {
+    /// Open documents by URI.
+    documents: HashMap<String, Analysis>,
+    shut_down: bool,
+    exited: bool,
+}
+
+impl Server {
+    /// Handles one message, returning the messages to send back.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // a response to a request of ours, which we never send
+            return Vec::new();
+        };
+        let params = &message["params"];
+
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+
+        let result = if self.shut_down {
+            Err(ResponseError {
+                code: INVALID_REQUEST,
+                message: "the server has been shut down".into(),
+            })
+        } else {
+            self.request(method, params)
+        };
+
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => error_response(id, &err),
+        }]
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, ResponseError> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // changes are sent as edits to ranges of the document
+                    "textDocumentSync": 2,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "patina", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => {
+                let (uri, analysis, offset) = self.position(params)?;
+                let map = SourceMap::new(uri, analysis.source());
+
+                Ok(analysis.hover(offset).map_or(Value::Null, |(span, text)| {
+                    json!({
+                        "contents": {
+                            "kind": "markdown",
+                            "value": format!("```patina\n{text}\n```"),
+                        },
+                        "range": range(&map, span),
+                    })
+                }))
+            }
+            "textDocument/definition" => {
+                let (uri, analysis, offset) = self.position(params)?;
+                let map = SourceMap::new(uri, analysis.source());
+
+                Ok(analysis.definition(offset).map_or(
+                    Value::Null,
+                    |span| json!({ "uri": uri, "range": range(&map, span) }),
+                ))
+            }
+            "textDocument/documentSymbol" => {
+                let (uri, analysis) = self.document(params)?;
+                let map = SourceMap::new(uri, analysis.source());
+
+                Ok(analysis
+                    .symbols()
+                    .iter()
+                    .map(|symbol| symbol_json(&map, symbol))
+                    .collect())
+            }
+            "textDocument/completion" => {
+                let (_, analysis, offset) = self.position(params)?;
+
+                Ok(analysis
+                    .completions(offset)
+                    .into_iter()
+                    .map(|completion| {
+                        json!({
+                            "label": completion.label,
+                            "kind": completion_kind(completion.kind),
+                        })
+                    })
+                    .collect())
+            }
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unsupported method `{method}`"),
+            }),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        match method {
+            "exit" => {
+                self.exited = true;
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, ParsedFile::new(text.into()))
+            }
+            "textDocument/didChange" => {
+                let Some(changes) = params["contentChanges"].as_array() else {
+                    return Vec::new();
+                };
+                let mut file = self
+                    .documents
+                    .remove(uri)
+                    .map_or_else(|| ParsedFile::new(String::new()), Analysis::into_file);
+
+                // each change applies to the document as the ones before it left it
+                for change in changes {
+                    let text = change["text"].as_str().unwrap_or_default();
+                    let map = SourceMap::new(uri, file.source());
+                    let range = &change["range"];
+                    match (
+                        utf16_offset(&map, &range["start"]),
+                        utf16_offset(&map, &range["end"]),
+                    ) {
+                        (Some(start), Some(end)) => file.edit((start..end.max(start)).into(), text),
+                        // a change without a range replaces the whole document
+                        _ => file = ParsedFile::new(text.into()),
+                    }
+                }
+                self.update(uri, file)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, &[])]
+            }
+            // `initialized`, `$/cancelRequest` and anything else we don't act on
+            _ => Vec::new(),
+        }
+    }
+
+    /// Re-analyses the document at `uri`, returning its new diagnostics.
+    fn update(&mut self, uri: &str, file: ParsedFile) -> Vec<Value> {
+        let analysis = Analysis::from_file(file);
+        let map = SourceMap::new(uri, analysis.source());
+
+        let diagnostics: Vec<_> = analysis
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                let span = diagnostic.span().unwrap_or(Span { start: 0, end: 0 });
+                let severity = match diagnostic.severity {
+                    Severity::Error => 1,
+                    Severity::Warning => 2,
+                    Severity::Note => 3,
+                };
+
+                let mut value = json!({
+                    "range": range(&map, span),
+                    "severity": severity,
+                    "code": diagnostic.code,
+                    "source": "patina",
+                    "message": full_message(diagnostic),
+                });
+                let related: Vec<_> = diagnostic
+                    .labels
+                    .iter()
+                    .filter(|label| !label.primary)
+                    .map(|label| {
+                        json!({
+                            "location": { "uri": uri, "range": range(&map, label.span) },
+                            "message": label.message,
+                        })
+                    })
+                    .collect();
+                if !related.is_empty() {
+                    value["relatedInformation"] = related.into();
+                }
+                value
+            })
+            .collect();
+
+        self.documents.insert(uri.into(), analysis);
+        vec![publish_diagnostics(uri, &diagnostics)]
+    }
+
+    /// The open document named by the `textDocument` parameter.
+    fn document<'a>(&self, params: &'a Value) -> Result<(&'a str, &Analysis), ResponseError> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("missing `textDocument`"))?;
+        let analysis = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| ResponseError::invalid_params(format!("`{uri}` is not open")))?;
+
+        Ok((uri, analysis))
+    }
+
+    /// The open document and byte offset named by the `textDocument` and `position`
+    /// parameters.
+    fn position<'a>(
+        &self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &Analysis, usize), ResponseError> {
+        let (uri, analysis) = self.document(params)?;
+
+        let map = SourceMap::new(uri, analysis.source());
+        let offset = utf16_offset(&map, &params["position"])
+            .ok_or_else(|| ResponseError::invalid_params("missing `position`"))?;
+
+        Ok((uri, analysis, offset))
+    }
+}
+
+/// The byte offset of a protocol `position`, which counts columns in UTF-16 code units.
+fn utf16_offset(map: &SourceMap<'_>, position: &Value) -> Option<usize> {
+    let line = position["line"].as_u64()?;
+    let character = position["character"].as_u64()?;
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "documents can't have more lines or columns than fit in memory"
+    )]
+    Some(map.utf16_offset(line as usize, character as usize))
+}
+
+fn error_response(id: &Value, err: &ResponseError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": err.code, "message": err.message },
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: &[Value]) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// The message of `diagnostic` followed by what its primary labels and notes say, which
+/// editors only show the message of.
+fn full_message(diagnostic: &Diagnostic) -> String {
+    let labels = diagnostic
+        .labels
+        .iter()
+        .filter(|label| label.primary && !label.message.is_empty())
+        .map(|label| label.message.clone());
+    let notes = diagnostic.notes.iter().map(|note| format!("note: {note}"));
+
+    iter::once(diagnostic.message.clone())
+        .chain(labels)
+        .chain(notes)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn range(map: &SourceMap<'_>, span: Span) -> Value {
+    let position = |offset| {
+        let (line, character) = map.utf16_position(offset);
+        json!({ "line": line, "character": character })
+    };
+
+    json!({ "start": position(span.start), "end": position(span.end) })
+}
+
+fn symbol_json(map: &SourceMap<'_>, symbol: &Symbol) -> Value {
+    let kind = match symbol.kind {
+        SymbolKind::Function => 12,
+        SymbolKind::Constant => 14,
+        SymbolKind::Struct => 23,
+        SymbolKind::Enum => 10,
+        SymbolKind::Field => 8,
+        SymbolKind::Variant => 22,
+        SymbolKind::Variable => 13,
+        SymbolKind::Keyword => 20,
+    };
+    let children: Vec<_> = symbol
+        .children
+        .iter()
+        .map(|child| symbol_json(map, child))
+        .collect();
+
+    json!({
+        "name": symbol.name,
+        "kind": kind,
+        "range": range(map, symbol.span),
+        "selectionRange": range(map, symbol.name_span),
+        "children": children,
+    })
+}
+
+const fn completion_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Function => 3,
+        SymbolKind::Constant => 21,
+        SymbolKind::Struct => 22,
+        SymbolKind::Enum => 13,
+        SymbolKind::Field => 5,
+        SymbolKind::Variant => 20,
+        SymbolKind::Variable => 6,
+        SymbolKind::Keyword => 14,
+    }
+}
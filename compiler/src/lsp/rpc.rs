@@ -0,0 +1,53 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Reads the content of one message framed by a `Content-Length` header, returning
+/// `None` once the input ends between messages.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return if length.is_none() {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        // other headers, like `Content-Type`, don't change anything
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            let value = value.trim().parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid `Content-Length`")
+            })?;
+            length = Some(value);
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing `Content-Length` header",
+        )
+    })?;
+
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+
+    Ok(Some(content))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
@@ -0,0 +1,410 @@
+use serde_json::{Value, json};
+
+use super::{Analysis, Completion, SymbolKind, rpc, run};
+use crate::{diagnostics::Severity, helpers::Span};
+
+const SOURCE: &str = "\
+fn add(a: Int, b: Int): Int -> a + b
+const three: Int = add(1, 2)
+struct Point { x: Int, y: Int }
+fn main() -> {
+    let mut total = three;
+    total = add(total, 1);
+    total
+}";
+
+/// The offset of the `nth` occurrence of `needle` in [`SOURCE`].
+fn offset(needle: &str, nth: usize) -> usize {
+    SOURCE.match_indices(needle).nth(nth).unwrap().0
+}
+
+fn span_of(needle: &str, nth: usize) -> Span {
+    let start = offset(needle, nth);
+    (start..start + needle.len()).into()
+}
+
+#[test]
+fn hover_types() {
+    let analysis = Analysis::new(SOURCE.into());
+    assert_eq!(analysis.diagnostics(), []);
+
+    assert_eq!(
+        analysis.hover(offset("add", 0) + 1),
+        Some((span_of("add", 0), "fn add: fn(Int, Int): Int".into()))
+    );
+    assert_eq!(
+        analysis.hover(offset("Point", 0)),
+        Some((span_of("Point", 0), "struct Point".into()))
+    );
+    assert_eq!(
+        analysis.hover(offset("total", 2)),
+        Some((span_of("total", 2), "total: Int".into()))
+    );
+    // the binding itself, `mut` and all
+    assert_eq!(
+        analysis.hover(offset("total", 0)),
+        Some((span_of("mut total", 0), "total: Int".into()))
+    );
+    assert_eq!(
+        analysis.hover(offset("+", 0)),
+        Some((span_of("a + b", 0), "Int".into()))
+    );
+    assert_eq!(analysis.hover(0), None);
+}
+
+#[test]
+fn go_to_definition() {
+    let analysis = Analysis::new(SOURCE.into());
+
+    assert_eq!(
+        analysis.definition(offset("three", 1)),
+        Some(span_of("three", 0))
+    );
+    assert_eq!(
+        analysis.definition(offset("add", 1)),
+        Some(span_of("add", 0))
+    );
+    // both the assigned and the read `total` lead to the `let`
+    assert_eq!(
+        analysis.definition(offset("total", 1)),
+        Some(span_of("total", 0))
+    );
+    assert_eq!(
+        analysis.definition(offset("total", 2) + 5),
+        Some(span_of("total", 0))
+    );
+    assert_eq!(
+        analysis.definition(offset("a + b", 0)),
+        Some(span_of("a", 1))
+    );
+    assert_eq!(analysis.definition(offset("Int", 0)), None);
+
+    let analysis = Analysis::new("fn f(x: Int) -> { let x = x; x }".into());
+    assert_eq!(analysis.definition(26), Some((5..6).into()));
+    assert_eq!(analysis.definition(29), Some((22..23).into()));
+
+    // the names in an or-pattern are defined by its first alternative
+    let analysis = Analysis::new(
+        "fn f(p: (Int, Int)) -> match p { (a, b) | (b, a) if a > 0 => a + b, _ => 0 }".into(),
+    );
+    assert_eq!(analysis.definition(52), Some((34..35).into()));
+    assert_eq!(analysis.definition(61), Some((34..35).into()));
+    assert_eq!(analysis.hover(46), Some(((46..47).into(), "a: Int".into())));
+
+    // and so are the names in a destructuring `let` or parameter
+    let analysis =
+        Analysis::new("fn f((a, b): (Int, Int)) -> { let (c, _) = (a, 1); b + c }".into());
+    assert_eq!(analysis.definition(44), Some((6..7).into()));
+    assert_eq!(analysis.definition(51), Some((9..10).into()));
+    assert_eq!(analysis.definition(55), Some((35..36).into()));
+    assert_eq!(analysis.hover(55), Some(((55..56).into(), "c: Int".into())));
+}
+
+#[test]
+fn document_symbols() {
+    let analysis = Analysis::new(SOURCE.into());
+    let symbols = analysis.symbols();
+
+    let outline: Vec<_> = symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.children.len()))
+        .collect();
+    assert_eq!(
+        outline,
+        [
+            ("add", SymbolKind::Function, 0),
+            ("three", SymbolKind::Constant, 0),
+            ("Point", SymbolKind::Struct, 2),
+            ("main", SymbolKind::Function, 0),
+        ]
+    );
+    assert_eq!(symbols[2].name_span, span_of("Point", 0));
+    assert_eq!(symbols[2].children[1].name, "y");
+    assert_eq!(symbols[2].children[1].name_span, span_of("y", 0));
+}
+
+#[test]
+fn completions_in_scope() {
+    let source = "fn f(a: Int) -> {\n    let b = a;\n    \n}\nconst c: Int = 1";
+    let analysis = Analysis::new(source.into());
+    let names = |offset| -> Vec<Completion> {
+        analysis
+            .completions(offset)
+            .into_iter()
+            .filter(|completion| completion.kind != SymbolKind::Keyword)
+            .collect()
+    };
+    let completion = |label: &str, kind| Completion {
+        label: label.into(),
+        kind,
+    };
+
+    // on the empty line after the `let`
+    assert_eq!(
+        names(37),
+        [
+            completion("b", SymbolKind::Variable),
+            completion("a", SymbolKind::Variable),
+            completion("c", SymbolKind::Constant),
+            completion("f", SymbolKind::Function),
+        ]
+    );
+    // in the value of the `let`, before `b` exists
+    assert_eq!(names(30).len(), 3);
+    // outside of any item
+    assert_eq!(names(source.len()).len(), 2);
+
+    assert!(
+        analysis
+            .completions(37)
+            .contains(&completion("let", SymbolKind::Keyword))
+    );
+}
+
+#[test]
+fn analysis_of_broken_documents() {
+    let analysis = Analysis::new("fn f(a: Int) -> { a + }\nfn g() -> f(true)".into());
+    let codes: Vec<_> = analysis
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| diagnostic.code)
+        .collect();
+    // the type error in `g` isn't reported on top of the syntax error
+    assert_eq!(codes, [Some("E0102")]);
+    assert_eq!(analysis.symbols().len(), 2);
+
+    let analysis = Analysis::new("fn f(a: Int) -> a\nfn g() -> f(true)\nfn h() -> x".into());
+    let codes: Vec<_> = analysis
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| diagnostic.code)
+        .collect();
+    assert_eq!(codes, [Some("E0202"), Some("E0201")]);
+
+    // warnings are reported alongside the type errors of other items
+    let analysis =
+        Analysis::new("fn f(b: Bool) -> match b { _ => 0, true => 1 }\nfn g() -> x".into());
+    let severities: Vec<_> = analysis
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| (diagnostic.severity, diagnostic.code))
+        .collect();
+    assert_eq!(
+        severities,
+        [
+            (Severity::Error, Some("E0201")),
+            (Severity::Warning, Some("E0228"))
+        ]
+    );
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    let mut message = notification(method, params);
+    message["id"] = id.into();
+    message
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    let mut message = json!({ "jsonrpc": "2.0", "method": method });
+    message["params"] = params;
+    message
+}
+
+/// Runs the server over `messages`, returning its exit status and everything it sent.
+fn session(messages: &[Value]) -> (bool, Vec<Value>) {
+    let mut input = Vec::new();
+    for message in messages {
+        rpc::write_message(&mut input, message).unwrap();
+    }
+
+    let mut output = Vec::new();
+    let clean = run(input.as_slice(), &mut output).unwrap();
+
+    let mut output = output.as_slice();
+    let mut replies = Vec::new();
+    while let Some(content) = rpc::read_message(&mut output).unwrap() {
+        replies.push(serde_json::from_slice(&content).unwrap());
+    }
+
+    (clean, replies)
+}
+
+#[test]
+fn protocol_session() {
+    let uri = "file:///test.ptn";
+    let document = json!({ "uri": uri });
+    let text = "fn f(s: Str) -> { \"é\"; s }\nconst x: Bool = f(\"\")";
+
+    let (clean, replies) = session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "patina", "version": 1, "text": text } }),
+        ),
+        request(
+            2,
+            "textDocument/hover",
+            json!({ "textDocument": document, "position": { "line": 0, "character": 23 } }),
+        ),
+        request(
+            3,
+            "textDocument/definition",
+            json!({ "textDocument": document, "position": { "line": 1, "character": 16 } }),
+        ),
+        notification(
+            "textDocument/didChange",
+            json!({ "textDocument": document, "contentChanges": [{ "text": "fn f() -> 1" }] }),
+        ),
+        request(
+            4,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": document }),
+        ),
+        request(
+            5,
+            "textDocument/formatting",
+            json!({ "textDocument": document }),
+        ),
+        request(6, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+    assert!(clean);
+
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+
+    assert_eq!(
+        replies[1],
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": uri,
+                "diagnostics": [{
+                    "range": {
+                        "start": { "line": 1, "character": 16 },
+                        "end": { "line": 1, "character": 21 },
+                    },
+                    "severity": 1,
+                    "code": "E0202",
+                    "source": "patina",
+                    "message": "mismatched types\nexpected `Bool`, found `Str`",
+                    "relatedInformation": [{
+                        "location": {
+                            "uri": uri,
+                            "range": {
+                                "start": { "line": 1, "character": 9 },
+                                "end": { "line": 1, "character": 13 },
+                            },
+                        },
+                        "message": "expected because of this annotation",
+                    }],
+                }],
+            },
+        })
+    );
+
+    assert_eq!(
+        replies[2]["result"]["contents"]["value"],
+        "```patina\ns: Str\n```"
+    );
+    // columns count UTF-16 code units, so the two byte `é` is one wide
+    assert_eq!(
+        replies[2]["result"]["range"],
+        json!({ "start": { "line": 0, "character": 23 }, "end": { "line": 0, "character": 24 } })
+    );
+
+    assert_eq!(
+        replies[3]["result"],
+        json!({
+            "uri": uri,
+            "range": {
+                "start": { "line": 0, "character": 3 },
+                "end": { "line": 0, "character": 4 },
+            },
+        })
+    );
+
+    assert_eq!(replies[4]["params"]["diagnostics"], json!([]));
+    assert_eq!(replies[5]["result"][0]["name"], "f");
+    assert_eq!(replies[6]["error"]["code"], -32601);
+    assert_eq!(
+        replies[7],
+        json!({ "jsonrpc": "2.0", "id": 6, "result": null })
+    );
+    assert_eq!(replies.len(), 8);
+}
+
+#[test]
+fn incremental_changes() {
+    let uri = "file:///test.ptn";
+    let document = json!({ "uri": uri });
+    let change = |start: (u64, u64), end: (u64, u64), text: &str| {
+        json!({
+            "range": {
+                "start": { "line": start.0, "character": start.1 },
+                "end": { "line": end.0, "character": end.1 },
+            },
+            "text": text,
+        })
+    };
+
+    let (clean, replies) = session(&[
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "patina", "version": 1, "text": "const x: Int = 1" } }),
+        ),
+        notification(
+            "textDocument/didChange",
+            json!({ "textDocument": document, "contentChanges": [change((0, 15), (0, 16), "\"é\"")] }),
+        ),
+        // `é` is one UTF-16 code unit wide, and each change sees the ones before it
+        notification(
+            "textDocument/didChange",
+            json!({ "textDocument": document, "contentChanges": [
+                change((0, 16), (0, 17), "ab"),
+                change((0, 15), (0, 19), "2"),
+                change((0, 0), (0, 0), "fn f() -> x\n"),
+            ] }),
+        ),
+        request(
+            1,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": document }),
+        ),
+        notification("exit", Value::Null),
+    ]);
+    assert!(!clean);
+
+    assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+    assert_eq!(
+        replies[1]["params"]["diagnostics"][0]["range"],
+        json!({ "start": { "line": 0, "character": 15 }, "end": { "line": 0, "character": 18 } })
+    );
+    assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+    assert_eq!(replies[3]["result"][0]["name"], "f");
+    assert_eq!(replies[3]["result"][1]["name"], "x");
+    assert_eq!(
+        replies[3]["result"][1]["range"],
+        json!({ "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 16 } })
+    );
+}
+
+#[test]
+fn protocol_errors() {
+    let (clean, replies) = session(&[
+        request(
+            1,
+            "textDocument/hover",
+            json!({ "textDocument": { "uri": "file:///nope" } }),
+        ),
+        json!("not a message"),
+        notification("exit", Value::Null),
+    ]);
+    assert!(!clean);
+
+    assert_eq!(replies[0]["error"]["code"], -32602);
+    // a message that isn't an object is ignored
+    assert_eq!(replies.len(), 1);
+}
@@ -1,27 +1,290 @@
-use anyhow::anyhow;
-use std::{env, fs};
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    io::{self, IsTerminal, Read, StdoutLock, Write},
+    ops::Range,
+    process::ExitCode,
+};
 
-use crate::parser::Parser;
+use args::{Action, Args, Command, ErrorFormat, Input, Stage, USAGE};
+use compiler::{
+    diagnostics::{Diagnostic, SourceMap},
+    parser::ast::Ast,
+    typecheck::TypeChecker,
+};
 
-mod helpers;
-mod lexer;
-mod parser;
-mod typecheck;
+mod args;
 
-//use parser::Parser;
+/// Exit code for when the compiler reported problems with the input.
+const EXIT_DIAGNOSTICS: u8 = 1;
+/// Exit code for invalid arguments or unreadable input.
+const EXIT_USAGE: u8 = 2;
 
-fn main() -> anyhow::Result<()> {
-    let source_path = env::args()
-        .nth(1)
-        .ok_or_else(|| anyhow!("source filepath argument missing"))?;
-    let source = fs::read_to_string(source_path)?;
+fn main() -> ExitCode {
+    let action = match Args::parse(env::args().skip(1)) {
+        Ok(action) => action,
+        Err(err) => return usage_error(&err),
+    };
 
-    let mut parser = Parser::new(&source);
+    let mut out = io::stdout().lock();
+    let mut stderr = io::stderr().lock();
+    let result = match action {
+        Action::Compile(args) => compile(&args, &mut out, &mut stderr),
+        Action::Help => writeln!(out, "{USAGE}").map(|()| ExitCode::SUCCESS),
+        Action::Explain(code) => explain(&code, &mut out, &mut stderr),
+        Action::Lsp => return serve(out, &mut stderr),
+        Action::Format { check, inputs } => format(check, &inputs, &mut out, &mut stderr),
+    };
 
-    let ast = parser.file()?;
-    println!("{ast:?}");
+    match result {
+        Ok(exit) => exit,
+        // whatever reads the output or the diagnostics stopped early, like `head` does
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            // there is nowhere left to complain if this fails too
+            let _ = writeln!(stderr, "patina: couldn't write the output: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reports invalid arguments along with the usage.
+fn usage_error(err: &anyhow::Error) -> ExitCode {
+    let _ = writeln!(io::stderr().lock(), "patina: {err}\n\n{USAGE}");
+    ExitCode::from(EXIT_USAGE)
+}
+
+/// Runs the front end over each input as far as `args.command` asks for.
+fn compile(args: &Args, out: &mut impl Write, stderr: &mut impl Write) -> io::Result<ExitCode> {
+    let colored = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let mut exit = ExitCode::SUCCESS;
+    let mut codes = BTreeSet::new();
+
+    for input in &args.inputs {
+        if args.inputs.len() > 1 && !args.emit.is_empty() {
+            writeln!(out, "==> {} <==", input.name())?;
+        }
+
+        let source = match read(input) {
+            Ok(source) => source,
+            Err(err) => {
+                writeln!(stderr, "patina: couldn't read {}: {err}", input.name())?;
+                return Ok(ExitCode::from(EXIT_USAGE));
+            }
+        };
+
+        let name = input.name();
+        let map = SourceMap::new(&name, &source);
+        let mut diagnostics: Vec<Diagnostic> = compiler::lexer::confusables(&source)
+            .iter()
+            .map(Diagnostic::from)
+            .collect();
+
+        let failed = match process(args, &source, out, &mut diagnostics) {
+            Ok(()) => false,
+            Err(Stop::Output(err)) => return Err(err),
+            Err(Stop::Diagnostics(err)) => {
+                diagnostics.extend(err.diagnostics());
+                exit = ExitCode::from(EXIT_DIAGNOSTICS);
+                true
+            }
+        };
+        for diagnostic in diagnostics {
+            codes.extend(diagnostic.code);
+            match args.error_format {
+                ErrorFormat::Human => writeln!(stderr, "{}", diagnostic.render(&map, colored))?,
+                ErrorFormat::Json => writeln!(stderr, "{}", diagnostic.to_json(&map))?,
+            }
+        }
+
+        if !failed && args.command == Command::Run {
+            writeln!(
+                stderr,
+                "patina: can't run {}: there is no execution backend yet",
+                input.name()
+            )?;
+            return Ok(ExitCode::from(EXIT_USAGE));
+        }
+    }
+
+    if args.error_format == ErrorFormat::Human
+        && let Some(first) = codes.first()
+    {
+        if codes.len() > 1 {
+            let codes: Vec<_> = codes.iter().copied().collect();
+            writeln!(
+                stderr,
+                "Some errors have detailed explanations: {}.",
+                codes.join(", ")
+            )?;
+        }
+        writeln!(
+            stderr,
+            "For more information about an error, try `patina --explain {first}`."
+        )?;
+    }
+
+    Ok(exit)
+}
+
+/// Runs the language server until the client exits.
+fn serve(out: StdoutLock, stderr: &mut impl Write) -> ExitCode {
+    match compiler::lsp::run(io::stdin().lock(), out) {
+        Ok(true) => ExitCode::SUCCESS,
+        // the client exited without asking the server to shut down first
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            let _ = writeln!(stderr, "patina: language server failed: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Formats each input in place, or prints it if it came from stdin. With `check`, the
+/// inputs are left alone and the ones that aren't formatted are listed instead.
+fn format(
+    check: bool,
+    inputs: &[Input],
+    out: &mut impl Write,
+    stderr: &mut impl Write,
+) -> io::Result<ExitCode> {
+    let colored = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let mut exit = ExitCode::SUCCESS;
+
+    for input in inputs {
+        let source = match read(input) {
+            Ok(source) => source,
+            Err(err) => {
+                writeln!(stderr, "patina: couldn't read {}: {err}", input.name())?;
+                return Ok(ExitCode::from(EXIT_USAGE));
+            }
+        };
+
+        let formatted = match compiler::format::format(&source) {
+            Ok(formatted) => formatted,
+            Err(errs) => {
+                let name = input.name();
+                let map = SourceMap::new(&name, &source);
+                for diagnostic in compiler::Error::from(errs).diagnostics() {
+                    writeln!(stderr, "{}", diagnostic.render(&map, colored))?;
+                }
+                exit = ExitCode::from(EXIT_DIAGNOSTICS);
+                continue;
+            }
+        };
+
+        if check {
+            if formatted != source {
+                writeln!(out, "{}", input.name())?;
+                exit = ExitCode::from(EXIT_DIAGNOSTICS);
+            }
+            continue;
+        }
+
+        match input {
+            Input::Stdin => write!(out, "{formatted}")?,
+            Input::File(path) if formatted != source => {
+                if let Err(err) = fs::write(path, formatted) {
+                    writeln!(stderr, "patina: couldn't write {}: {err}", input.name())?;
+                    return Ok(ExitCode::from(EXIT_USAGE));
+                }
+            }
+            Input::File(_) => {}
+        }
+    }
 
-    //typecheck::TypeChecker::new(&ast).check(&[parser::ast::Expr::Int(42).spanned(0..2)])?;
+    Ok(exit)
+}
+
+/// Prints the explanation of the diagnostic with `code`.
+fn explain(code: &str, out: &mut impl Write, stderr: &mut impl Write) -> io::Result<ExitCode> {
+    let Some(explanation) = compiler::diagnostics::explain(code) else {
+        writeln!(stderr, "patina: `{code}` is not a diagnostic code")?;
+        return Ok(ExitCode::from(EXIT_USAGE));
+    };
+
+    write!(
+        out,
+        "{}: {}\n\n{}",
+        explanation.code, explanation.title, explanation.text
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn read(input: &Input) -> io::Result<String> {
+    match input {
+        Input::Stdin => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            Ok(source)
+        }
+        Input::File(path) => fs::read_to_string(path),
+    }
+}
+
+/// Why [`process`] stopped before the end.
+enum Stop {
+    Diagnostics(compiler::Error),
+    Output(io::Error),
+}
+
+impl From<compiler::Error> for Stop {
+    fn from(value: compiler::Error) -> Self {
+        Self::Diagnostics(value)
+    }
+}
+
+impl From<io::Error> for Stop {
+    fn from(value: io::Error) -> Self {
+        Self::Output(value)
+    }
+}
+
+/// Runs the front end over `source` as far as `args.command` needs, printing each emitted stage
+/// to `out` and adding any warnings to `diagnostics`.
+fn process(
+    args: &Args,
+    source: &str,
+    out: &mut impl Write,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), Stop> {
+    if args.emit.contains(&Stage::Tokens) {
+        for token in compiler::tokenize(source) {
+            let text = &source[Range::from(token.span)];
+            writeln!(out, "{}\t{}\t{text:?}", token.span, token.inner)?;
+        }
+    }
+    if args.command == Command::Lex {
+        compiler::lex(source).map_err(compiler::Error::from)?;
+        return Ok(());
+    }
+
+    let ast = compiler::parse(source).map_err(compiler::Error::from)?;
+    if args.emit.contains(&Stage::Ast) {
+        writeln!(out, "{ast:#?}")?;
+    }
+    if args.command == Command::Parse {
+        return Ok(());
+    }
+
+    let mut checker = TypeChecker::new(&ast);
+    let types = checker.check_items(&ast);
+    diagnostics.extend(checker.warnings().iter().map(Diagnostic::from));
+    let types = types.map_err(compiler::Error::from)?;
+    if args.emit.contains(&Stage::Types) {
+        print_types(&ast, &types, out)?;
+    }
+
+    Ok(())
+}
 
+fn print_types(
+    ast: &Ast,
+    types: &[compiler::typecheck::Type],
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for (item, ty) in ast.iter().zip(types) {
+        writeln!(out, "{}: {ty}", item.inner.name())?;
+    }
     Ok(())
 }
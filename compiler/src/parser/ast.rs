@@ -1,4 +1,10 @@
-use crate::{helpers::Spanned, span};
+use std::fmt::Display;
+
+use crate::{
+    helpers::{Span, Spanned},
+    lexer::NumericSuffix,
+    span,
+};
 
 pub type Ast = Vec<ItemS>;
 
@@ -6,26 +12,55 @@ span! {Item as ItemS}
 #[derive(Debug, Clone, PartialEq)]
 pub enum Item {
     Const {
+        /// The lines of the `///` comments before the item.
+        docs: Vec<String>,
         name: String,
         ty: TypeS,
         value: ExprS,
     },
     Function {
+        docs: Vec<String>,
         name: String,
         params: Vec<BindingS>,
         return_type: Option<TypeS>,
         body: ExprS,
     },
     Struct {
+        docs: Vec<String>,
         name: String,
         generic_params: Vec<String>,
         fields: Vec<FieldS>,
     },
     Enum {
+        docs: Vec<String>,
         name: String,
         generic_params: Vec<String>,
         variants: Vec<VariantS>,
     },
+    /// Placeholder for an item that failed to parse.
+    Error,
+}
+
+impl Item {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Const { name, .. }
+            | Self::Function { name, .. }
+            | Self::Struct { name, .. }
+            | Self::Enum { name, .. } => name,
+            Self::Error => "<error>",
+        }
+    }
+
+    pub fn docs(&self) -> &[String] {
+        match self {
+            Self::Const { docs, .. }
+            | Self::Function { docs, .. }
+            | Self::Struct { docs, .. }
+            | Self::Enum { docs, .. } => docs,
+            Self::Error => &[],
+        }
+    }
 }
 
 span! {Variant as VariantS}
@@ -46,13 +81,32 @@ pub struct Field {
 }
 
 span! {Binding as BindingS}
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Binding {
     Var {
         mutable: bool,
         ident: String,
         type_annotation: Option<TypeS>,
     },
+    /// A pattern that takes the value apart, as in `let (a, b) = pair`, binding the names
+    /// in it. It has to match every value of its type.
+    Pattern {
+        pattern: Box<PatternS>,
+        type_annotation: Option<TypeS>,
+    },
+}
+
+impl Binding {
+    pub const fn type_annotation(&self) -> Option<&TypeS> {
+        match self {
+            Self::Var {
+                type_annotation, ..
+            }
+            | Self::Pattern {
+                type_annotation, ..
+            } => type_annotation.as_ref(),
+        }
+    }
 }
 
 span! {Type as TypeS}
@@ -74,9 +128,13 @@ span! {Expr as ExprS}
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Ident(String),
-    Int(u64),
-    Float(f64),
+    /// An integer literal, and the type its suffix gives it.
+    Int(u64, Option<NumericSuffix>),
+    /// A float literal, and the type its suffix gives it.
+    Float(f64, Option<NumericSuffix>),
     Str(String),
+    /// A string with expressions embedded in it, as in `"sum is {a + b}"`.
+    Interpolation(Vec<StringPart>),
     Char(char),
     Bool(bool),
     Array(Vec<ExprS>),
@@ -124,6 +182,169 @@ pub enum Expr {
         exprs: Vec<ExprS>,
         trailing: bool,
     },
+    Match {
+        scrutinee: Box<ExprS>,
+        arms: Vec<MatchArmS>,
+    },
+    /// A variant of an enum, as in `Shape::Empty`. A variant with fields, as in
+    /// `Shape::Circle`, is a function from them to the enum.
+    Path(Path),
+    /// `Point { x: 1, y }` or `Shape::Rect { w, h }`. With `..base`, the fields left out
+    /// are taken from `base`.
+    Struct {
+        path: Path,
+        fields: Vec<FieldInitS>,
+        base: Option<Box<ExprS>>,
+    },
+    /// `_`, an argument left out of a call, which makes the call a function of the
+    /// arguments left out. See [`partial_application`].
+    ///
+    /// Lowering keeps the call as written instead of turning it into that lambda: the
+    /// formatter prints this tree back out as source, and the language server resolves
+    /// names in it, and neither should see the generated parameters. The type checker
+    /// desugars the call when it gets to it, rejecting a `_` anywhere else.
+    Placeholder,
+    /// Placeholder for an expression that failed to parse.
+    Error,
+}
+
+span! {MatchArm as MatchArmS}
+/// `pattern if guard => body` in a `match`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: PatternS,
+    pub guard: Option<ExprS>,
+    pub body: ExprS,
+}
+
+span! {Pattern as PatternS}
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_`, which matches anything.
+    Wildcard,
+    /// `..` in an array pattern, which matches any number of elements.
+    Rest,
+    /// A name the value is bound to, as in `x` and `mut x`. With a pattern after `@`, as
+    /// in `n @ 1`, the value also has to match it.
+    Binding {
+        mutable: bool,
+        ident: String,
+        subpattern: Option<Box<PatternS>>,
+    },
+    /// A literal the value has to be equal to, which is an [`Expr::Int`], [`Expr::Float`],
+    /// [`Expr::Str`], [`Expr::Char`] or [`Expr::Bool`], or a negated number.
+    Literal(ExprS),
+    /// `1..=5` or `0..10`, which matches the integers from `start` up to `end`, and `end`
+    /// itself if `inclusive`. The bounds are literals like those of [`Self::Literal`].
+    Range {
+        start: ExprS,
+        end: ExprS,
+        inclusive: bool,
+    },
+    Tuple(Vec<PatternS>),
+    /// `[a, b]`, or with a [`Self::Rest`] among the elements, `[first, ..]`.
+    Array(Vec<PatternS>),
+    /// `Point { x, y: 0, .. }`, where `x` is short for `x: x` and `..` allows fields to be
+    /// left out.
+    Struct {
+        path: Path,
+        fields: Vec<FieldPatternS>,
+        rest: bool,
+    },
+    /// `Shape::Circle(r)`.
+    TupleVariant {
+        path: Path,
+        elements: Vec<PatternS>,
+    },
+    /// `Shape::Empty`.
+    Path(Path),
+    /// `a | b`, which matches what any of the alternatives does.
+    Or(Vec<PatternS>),
+}
+
+/// The name of a struct, as in `Point`, or of an enum variant, as in `Shape::Circle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    pub name: String,
+    pub variant: Option<String>,
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.variant {
+            Some(variant) => write!(f, "{}::{variant}", self.name),
+            None => self.name.fmt(f),
+        }
+    }
+}
+
+span! {FieldPattern as FieldPatternS}
+/// `name: pattern` in a struct pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPattern {
+    pub name: String,
+    pub pattern: PatternS,
+}
+
+span! {FieldInit as FieldInitS}
+/// `name: value` in a struct literal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInit {
+    pub name: String,
+    pub value: ExprS,
+}
+
+/// A piece of an interpolated string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Expr(ExprS),
+}
+
+/// The lambda that the call at `span` stands for if some of its arguments are `_`.
+///
+/// `f(a, _, c, _)` is `fn(x, y) -> f(a, x, c, y)`, with a parameter for each `_` in
+/// order. The parameters are named so that they can't clash with names in the source.
+pub fn partial_application(fun: &ExprS, args: &[ExprS], span: Span) -> Option<Expr> {
+    if !args
+        .iter()
+        .any(|arg| matches!(arg.inner, Expr::Placeholder))
+    {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    let args = args
+        .iter()
+        .map(|arg| {
+            if !matches!(arg.inner, Expr::Placeholder) {
+                return arg.clone();
+            }
+
+            let ident = format!("<_{}>", params.len());
+            params.push(
+                Binding::Var {
+                    mutable: false,
+                    ident: ident.clone(),
+                    type_annotation: None,
+                }
+                .spanned(arg.span),
+            );
+            Expr::Ident(ident).spanned(arg.span)
+        })
+        .collect();
+
+    Some(Expr::Lambda {
+        params,
+        return_type: None,
+        body: Box::new(
+            Expr::FnCall {
+                fun: Box::new(fun.clone()),
+                args,
+            }
+            .spanned(span),
+        ),
+    })
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -0,0 +1,286 @@
+//! The concrete syntax tree, which keeps every token of the source, whitespace and
+//! comments included, so that the source can be reproduced from it exactly.
+//!
+//! The [`ast`](super::ast) is derived from it, dropping everything that doesn't affect
+//! what the code means.
+
+use crate::{
+    helpers::Span,
+    lexer::{Token, TokenType},
+};
+
+/// The kinds of syntactic construct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A whole source file, which spans all of it.
+    File,
+
+    // Items
+    ConstItem,
+    FnItem,
+    StructItem,
+    EnumItem,
+    /// `<T, U>` after the name of a struct or enum.
+    GenericParamList,
+    /// `{ name: Type, ... }` in a struct or struct variant.
+    FieldList,
+    Field,
+    /// `{ Variant, ... }` in an enum.
+    VariantList,
+    Variant,
+    /// `(a, b: Type)` in a function or lambda.
+    ParamList,
+    Binding,
+
+    // Types
+    NamedType,
+    /// `<A, B>` after the name of a type.
+    GenericArgList,
+    ArrayType,
+    TupleType,
+    FnType,
+    /// `(A, B)` in a function type or tuple variant.
+    TypeList,
+
+    // Expressions
+    Literal,
+    NameExpr,
+    /// `Shape::Empty`, or `Shape::Circle` before its arguments.
+    PathExpr,
+    /// An expression in parentheses.
+    ParenExpr,
+    TupleExpr,
+    ArrayExpr,
+    /// `Point { x: 1, y }` or `Shape::Rect { w, h }`.
+    StructExpr,
+    /// `{ x: 1, y, ..base }` in a struct literal.
+    FieldInitList,
+    FieldInit,
+    /// A string with expressions embedded in it.
+    InterpolationExpr,
+    PrefixExpr,
+    BinaryExpr,
+    CallExpr,
+    /// `(a, b)` in a call.
+    ArgList,
+    IndexExpr,
+    FieldExpr,
+    IfExpr,
+    LetExpr,
+    AssignExpr,
+    LambdaExpr,
+    BlockExpr,
+    /// `_` in place of an argument.
+    PlaceholderExpr,
+    MatchExpr,
+    /// `pattern if guard => body` in a `match`.
+    MatchArm,
+    /// `if guard` in a match arm.
+    MatchGuard,
+
+    // Patterns
+    WildcardPattern,
+    /// `..` in an array pattern.
+    RestPattern,
+    /// `x`, `mut x` or `x @ pattern`.
+    BindingPattern,
+    /// A literal, or a negated number, as in `-1`.
+    LiteralPattern,
+    /// `1..=5` or `0..10`, between two literal patterns.
+    RangePattern,
+    /// A pattern in parentheses.
+    ParenPattern,
+    TuplePattern,
+    ArrayPattern,
+    /// `Point { x, y: 0, .. }`.
+    StructPattern,
+    /// `{ x, y: 0, .. }` in a struct pattern.
+    FieldPatternList,
+    FieldPattern,
+    /// `Shape::Circle(r)`.
+    TupleVariantPattern,
+    /// `(a, b)` in a tuple variant pattern.
+    PatternList,
+    /// `Shape::Empty`.
+    PathPattern,
+    /// `a | b`.
+    OrPattern,
+
+    /// Source that couldn't be parsed.
+    Error,
+}
+
+impl NodeKind {
+    pub const fn is_type(self) -> bool {
+        matches!(
+            self,
+            Self::NamedType | Self::ArrayType | Self::TupleType | Self::FnType
+        )
+    }
+
+    pub const fn is_expr(self) -> bool {
+        matches!(
+            self,
+            Self::Literal
+                | Self::NameExpr
+                | Self::PathExpr
+                | Self::ParenExpr
+                | Self::TupleExpr
+                | Self::ArrayExpr
+                | Self::StructExpr
+                | Self::InterpolationExpr
+                | Self::PrefixExpr
+                | Self::BinaryExpr
+                | Self::CallExpr
+                | Self::IndexExpr
+                | Self::FieldExpr
+                | Self::IfExpr
+                | Self::LetExpr
+                | Self::AssignExpr
+                | Self::LambdaExpr
+                | Self::BlockExpr
+                | Self::PlaceholderExpr
+                | Self::MatchExpr
+                | Self::Error
+        )
+    }
+
+    pub const fn is_pattern(self) -> bool {
+        matches!(
+            self,
+            Self::WildcardPattern
+                | Self::RestPattern
+                | Self::BindingPattern
+                | Self::LiteralPattern
+                | Self::RangePattern
+                | Self::ParenPattern
+                | Self::TuplePattern
+                | Self::ArrayPattern
+                | Self::StructPattern
+                | Self::TupleVariantPattern
+                | Self::PathPattern
+                | Self::OrPattern
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+impl Element {
+    pub const fn span(&self) -> Span {
+        match self {
+            Self::Node(node) => node.span,
+            Self::Token(token) => token.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    /// From the start of the first token in the node to the end of the last one that
+    /// isn't trivia or a stray character. Trivia is kept within the innermost node that
+    /// surrounds it, so a node only starts or ends with trivia if it is the whole file.
+    pub span: Span,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    /// Builds a node out of `children`, which is placed at `empty_at` if there are no
+    /// tokens in it.
+    pub(crate) fn new(kind: NodeKind, children: Vec<Element>, empty_at: usize) -> Self {
+        let mut spans = children
+            .iter()
+            .filter(|child| !matches!(child, Element::Token(token) if token.inner.is_skipped()))
+            .map(Element::span);
+        let span = match (spans.next(), spans.next_back()) {
+            (Some(first), Some(last)) => (first.start..last.end).into(),
+            (Some(only), None) => only,
+            _ => (empty_at..empty_at).into(),
+        };
+
+        Self {
+            kind,
+            span,
+            children,
+        }
+    }
+
+    /// The nodes directly within this one.
+    pub fn nodes(&self) -> impl DoubleEndedIterator<Item = &Self> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// The tokens directly within this one that aren't trivia or stray characters.
+    pub fn tokens(&self) -> impl DoubleEndedIterator<Item = Token> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Token(token) if !token.inner.is_skipped() => Some(*token),
+            _ => None,
+        })
+    }
+
+    /// The first token of type `kind` directly within this node.
+    pub fn token(&self, kind: TokenType) -> Option<Token> {
+        self.tokens().find(|token| token.inner == kind)
+    }
+
+    /// The first node of type `kind` directly within this node.
+    pub fn node(&self, kind: NodeKind) -> Option<&Self> {
+        self.nodes().find(|node| node.kind == kind)
+    }
+
+    /// The lines of the doc comments at the start of this node, without their `///`, or
+    /// the `//!` comments at the start of the file if this is the root.
+    pub fn docs(&self, source: &str) -> Vec<String> {
+        let kind = if self.kind == NodeKind::File {
+            TokenType::InnerDocComment
+        } else {
+            TokenType::DocComment
+        };
+
+        self.children
+            .iter()
+            .map_while(|child| match child {
+                Element::Token(token) if token.inner.is_trivia() => Some(*token),
+                _ => None,
+            })
+            .filter(|token| token.inner == kind)
+            .map(|token| {
+                let line = source[token.span.start + 3..token.span.end].trim_end();
+                line.strip_prefix(' ').unwrap_or(line).into()
+            })
+            .collect()
+    }
+
+    /// Every token in the tree under this node, trivia included, in order.
+    pub fn descendant_tokens(&self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens(&self, tokens: &mut Vec<Token>) {
+        for child in &self.children {
+            match child {
+                Element::Node(node) => node.collect_tokens(tokens),
+                Element::Token(token) => tokens.push(*token),
+            }
+        }
+    }
+
+    /// The source text of the tree under this node, which is exactly the source it was
+    /// parsed from if it is the root.
+    pub fn text(&self, source: &str) -> String {
+        self.descendant_tokens()
+            .iter()
+            .map(|token| &source[token.span.start..token.span.end])
+            .collect()
+    }
+}
@@ -1,30 +1,155 @@
-use crate::lexer::TokenType;
+use crate::{
+    helpers::Span,
+    lexer::{LexError, LiteralError, TokenType},
+};
 use std::{error::Error, fmt::Display};
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
-#[derive(Debug)]
-pub enum ParseError {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The span of the offending token.
+    pub span: Span,
+    /// The constructs that were being parsed when the error occurred, outermost first.
+    pub context: Vec<String>,
+    /// Where the one token that was expected goes if it is only missing, which is when
+    /// putting it there lets parsing carry on for a few tokens past the offending one.
+    pub missing_at: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// One of a known set of tokens was expected.
     Mismatched {
-        expected: TokenType,
+        expected: Vec<TokenType>,
         found: TokenType,
     },
-    Unexpected(TokenType, Option<String>),
+    /// The token can't appear here, `expected` describes what could have.
+    Unexpected { found: TokenType, expected: String },
+    /// The input ended while more tokens were needed.
     Missing,
+    /// The contents of a literal are malformed, like a string with an escape that doesn't
+    /// stand for a character.
+    InvalidLiteral(LiteralError),
+    /// A token is malformed as a whole, like a string that is never closed, or is a
+    /// stray character. Parsing carries on as if it were well-formed, or wasn't there.
+    InvalidToken(LexError),
 }
 
-impl Display for ParseError {
+/// Formats a token the way error messages refer to it.
+pub fn describe(token: TokenType) -> String {
+    match token {
+        TokenType::Eof => "end of file".into(),
+        TokenType::StringStart => TokenType::StringLit.to_string(),
+        // what ends an interpolation
+        TokenType::StringMiddle | TokenType::StringEnd => "`}`".into(),
+        _ if token.is_fixed() => format!("`{token}`"),
+        _ => token.to_string(),
+    }
+}
+
+/// Formats a set of tokens as "`a`", "`a` or `b`" or "one of `a`, `b`, `c`".
+pub fn describe_all(tokens: &[TokenType]) -> String {
+    let described: Vec<_> = tokens.iter().copied().map(describe).collect();
+
+    match described.as_slice() {
+        [] => "nothing".into(),
+        [one] => one.clone(),
+        [first, second] => format!("{first} or {second}"),
+        many => format!("one of {}", many.join(", ")),
+    }
+}
+
+impl ParseError {
+    /// Where to put in the token this error is missing, and the text to put there, spaced
+    /// the way it is usually written.
+    pub fn insertion(&self) -> Option<(usize, String)> {
+        let (ParseErrorKind::Mismatched { expected, found }, Some(at)) =
+            (&self.kind, self.missing_at)
+        else {
+            return None;
+        };
+        let [token] = expected.as_slice() else {
+            return None;
+        };
+
+        // with nothing between the tokens either side, a space is needed after it
+        let space = if at == self.span.start && *found != TokenType::Eof {
+            " "
+        } else {
+            ""
+        };
+        let insertion = match token {
+            // `f(x)`
+            TokenType::LParen | TokenType::LBracket => (self.span.start, token.to_string()),
+            // `x: Int`, `f(x, y)`
+            TokenType::Colon
+            | TokenType::Comma
+            | TokenType::Semicolon
+            | TokenType::RParen
+            | TokenType::RBracket => (at, format!("{token}{space}")),
+            // `let x = 1`
+            _ => (at, format!(" {token}{space}")),
+        };
+        Some(insertion)
+    }
+
+    /// The stable code identifying this kind of error, see [`crate::diagnostics::explain`].
+    pub const fn code(&self) -> &'static str {
+        match self.kind {
+            ParseErrorKind::Mismatched {
+                found: TokenType::Eof,
+                ..
+            }
+            | ParseErrorKind::Unexpected {
+                found: TokenType::Eof,
+                ..
+            }
+            | ParseErrorKind::Missing => "E0103",
+            ParseErrorKind::Mismatched { .. } => "E0101",
+            ParseErrorKind::Unexpected { .. } => "E0102",
+            ParseErrorKind::InvalidLiteral(err) => err.code(),
+            ParseErrorKind::InvalidToken(err) => err.code(),
+        }
+    }
+
+    /// Describes where the error happened, e.g. "in parameter list of fn `sum`".
+    pub fn context_description(&self) -> Option<String> {
+        match self.context.as_slice() {
+            [] => None,
+            [only] => Some(format!("in {only}")),
+            [outermost, .., innermost] => Some(format!("in {innermost} of {outermost}")),
+        }
+    }
+}
+
+impl Display for ParseErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Mismatched { expected, found } => {
-                write!(f, "expected token {expected}, found token {found}")
+            Self::Mismatched { expected, found } => write!(
+                f,
+                "expected {}, found {}",
+                describe_all(expected),
+                describe(*found)
+            ),
+            Self::Unexpected { found, expected } => {
+                write!(f, "expected {expected}, found {}", describe(*found))
             }
-            Self::Unexpected(token, Some(desc)) => {
-                write!(f, "unexpected token `{token}` at {desc}")
-            }
-            Self::Unexpected(token, None) => write!(f, "unexpected token `{token:?}`"),
-            Self::Missing => "expected another token".fmt(f),
+            Self::Missing => "unexpected end of input".fmt(f),
+            Self::InvalidLiteral(err) => err.fmt(f),
+            Self::InvalidToken(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(context) = self.context_description() {
+            write!(f, " {context}")?;
         }
+        write!(f, " at {}", self.span)
     }
 }
 
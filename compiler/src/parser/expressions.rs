@@ -1,18 +1,20 @@
-use std::{ops::Range, str::FromStr};
-
-use crate::{
-    helpers::{Span, Spanned},
-    lexer::{Token, TokenType},
-    parser::ast::ExprS,
-};
+use crate::lexer::{Token, TokenType, number_value, string_value};
 
 use super::{
-    ParseError, ParseResult, Parser,
-    ast::{Bop, Expr, Unop},
+    ParseError, ParseErrorKind, ParseResult, Parser,
+    ast::{Bop, ExprS, Unop},
+    cst::{Element, NodeKind},
+    lower,
 };
 
 impl<I: Iterator<Item = Token>> Parser<'_, I> {
     pub fn expression(&mut self) -> ParseResult<ExprS> {
+        self.expr()?;
+        Ok(lower::expr(self.last_node(), self.input))
+    }
+
+    /// Parses an expression into a node of the tree being built.
+    pub(crate) fn expr(&mut self) -> ParseResult<()> {
         self.parse_expression(0)
     }
 
@@ -20,123 +22,96 @@ impl<I: Iterator<Item = Token>> Parser<'_, I> {
         clippy::too_many_lines,
         reason = "still readable and segmented via the match"
     )]
-    fn parse_expression(&mut self, binding_power: u8) -> ParseResult<ExprS> {
-        let mut lhs = match self.peek() {
+    fn parse_expression(&mut self, binding_power: u8) -> ParseResult<()> {
+        let start = self.checkpoint();
+
+        match self.peek() {
             TokenType::LParen => {
-                let start = self.next().unwrap().span.start;
-                let expr = self.expression()?;
+                self.next();
 
-                let expr = if self.consume_at(TokenType::Comma) {
-                    let mut exprs = vec![expr];
-                    while !self.at(TokenType::RParen) {
-                        exprs.push(self.expression()?);
+                let kind = self.with_struct_literals(true, |this| {
+                    this.expr()?;
 
-                        if !self.consume_at(TokenType::Comma) {
-                            break;
-                        }
-                    }
+                    Ok(if this.consume_at(TokenType::Comma) {
+                        while !this.at(TokenType::RParen) {
+                            this.expr()?;
 
-                    Expr::Tuple(exprs)
-                } else {
-                    expr.inner
-                };
-
-                let end = self.consume(TokenType::RParen)?.span.end;
-
-                expr.spanned(start..end)
-            }
-            TokenType::IntLit => {
-                let token = self.next().unwrap();
-                let val = u64::from_str(&self.input[Range::from(token.span)]).unwrap();
-                Expr::Int(val).spanned(token.span)
-            }
-            TokenType::FloatLit => {
-                let token = self.next().unwrap();
-                let val = f64::from_str(&self.input[Range::from(token.span)]).unwrap();
-                Expr::Float(val).spanned(token.span)
-            }
-            TokenType::StringLit => {
-                let token = self.next().unwrap();
-                let span = token.span;
+                            if !this.consume_at(TokenType::Comma) {
+                                break;
+                            }
+                        }
 
-                let val = self.input[span.start + 1..span.end - 1]
-                    .replace("\\n", "\n")
-                    .replace("\\\"", "\"")
-                    .replace("\\\\", "\\");
+                        NodeKind::TupleExpr
+                    } else {
+                        NodeKind::ParenExpr
+                    })
+                })?;
 
-                Expr::Str(val).spanned(token.span)
+                self.consume(TokenType::RParen)?;
+                self.wrap(start, kind);
             }
-            TokenType::CharLit => {
+            TokenType::IntLit
+            | TokenType::FloatLit
+            | TokenType::StringLit
+            | TokenType::RawStringLit
+            | TokenType::MultiLineStringLit
+            | TokenType::CharLit
+            | TokenType::True
+            | TokenType::False => {
                 let token = self.next().unwrap();
-                let span = token.span;
-
-                let val = self.input[span.start + 1..span.end - 1]
-                    .replace("\\n", "\n")
-                    .replace("\\\'", "'")
-                    .replace("\\\\", "\\")
-                    .chars()
-                    .next()
-                    .unwrap();
-
-                Expr::Char(val).spanned(token.span)
+                self.check_literal(token);
+                self.wrap(start, NodeKind::Literal);
             }
-            TokenType::True => Expr::Bool(true).spanned(self.next().unwrap().span),
-            TokenType::False => Expr::Bool(false).spanned(self.next().unwrap().span),
-            TokenType::LBracket => {
-                let Spanned { inner: arr, span } = self.delimited_list(
-                    Self::expression,
+            TokenType::StringStart => self.interpolation()?,
+            TokenType::LBracket => self.in_context("array", |this| {
+                this.delimited_list(
+                    NodeKind::ArrayExpr,
+                    Self::expr,
                     TokenType::LBracket,
                     TokenType::RBracket,
-                )?;
-                Expr::Array(arr).spanned(span)
-            }
+                )
+            })?,
             TokenType::Ident => {
-                let token = self.next().unwrap();
-                let range: Range<_> = token.span.into();
-
-                let ident = self.input[range].to_string();
-
-                if self.consume_at(TokenType::Eq) {
-                    let val = self.expression()?;
+                self.next();
 
-                    let end = val.span.end;
+                let path = self.consume_at(TokenType::ColonColon);
+                if path {
+                    self.ident()?;
+                }
 
-                    Expr::Assign {
-                        ident: Spanned {
-                            inner: ident,
-                            span: token.span,
-                        },
-                        value: val.into(),
-                    }
-                    .spanned(token.span.start..end)
+                if self.struct_literals && self.at(TokenType::LBrace) {
+                    self.in_context("struct literal", Self::field_inits)?;
+                    self.wrap(start, NodeKind::StructExpr);
+                } else if path {
+                    self.wrap(start, NodeKind::PathExpr);
+                } else if self.consume_at(TokenType::Eq) {
+                    self.expr()?;
+                    self.wrap(start, NodeKind::AssignExpr);
                 } else {
-                    Expr::Ident(ident).spanned(token.span)
+                    self.wrap(start, NodeKind::NameExpr);
                 }
             }
-            TokenType::If => {
-                let start = self.next().unwrap().span.start;
-
-                self.consume(TokenType::LParen)?;
-                let cond = self.expression()?;
-                self.consume(TokenType::RParen)?;
-
-                let th = self.expression()?;
+            TokenType::Underscore => {
+                self.next();
+                self.wrap(start, NodeKind::PlaceholderExpr);
+            }
+            TokenType::If => self.node(NodeKind::IfExpr, |this| {
+                this.next();
 
-                let el = if self.consume_at(TokenType::Else) {
-                    Some(Box::new(self.expression()?))
-                } else {
-                    None
-                };
+                this.in_context("if condition", |this| {
+                    this.consume(TokenType::LParen)?;
+                    this.with_struct_literals(true, Self::expr)?;
+                    this.consume(TokenType::RParen)?;
+                    Ok(())
+                })?;
 
-                let end = el.as_ref().map_or(th.span.end, |e| e.span.end);
+                this.expr()?;
 
-                Expr::If {
-                    cond: Box::new(cond),
-                    th: Box::new(th),
-                    el,
+                if this.consume_at(TokenType::Else) {
+                    this.expr()?;
                 }
-                .spanned(start..end)
-            }
+                Ok(())
+            })?,
             op @ (TokenType::Minus | TokenType::Bang) => {
                 let op = match op {
                     TokenType::Minus => Unop::Neg,
@@ -144,148 +119,70 @@ impl<I: Iterator<Item = Token>> Parser<'_, I> {
                     _ => unreachable!(),
                 };
 
-                let start = self.next().unwrap().span.start;
-
-                let right_binding_power = op.binding_power();
-                let expr = self.parse_expression(right_binding_power)?;
-
-                let end = expr.span.end;
-
-                Expr::UnaryOp {
-                    op,
-                    expr: Box::new(expr),
-                }
-                .spanned(start..end)
+                self.node(NodeKind::PrefixExpr, |this| {
+                    this.next();
+                    this.parse_expression(op.binding_power())
+                })?;
             }
-            TokenType::Let => {
-                let start = self.next().unwrap().span.start;
-
-                let binding = self.binding()?;
-
-                self.consume(TokenType::Eq)?;
-                let value = self.expression()?;
-
-                let end = value.span.end;
-
-                Expr::Let {
-                    binding,
-                    value: Box::new(value),
-                }
-                .spanned(start..end)
+            TokenType::Let => self.node(NodeKind::LetExpr, |this| {
+                this.next();
+
+                this.in_context("let binding", |this| {
+                    this.binding()?;
+
+                    this.consume(TokenType::Eq)?;
+                    this.expr()
+                })
+            })?,
+            // where an operand is expected, `|` and `||` start closures rather than being
+            // bitwise and logical or
+            TokenType::Fn | TokenType::Pipe | TokenType::Or => {
+                self.node(NodeKind::LambdaExpr, Self::lambda)?;
             }
-            TokenType::Fn => {
-                let start = self.next().unwrap().span.start;
-
-                let Spanned { inner: params, .. } =
-                    self.delimited_list(Self::binding, TokenType::LParen, TokenType::RParen)?;
-
-                let return_type = if self.consume_at(TokenType::Colon) {
-                    Some(self.type_()?)
-                } else {
-                    None
-                };
-
-                self.consume(TokenType::Arrow)?;
-
-                let body = Box::new(self.expression()?);
-                let end = body.span.end;
-
-                Expr::Lambda {
-                    params,
-                    return_type,
-                    body,
-                }
-                .spanned(start..end)
-            }
-            TokenType::LBrace => {
-                let start = self.next().unwrap().span.start;
-
-                let mut trailing = true;
-                let mut exprs = Vec::new();
-                while !self.at(TokenType::RBrace) {
-                    exprs.push(self.expression()?);
-
-                    if self.consume_at(TokenType::Semicolon) && self.at(TokenType::RBrace) {
-                        trailing = false;
-                        break;
-                    }
-                }
-                let end = self.consume(TokenType::RBrace)?.span.end;
+            TokenType::LBrace => self.in_context("block", Self::block)?,
+            TokenType::Match => self.node(NodeKind::MatchExpr, |this| {
+                this.next();
+                // the `{` after the scrutinee starts the arms
+                this.with_struct_literals(false, Self::expr)?;
+
+                this.in_context("match", |this| {
+                    this.with_struct_literals(true, Self::match_arms)
+                })
+            })?,
+            _ => return Err(self.unexpected("expression")),
+        }
 
-                Expr::Block { exprs, trailing }.spanned(start..end)
-            }
-            token => {
-                return Err(ParseError::Unexpected(
-                    token,
-                    Some("start of expression".into()),
-                ));
-            }
-        };
         loop {
             let op = match self.peek() {
-                TokenType::Plus => Bop::Add,
-                TokenType::Minus => Bop::Sub,
-                TokenType::Times => Bop::Mul,
-                TokenType::FSlash => Bop::Div,
-                TokenType::Xor => Bop::Xor,
-                TokenType::Ampersand => Bop::BAnd,
-                TokenType::Pipe => Bop::BOr,
-                TokenType::Exponent => Bop::Exp,
-                TokenType::Eqq => Bop::Eqq,
-                TokenType::Neq => Bop::Neq,
-                TokenType::And => Bop::And,
-                TokenType::Or => Bop::Or,
-                TokenType::LAngle => Bop::Lt,
-                TokenType::Leq => Bop::Leq,
-                TokenType::RAngle => Bop::Gt,
-                TokenType::Geq => Bop::Geq,
                 TokenType::LBracket => {
                     self.next();
 
-                    let start = lhs.span.start;
-
-                    let index = Box::new(self.expression()?);
-                    let end = self.consume(TokenType::RBracket)?.span.end;
+                    self.in_context("index", |this| {
+                        this.with_struct_literals(true, Self::expr)?;
+                        this.consume(TokenType::RBracket)
+                    })?;
 
-                    lhs = Expr::Index {
-                        arr: Box::new(lhs),
-                        index,
-                    }
-                    .spanned(start..end);
+                    self.wrap(start, NodeKind::IndexExpr);
                     continue;
                 }
                 TokenType::Dot => {
                     self.next();
+                    self.ident()?;
 
-                    let start = lhs.span.start;
-
-                    let (field, field_span) = self.ident()?;
-                    let end = field_span.end;
-
-                    lhs = Expr::FieldAccess {
-                        base: Box::new(lhs),
-                        field: Spanned {
-                            inner: field,
-                            span: field_span,
-                        },
-                    }
-                    .spanned(start..end);
+                    self.wrap(start, NodeKind::FieldExpr);
                     continue;
                 }
                 TokenType::LParen => {
-                    let start = lhs.span.start;
-
-                    let Spanned { inner: args, span: Span { end, .. } } = self.delimited_list(
-                        Self::expression,
-                        TokenType::LParen,
-                        TokenType::RParen,
-                    )?;
-
-                    lhs = Expr::FnCall {
-                        fun: Box::new(lhs),
-                        args,
-                    }
-                    .spanned(start..end);
+                    self.in_context("argument list", |this| {
+                        this.delimited_list(
+                            NodeKind::ArgList,
+                            Self::expr,
+                            TokenType::LParen,
+                            TokenType::RParen,
+                        )
+                    })?;
+
+                    self.wrap(start, NodeKind::CallExpr);
                     continue;
                 }
                 TokenType::Eof
@@ -294,17 +191,21 @@ impl<I: Iterator<Item = Token>> Parser<'_, I> {
                 | TokenType::RBracket
                 | TokenType::Comma
                 | TokenType::Semicolon
+                | TokenType::StringMiddle
+                | TokenType::StringEnd
                 | TokenType::Else
+                | TokenType::LBrace
+                | TokenType::FatArrow
                 | TokenType::Fn
                 | TokenType::Const
                 | TokenType::Struct
                 | TokenType::Enum => break,
-                token => {
-                    return Err(ParseError::Unexpected(
-                        token,
-                        Some("end of expression".into()),
-                    ));
-                }
+                token => match binary_op(token) {
+                    Some(op) => op,
+                    None => {
+                        return Err(self.unexpected("an operator or the end of the expression"));
+                    }
+                },
             };
 
             let (left_binding_power, right_binding_power) = op.binding_power();
@@ -314,20 +215,272 @@ impl<I: Iterator<Item = Token>> Parser<'_, I> {
             }
 
             self.next();
+            self.parse_expression(right_binding_power)?;
+
+            self.wrap(start, NodeKind::BinaryExpr);
+        }
+
+        Ok(())
+    }
+
+    /// Parses a block, recovering from errors in its statements at the next `;` or `}`.
+    fn block(&mut self) -> ParseResult<()> {
+        self.with_struct_literals(true, Self::block_contents)
+    }
+
+    fn block_contents(&mut self) -> ParseResult<()> {
+        self.node(NodeKind::BlockExpr, |this| {
+            this.next();
+
+            while !this.at(TokenType::RBrace) && !this.at(TokenType::Eof) {
+                let start = this.checkpoint();
+
+                if let Err(err) = this.expr() {
+                    // skip to the end of the statement, stepping over any nested blocks
+                    let mut depth = 0_usize;
+                    this.recover(err, start, |token| match token {
+                        TokenType::LBrace => {
+                            depth += 1;
+                            false
+                        }
+                        TokenType::RBrace if depth > 0 => {
+                            depth -= 1;
+                            false
+                        }
+                        TokenType::RBrace | TokenType::Semicolon => depth == 0,
+                        _ => false,
+                    });
+                }
+
+                if this.consume_at(TokenType::Semicolon) && this.at(TokenType::RBrace) {
+                    break;
+                }
+            }
+            this.consume(TokenType::RBrace)?;
+
+            Ok(())
+        })
+    }
+
+    /// Parses the arms of a `match` between braces. Each arm is followed by a comma,
+    /// unless it is the last one or its body is a block.
+    fn match_arms(&mut self) -> ParseResult<()> {
+        self.consume(TokenType::LBrace)?;
+
+        while !self.at(TokenType::RBrace) {
+            self.node(NodeKind::MatchArm, |this| {
+                this.pattern()?;
+
+                let guard = this.at(TokenType::If);
+                if guard {
+                    this.node(NodeKind::MatchGuard, |this| {
+                        this.next();
+                        this.expr()
+                    })?;
+                }
+
+                let alternatives: &[_] = if guard { &[] } else { &[TokenType::If] };
+                this.consume_expecting(TokenType::FatArrow, alternatives)?;
+
+                // a block ends the arm, rather than being the start of a longer expression
+                if this.at(TokenType::LBrace) {
+                    this.block()
+                } else {
+                    this.expr()
+                }
+            })?;
+
+            let block = self.last_node().nodes().next_back().map(|body| body.kind)
+                == Some(NodeKind::BlockExpr);
+            if block || self.at(TokenType::RBrace) {
+                self.consume_at(TokenType::Comma);
+            } else {
+                self.consume_expecting(TokenType::Comma, &[TokenType::RBrace])?;
+            }
+        }
+        self.consume(TokenType::RBrace)?;
+
+        Ok(())
+    }
+
+    /// A lambda, with its parameters in parentheses after `fn`, as in `fn(a) -> a`, or
+    /// between pipes, as in `|a| -> a` and `|| -> 1`.
+    fn lambda(&mut self) -> ParseResult<()> {
+        match self.peek() {
+            TokenType::Fn => {
+                self.next();
 
-            let rhs = self.parse_expression(right_binding_power)?;
+                self.in_context("lambda parameter list", |this| {
+                    this.delimited_list(
+                        NodeKind::ParamList,
+                        Self::binding,
+                        TokenType::LParen,
+                        TokenType::RParen,
+                    )
+                })?;
+            }
+            TokenType::Or => self.node(NodeKind::ParamList, |this| {
+                this.next();
+                Ok(())
+            })?,
+            _ => self.in_context("closure parameter list", |this| {
+                this.delimited_list(
+                    NodeKind::ParamList,
+                    Self::binding,
+                    TokenType::Pipe,
+                    TokenType::Pipe,
+                )
+            })?,
+        }
+
+        let return_type = self.consume_at(TokenType::Colon);
+        if return_type {
+            self.type_()?;
+        }
 
-            let start = lhs.span.start;
-            let end = rhs.span.end;
+        let alternatives: &[_] = if return_type {
+            &[]
+        } else {
+            &[TokenType::Colon]
+        };
+        self.consume_expecting(TokenType::Arrow, alternatives)?;
+
+        self.expr()
+    }
+
+    /// Parses `{ x: 1, y, ..base }` in a struct literal, where `..base` can only come
+    /// last.
+    fn field_inits(&mut self) -> ParseResult<()> {
+        self.node(NodeKind::FieldInitList, |this| {
+            this.consume(TokenType::LBrace)?;
+
+            let mut alternatives: &[_] = &[];
+            while !this.at(TokenType::RBrace) {
+                if this.consume_at(TokenType::DotDot) {
+                    this.expr()?;
+                    this.consume_at(TokenType::Comma);
+                    break;
+                }
+
+                // `x` is short for `x: x`
+                this.node(NodeKind::FieldInit, |this| {
+                    this.ident()?;
+
+                    if this.consume_at(TokenType::Colon) {
+                        this.expr()?;
+                    }
+                    Ok(())
+                })?;
 
-            lhs = Expr::BinaryOp {
-                op,
-                lhs: Box::new(lhs),
-                rhs: Box::new(rhs),
+                if !this.consume_at(TokenType::Comma) {
+                    alternatives = &[TokenType::Comma];
+                    break;
+                }
+            }
+            this.consume_expecting(TokenType::RBrace, alternatives)?;
+
+            Ok(())
+        })
+    }
+
+    /// Parses a string with expressions embedded in it, from its `StringStart` token up
+    /// to its `StringEnd`.
+    fn interpolation(&mut self) -> ParseResult<()> {
+        self.node(NodeKind::InterpolationExpr, |this| {
+            this.in_context("string interpolation", |this| {
+                let start = this.next().unwrap();
+                this.check_literal(start);
+
+                loop {
+                    this.expr()?;
+
+                    if this.at(TokenType::StringMiddle) {
+                        let middle = this.next().unwrap();
+                        this.check_literal(middle);
+                    } else {
+                        let end = this.consume(TokenType::StringEnd)?;
+                        this.check_literal(end);
+                        return Ok(());
+                    }
+                }
+            })
+        })
+    }
+
+    /// Records an error for each invalid part of `token`, if it is a string, char or
+    /// number literal. The literal is still usable, so parsing carries on.
+    ///
+    /// The contents of a literal that is malformed as a whole aren't checked, since
+    /// where it ends is only a guess.
+    pub(super) fn check_literal(&mut self, token: Token) {
+        if self.check_token(token) {
+            return;
+        }
+
+        let errors = match token.inner {
+            TokenType::StringLit
+            | TokenType::StringStart
+            | TokenType::StringMiddle
+            | TokenType::StringEnd
+            | TokenType::RawStringLit
+            | TokenType::MultiLineStringLit
+            | TokenType::CharLit => string_value(token, self.input).1,
+            TokenType::IntLit | TokenType::FloatLit => {
+                number_value(token, self.input, self.is_negated(token)).1
             }
-            .spanned(start..end);
+            _ => return,
+        };
+
+        for err in errors {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::InvalidLiteral(err.inner),
+                span: err.span,
+                context: self.context.clone(),
+                missing_at: None,
+            });
         }
+    }
 
-        Ok(lhs)
+    /// Whether `token`, the last one consumed, is all there is after the `-` of a
+    /// prefix expression, so that a negative literal is written.
+    fn is_negated(&self, token: Token) -> bool {
+        let mut parts = self.nodes.last().unwrap().iter().filter(
+            |element| !matches!(element, Element::Token(token) if token.inner.is_skipped()),
+        );
+
+        matches!(
+            (parts.next(), parts.next(), parts.next()),
+            (
+                Some(Element::Token(Token {
+                    inner: TokenType::Minus,
+                    ..
+                })),
+                Some(Element::Token(last)),
+                None,
+            ) if *last == token
+        )
     }
 }
+
+/// The binary operator `token` stands for, if any.
+pub(super) const fn binary_op(token: TokenType) -> Option<Bop> {
+    Some(match token {
+        TokenType::Plus => Bop::Add,
+        TokenType::Minus => Bop::Sub,
+        TokenType::Times => Bop::Mul,
+        TokenType::FSlash => Bop::Div,
+        TokenType::Xor => Bop::Xor,
+        TokenType::Ampersand => Bop::BAnd,
+        TokenType::Pipe => Bop::BOr,
+        TokenType::Exponent => Bop::Exp,
+        TokenType::Eqq => Bop::Eqq,
+        TokenType::Neq => Bop::Neq,
+        TokenType::And => Bop::And,
+        TokenType::Or => Bop::Or,
+        TokenType::LAngle => Bop::Lt,
+        TokenType::Leq => Bop::Leq,
+        TokenType::RAngle => Bop::Gt,
+        TokenType::Geq => Bop::Geq,
+        _ => return None,
+    })
+}
@@ -1,96 +1,78 @@
 use std::ops::Range;
 
 use crate::{
-    helpers::{Span, Spanned},
+    helpers::Span,
     lexer::{Token, TokenType},
 };
 
-use super::{
-    ParseError, ParseResult, Parser,
-    ast::{Binding, BindingS, Type, TypeS},
-};
+use super::{ParseResult, Parser, cst::NodeKind};
 
 impl<I: Iterator<Item = Token>> Parser<'_, I> {
-    pub fn binding(&mut self) -> ParseResult<BindingS> {
-        let mutable = self.at(TokenType::Mut);
-        let mut_start = mutable.then(|| self.next().unwrap().span.start);
-
-        let (name, name_span) = self.ident()?;
-
-        let start = mut_start.unwrap_or(name_span.start);
-
-        let type_annotation = if self.consume_at(TokenType::Colon) {
-            Some(self.type_()?)
-        } else {
-            None
-        };
-
-        let end = type_annotation
-            .as_ref()
-            .map_or(name_span.end, |ty| ty.span.end);
-
-        Ok(Binding::Var {
-            mutable,
-            ident: name,
-            type_annotation,
-        }
-        .spanned(start..end))
-    }
-
-    pub fn type_(&mut self) -> ParseResult<TypeS> {
-        Ok(match self.peek() {
-            TokenType::Ident => {
-                let span = self.next().unwrap().span;
-                let name = self.input[Range::from(span)].to_string();
-
-                let start = span.start;
-
-                let (generics, end) = if self.at(TokenType::LAngle) {
-                    let Spanned { inner: generics, span: generics_span } =
-                        self.delimited_list(Self::type_, TokenType::LAngle, TokenType::RAngle)?;
-                    (generics, generics_span.end)
-                } else {
-                    (Vec::new(), span.end)
-                };
-
-                Type::Named { name, generics }.spanned(start..end)
-            }
-            TokenType::LBracket => {
-                let start = self.next().unwrap().span.start;
-
-                let inner_type = self.type_()?;
-
-                let end = self.consume(TokenType::RBracket)?.span.end;
-
-                Type::Array(Box::new(inner_type)).spanned(start..end)
-            }
-            TokenType::LParen => {
-                let Spanned { inner: types, span} =
-                    self.delimited_list(Self::type_, TokenType::LParen, TokenType::RParen)?;
-                Type::Tuple(types).spanned(span)
-            }
-            TokenType::Fn => {
-                let start = self.next().unwrap().span.start;
-
-                let Spanned { inner: params, .. } =
-                    self.delimited_list(Self::type_, TokenType::LParen, TokenType::RParen)?;
-
-                self.consume(TokenType::Colon)?;
-                let result = Box::new(self.type_()?);
-
-                let end = result.span.end;
-
-                Type::Fn { params, result }.spanned(start..end)
-            }
-            token => {
-                return Err(ParseError::Unexpected(
-                    token,
-                    Some("start of type name".into()),
-                ));
+    /// Parses a pattern binding a value, as in a `let` or a parameter list, with an
+    /// optional type annotation. Alternatives have to be in parentheses, so that the
+    /// pipes around the parameters of a closure can't be mistaken for them.
+    pub fn binding(&mut self) -> ParseResult<()> {
+        self.node(NodeKind::Binding, |this| {
+            this.single_pattern()?;
+
+            if this.consume_at(TokenType::Colon) {
+                this.type_()?;
             }
+            Ok(())
         })
     }
 
+    pub fn type_(&mut self) -> ParseResult<()> {
+        match self.peek() {
+            TokenType::Ident => self.node(NodeKind::NamedType, |this| {
+                this.next();
+
+                if this.at(TokenType::LAngle) {
+                    this.in_context("generic arguments", |this| {
+                        this.delimited_list(
+                            NodeKind::GenericArgList,
+                            Self::type_,
+                            TokenType::LAngle,
+                            TokenType::RAngle,
+                        )
+                    })?;
+                }
+                Ok(())
+            }),
+            TokenType::LBracket => self.node(NodeKind::ArrayType, |this| {
+                this.next();
+                this.type_()?;
+                this.consume(TokenType::RBracket)?;
+                Ok(())
+            }),
+            TokenType::LParen => self.delimited_list(
+                NodeKind::TupleType,
+                Self::type_,
+                TokenType::LParen,
+                TokenType::RParen,
+            ),
+            TokenType::Fn => self.node(NodeKind::FnType, |this| {
+                this.next();
+
+                this.delimited_list(
+                    NodeKind::TypeList,
+                    Self::type_,
+                    TokenType::LParen,
+                    TokenType::RParen,
+                )?;
+
+                this.consume(TokenType::Colon)?;
+                this.type_()
+            }),
+            _ => Err(self.mismatched(vec![
+                TokenType::Ident,
+                TokenType::LBracket,
+                TokenType::LParen,
+                TokenType::Fn,
+            ])),
+        }
+    }
+
     pub fn ident(&mut self) -> ParseResult<(String, Span)> {
         match self.peek() {
             TokenType::Ident => {
@@ -98,34 +80,41 @@ impl<I: Iterator<Item = Token>> Parser<'_, I> {
 
                 Ok((self.input[Range::from(span)].to_string(), span))
             }
-            other_type => Err(ParseError::Mismatched {
-                expected: TokenType::Ident,
-                found: other_type,
-            }),
+            _ => Err(self.mismatched(vec![TokenType::Ident])),
         }
     }
 
-    pub fn delimited_list<T, F>(
+    /// Parses a comma separated list of `f` between `start` and `end` into a node of type
+    /// `kind`.
+    pub fn delimited_list<F>(
         &mut self,
+        kind: NodeKind,
         mut f: F,
         start: TokenType,
         end: TokenType,
-    ) -> ParseResult<Spanned<Vec<T>>>
+    ) -> ParseResult<()>
     where
-        F: FnMut(&mut Self) -> ParseResult<T>,
+        F: FnMut(&mut Self) -> ParseResult<()>,
     {
-        let start = self.consume(start)?.span.start;
-
-        let mut items = Vec::new();
-        while !self.at(end) {
-            items.push(f(self)?);
-
-            if !self.consume_at(TokenType::Comma) {
-                break;
-            }
-        }
-        let end = self.consume(end)?.span.end;
-
-        Ok(Spanned::span(items, start..end))
+        self.node(kind, |this| {
+            this.consume(start)?;
+
+            let mut comma = true;
+            this.with_struct_literals(true, |this| {
+                while !this.at(end) {
+                    f(this)?;
+
+                    comma = this.consume_at(TokenType::Comma);
+                    if !comma {
+                        break;
+                    }
+                }
+                Ok(())
+            })?;
+            let alternatives: &[_] = if comma { &[] } else { &[TokenType::Comma] };
+            this.consume_expecting(end, alternatives)?;
+
+            Ok(())
+        })
     }
 }
@@ -0,0 +1,275 @@
+//! Keeping the syntax tree of a file up to date as it is edited, re-lexing and
+//! re-parsing only the items an edit affects.
+//!
+//! Items are parsed one after the other in the same state, so a file can be re-parsed
+//! from the start of any item. As the parser only ever looks one token ahead, an item
+//! is affected by an edit to its own tokens, or to the first token after it.
+
+use std::{iter::Copied, mem, slice};
+
+use crate::{
+    helpers::Span,
+    lexer::{Lexer, Token, TokenType, relex},
+};
+
+use super::{
+    ParseError, Parser,
+    ast::Ast,
+    confirm_insertions,
+    cst::{Element, Node},
+    items::file_node,
+    lower,
+};
+
+/// A source file along with its tokens and syntax tree, which [`Self::edit`] keeps up
+/// to date without starting over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedFile {
+    source: String,
+    /// Every token of the source, trivia included, ending with `Eof`.
+    tokens: Vec<Token>,
+    tree: Node,
+    /// Errors in the stray characters and comments before the first item.
+    leading_errors: Vec<ParseError>,
+    /// Where the parser was as it started on each item, in order.
+    items: Vec<ItemParse>,
+}
+
+/// The parse of one item, or of the text skipped where an item should have been.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ItemParse {
+    /// The index of the first token after the previous item, where the trivia set aside
+    /// before this one starts.
+    start: usize,
+    /// The index of the first token of the item.
+    first: usize,
+    /// How many children the file node had before the item.
+    child: usize,
+    /// The errors found from the start of the item to the start of the next, including
+    /// the ones in trivia read ahead of it.
+    errors: Vec<ParseError>,
+}
+
+type TokenParser<'a> = Parser<'a, Copied<slice::Iter<'a, Token>>>;
+
+impl ParsedFile {
+    pub fn new(source: String) -> Self {
+        let tokens: Vec<_> = Lexer::with_trivia(&source).collect();
+
+        let mut leading_errors = Vec::new();
+        let mut items = Vec::new();
+        let tree = {
+            let mut parser = Parser::resume(&source, tokens.iter().copied(), Vec::new());
+            parse_items(
+                &mut parser,
+                &tokens,
+                0,
+                &mut items,
+                &mut leading_errors,
+                |_| false,
+            );
+            file_node(parser.finish(), source.len())
+        };
+
+        Self {
+            source,
+            tokens,
+            tree,
+            leading_errors,
+            items,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Every token of the source, trivia included, ending with `Eof`.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub const fn tree(&self) -> &Node {
+        &self.tree
+    }
+
+    /// Every syntax error in the file, in the order parsing it from scratch finds them.
+    pub fn errors(&self) -> Vec<ParseError> {
+        let mut errors: Vec<_> = self
+            .leading_errors
+            .iter()
+            .chain(self.items.iter().flat_map(|item| &item.errors))
+            .cloned()
+            .collect();
+        confirm_insertions(&self.source, &mut errors);
+        errors
+    }
+
+    pub fn ast(&self) -> Ast {
+        lower::file(&self.tree, &self.source)
+    }
+
+    /// Replaces the text at `span` with `replacement`. Only the tokens around the edit
+    /// are lexed again, and only the items whose tokens changed are parsed again.
+    pub fn edit(&mut self, span: Span, replacement: &str) {
+        let old = mem::take(&mut self.source);
+        self.source = [&old[..span.start], replacement, &old[span.end..]].concat();
+        let moved = byte_offset(self.source.len(), old.len());
+
+        let relexed = relex(&self.tokens, &old, &self.source, span);
+        let replaced = relexed.replaced.clone();
+        let changed = replaced.start..replaced.start + relexed.tokens.len();
+        let moved_index = byte_offset(changed.end, replaced.end);
+        self.tokens.splice(replaced.clone(), relexed.tokens);
+        for token in &mut self.tokens[changed.end..] {
+            token.span = shift(token.span, moved);
+        }
+
+        // the last item that starts before the first changed token, whose parse may have
+        // looked at it, or the start of the file if there is none
+        let restart = self
+            .items
+            .iter()
+            .rposition(|item| item.first < changed.start);
+        let old_items = mem::take(&mut self.items);
+        let mut children = mem::take(&mut self.tree.children);
+        let (start, first, child) = restart.map_or((0, 0, 0), |restart| {
+            let item = &old_items[restart];
+            (item.start, item.first, item.child)
+        });
+        if restart.is_none() {
+            self.leading_errors.clear();
+        }
+        let old_tail = children.split_off(child);
+        let mut items = old_items[..restart.unwrap_or(0)].to_vec();
+
+        let mut parser = Parser::resume(
+            &self.source,
+            self.tokens[first..].iter().copied(),
+            self.tokens[start..first].to_vec(),
+        );
+        // carry on until the parser is where it was at the start of an item after the
+        // changed tokens, from where the old tree still holds
+        let moved_item = |old: &ItemParse| {
+            (
+                old.start.wrapping_add_signed(moved_index),
+                old.first.wrapping_add_signed(moved_index),
+            )
+        };
+        let mut resume_at = restart.unwrap_or(0);
+        let resynced = parse_items(
+            &mut parser,
+            &self.tokens,
+            children.len(),
+            &mut items,
+            &mut self.leading_errors,
+            |item| {
+                if item.start < changed.end {
+                    return false;
+                }
+                while old_items
+                    .get(resume_at)
+                    .is_some_and(|old| old.start < replaced.end || moved_item(old).0 < item.start)
+                {
+                    resume_at += 1;
+                }
+                old_items
+                    .get(resume_at)
+                    .is_some_and(|old| moved_item(old) == (item.start, item.first))
+            },
+        );
+
+        if resynced {
+            children.append(&mut parser.nodes[0]);
+            let skipped = old_items[resume_at].child - child;
+            let moved_child = byte_offset(children.len(), old_items[resume_at].child);
+
+            children.extend(old_tail.into_iter().skip(skipped).map(|mut element| {
+                shift_element(&mut element, moved);
+                element
+            }));
+            items.extend(old_items.into_iter().skip(resume_at).map(|mut item| {
+                (item.start, item.first) = moved_item(&item);
+                item.child = item.child.wrapping_add_signed(moved_child);
+                for err in &mut item.errors {
+                    err.span = shift(err.span, moved);
+                    err.missing_at = err.missing_at.map(|at| at.wrapping_add_signed(moved));
+                }
+                item
+            }));
+        } else {
+            children.extend(parser.finish());
+        }
+
+        self.tree = file_node(children, self.source.len());
+        self.items = items;
+    }
+}
+
+/// Parses items from where `parser` is until the end of the input, or until `sync`
+/// accepts the parser's state at the start of an item, which is left unparsed. Returns
+/// whether it stopped because of `sync`.
+///
+/// `kept` is how many children the file node already has before the ones `parser`
+/// adds, and `items` is the parse of every item before them.
+fn parse_items(
+    parser: &mut TokenParser<'_>,
+    tokens: &[Token],
+    kept: usize,
+    items: &mut Vec<ItemParse>,
+    leading_errors: &mut Vec<ParseError>,
+    mut sync: impl FnMut(&ItemParse) -> bool,
+) -> bool {
+    loop {
+        let next = parser.peek_span().start;
+        // errors in the trivia read ahead belong to what was parsed before it
+        let errors = mem::take(&mut parser.errors);
+        items
+            .last_mut()
+            .map_or(&mut *leading_errors, |item| &mut item.errors)
+            .extend(errors);
+
+        if parser.at(TokenType::Eof) {
+            return false;
+        }
+
+        let first = tokens.partition_point(|token| token.span.start < next);
+        let item = ItemParse {
+            start: first - parser.trivia.len(),
+            first,
+            child: kept + parser.nodes[0].len(),
+            errors: Vec::new(),
+        };
+        if sync(&item) {
+            return true;
+        }
+
+        items.push(item);
+        parser.item_or_skip();
+    }
+}
+
+/// How far something at `old` has moved to be at `new`.
+#[allow(
+    clippy::cast_possible_wrap,
+    reason = "sources and token lists are far shorter than `isize::MAX`"
+)]
+const fn byte_offset(new: usize, old: usize) -> isize {
+    new as isize - old as isize
+}
+
+fn shift(span: Span, by: isize) -> Span {
+    (span.start.wrapping_add_signed(by)..span.end.wrapping_add_signed(by)).into()
+}
+
+fn shift_element(element: &mut Element, by: isize) {
+    match element {
+        Element::Token(token) => token.span = shift(token.span, by),
+        Element::Node(node) => {
+            node.span = shift(node.span, by);
+            for child in &mut node.children {
+                shift_element(child, by);
+            }
+        }
+    }
+}
@@ -1,179 +1,241 @@
-use std::ops::Range;
+use std::mem;
 
-use crate::{
-    helpers::Spanned,
-    lexer::{Token, TokenType},
-    parser::ast::FieldS,
-};
+use crate::lexer::{Token, TokenType};
 
 use super::{
     ParseError, ParseResult, Parser,
-    ast::{Ast, Field, Item, ItemS, Variant},
+    ast::Ast,
+    confirm_insertions,
+    cst::{Element, Node, NodeKind},
+    lower,
 };
 
 impl<I: Iterator<Item = Token>> Parser<'_, I> {
-    pub fn file(&mut self) -> ParseResult<Ast> {
-        let mut items = Vec::new();
+    /// Parses items up to the end of the input, failing with every syntax error found.
+    pub fn file(&mut self) -> Result<Ast, Vec<ParseError>> {
+        let (ast, errors) = self.partial_file();
+
+        if errors.is_empty() {
+            Ok(ast)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses items up to the end of the input, carrying on past syntax errors.
+    ///
+    /// An item that fails to parse is skipped up to the start of the next one and left in
+    /// the tree as an [`Item::Error`](super::ast::Item::Error). Every error found is
+    /// returned along with the tree.
+    pub fn partial_file(&mut self) -> (Ast, Vec<ParseError>) {
+        let (tree, errors) = self.syntax_tree();
+        (lower::file(&tree, self.input), errors)
+    }
+
+    /// Parses the whole input into a concrete syntax tree, carrying on past syntax errors
+    /// like [`Self::partial_file`] does. Every error found is returned along with the tree.
+    pub fn syntax_tree(&mut self) -> (Node, Vec<ParseError>) {
+        let (tree, mut errors) = self.syntax_tree_unconfirmed();
+        confirm_insertions(self.input, &mut errors);
+        (tree, errors)
+    }
+
+    /// Like [`Self::syntax_tree`], without finding out which missing tokens can be put in.
+    pub(super) fn syntax_tree_unconfirmed(&mut self) -> (Node, Vec<ParseError>) {
         while !self.at(TokenType::Eof) {
-            items.push(self.item()?);
+            self.item_or_skip();
         }
-        Ok(items)
+        let children = self.finish();
+
+        (
+            file_node(children, self.input.len()),
+            mem::take(&mut self.errors),
+        )
     }
 
-    pub fn item(&mut self) -> ParseResult<ItemS> {
-        Ok(match self.peek() {
-            TokenType::Const => {
-                let start = self.next().unwrap().span.start;
+    /// Parses an item into the file node being built, or if there isn't a valid one
+    /// next, skips ahead to the start of the next item and leaves an error node.
+    pub(super) fn item_or_skip(&mut self) {
+        let checkpoint = self.checkpoint();
+
+        if let Err(err) = self.item_node() {
+            self.recover(err, checkpoint, |token| {
+                matches!(
+                    token,
+                    TokenType::Const | TokenType::Fn | TokenType::Struct | TokenType::Enum
+                )
+            });
+        }
+    }
+
+    /// The children of the file node being built, once every item has been parsed.
+    pub(super) fn finish(&mut self) -> Vec<Element> {
+        self.flush_trivia();
+        mem::replace(&mut self.nodes, vec![Vec::new()]).remove(0)
+    }
 
-                let (name, _) = self.ident()?;
+    pub fn item(&mut self) -> ParseResult<super::ast::ItemS> {
+        self.item_node()?;
+        Ok(lower::item(self.last_node(), self.input))
+    }
 
-                self.consume(TokenType::Colon)?;
-                let ty = self.type_()?;
+    fn item_node(&mut self) -> ParseResult<()> {
+        match self.peek() {
+            TokenType::Const => self.node(NodeKind::ConstItem, |this| {
+                this.in_context("const item", Self::const_item)
+            }),
+            TokenType::Fn => self.node(NodeKind::FnItem, |this| {
+                this.in_context("fn item", Self::function_item)
+            }),
+            TokenType::Struct => self.node(NodeKind::StructItem, |this| {
+                this.in_context("struct item", Self::struct_item)
+            }),
+            TokenType::Enum => self.node(NodeKind::EnumItem, |this| {
+                this.in_context("enum item", Self::enum_item)
+            }),
+            _ => Err(self.mismatched(vec![
+                TokenType::Const,
+                TokenType::Fn,
+                TokenType::Struct,
+                TokenType::Enum,
+            ])),
+        }
+    }
 
-                self.consume(TokenType::Eq)?;
-                let value = self.expression()?;
+    fn const_item(&mut self) -> ParseResult<()> {
+        self.next();
 
-                let end = value.span.end;
+        let (name, _) = self.ident()?;
+        self.rename_context(format!("const `{name}`"));
 
-                Item::Const { name, ty, value }.spanned(start..end)
-            }
-            TokenType::Fn => {
-                let start = self.next().unwrap().span.start;
+        self.consume(TokenType::Colon)?;
+        self.type_()?;
 
-                let (name, _) = self.ident()?;
+        self.consume(TokenType::Eq)?;
+        self.expr()
+    }
 
-                let params =
-                    self.delimited_list(Self::binding, TokenType::LParen, TokenType::RParen)?;
+    fn function_item(&mut self) -> ParseResult<()> {
+        self.next();
 
-                let return_type = if self.consume_at(TokenType::Colon) {
-                    Some(self.type_()?)
-                } else {
-                    None
-                };
+        let (name, _) = self.ident()?;
+        self.rename_context(format!("fn `{name}`"));
+
+        self.in_context("parameter list", |this| {
+            this.delimited_list(
+                NodeKind::ParamList,
+                Self::binding,
+                TokenType::LParen,
+                TokenType::RParen,
+            )
+        })?;
+
+        let return_type = self.consume_at(TokenType::Colon);
+        if return_type {
+            self.in_context("return type", Self::type_)?;
+        }
 
-                self.consume(TokenType::Arrow)?;
+        let alternatives: &[_] = if return_type {
+            &[]
+        } else {
+            &[TokenType::Colon]
+        };
+        self.consume_expecting(TokenType::Arrow, alternatives)?;
 
-                let body = self.expression()?;
+        self.expr()
+    }
 
-                let end = body.span.end;
+    fn struct_item(&mut self) -> ParseResult<()> {
+        self.next();
 
-                Item::Function {
-                    name,
-                    params: params.inner,
-                    return_type,
-                    body,
-                }
-                .spanned(start..end)
-            }
-            TokenType::Struct => {
-                let start = self.next().unwrap().span.start;
+        let name = self.type_name()?;
+        self.rename_context(format!("struct `{name}`"));
 
-                let (name, generic_params) = self.type_name()?;
+        self.fields()
+    }
 
-                let Spanned { inner: fields, span} = self.fields()?;
-                let end = span.end;
+    fn enum_item(&mut self) -> ParseResult<()> {
+        self.next();
 
-                Item::Struct {
-                    name,
-                    generic_params,
-                    fields,
-                }
-                .spanned(start..end)
-            }
-            TokenType::Enum => {
-                let start = self.next().unwrap().span.start;
-
-                let (name, generic_params) = self.type_name()?;
-
-                let Spanned { inner: variants, span: variants_span} = self.delimited_list(
-                    |this| {
-                        let (variant_name, name_span) = this.ident()?;
-                        let start = name_span.start;
-
-                        Ok(match this.peek() {
-                            TokenType::LBrace => {
-                                let Spanned { inner: fields, span: fields_span} = this.fields()?;
-                                Variant::Struct(variant_name, fields)
-                                    .spanned(start..fields_span.end)
-                            }
-                            TokenType::LParen => {
-                                let Spanned { inner: vals, span} = this.delimited_list(
-                                    Self::type_,
-                                    TokenType::LParen,
-                                    TokenType::RParen,
-                                )?;
-
-                                Variant::Tuple(variant_name, vals).spanned(start..span.end)
-                            }
-                            TokenType::Comma => Variant::Unit(variant_name).spanned(name_span),
-                            token => {
-                                return Err(ParseError::Unexpected(
-                                    token,
-                                    Some("after variant name. expected one of `,` `(` `{`".into()),
-                                ));
-                            }
-                        })
-                    },
+        let name = self.type_name()?;
+        self.rename_context(format!("enum `{name}`"));
+
+        self.in_context("variant list", |this| {
+            this.delimited_list(
+                NodeKind::VariantList,
+                Self::variant,
+                TokenType::LBrace,
+                TokenType::RBrace,
+            )
+        })
+    }
+
+    fn variant(&mut self) -> ParseResult<()> {
+        self.node(NodeKind::Variant, |this| {
+            this.ident()?;
+
+            match this.peek() {
+                TokenType::LBrace => this.fields(),
+                TokenType::LParen => this.delimited_list(
+                    NodeKind::TypeList,
+                    Self::type_,
+                    TokenType::LParen,
+                    TokenType::RParen,
+                ),
+                TokenType::Comma | TokenType::RBrace => Ok(()),
+                _ => Err(this.mismatched(vec![
+                    TokenType::Comma,
+                    TokenType::LParen,
                     TokenType::LBrace,
                     TokenType::RBrace,
-                )?;
-
-                Item::Enum {
-                    name,
-                    generic_params,
-                    variants,
-                }
-                .spanned(start..variants_span.end)
-            }
-            token => {
-                return Err(ParseError::Unexpected(token, Some("start of item".into())));
+                ])),
             }
         })
     }
 
-    fn type_name(&mut self) -> ParseResult<(String, Vec<String>)> {
+    /// Parses the name of a type definition and its generic parameters, returning the name.
+    fn type_name(&mut self) -> ParseResult<String> {
         let (name, _) = self.ident()?;
 
-        let generic_params = if self.at(TokenType::LAngle) {
-            self.delimited_list(
-                |this| this.ident().map(|v| v.0),
-                TokenType::LAngle,
-                TokenType::RAngle,
-            )?
-            .inner
-        } else {
-            Vec::new()
-        };
+        if self.at(TokenType::LAngle) {
+            self.in_context("generic parameters", |this| {
+                this.delimited_list(
+                    NodeKind::GenericParamList,
+                    |this| this.ident().map(|_| ()),
+                    TokenType::LAngle,
+                    TokenType::RAngle,
+                )
+            })?;
+        }
 
-        Ok((name, generic_params))
+        Ok(name)
     }
 
-    fn fields(&mut self) -> ParseResult<Spanned<Vec<FieldS>>> {
-        self.delimited_list(
-            |this| {
-                let (name, start) = match this.peek() {
-                    TokenType::Ident => {
-                        let span = this.next().unwrap().span;
-
-                        (this.input[Range::from(span)].to_string(), span.start)
-                    }
-                    other_type => {
-                        return Err(ParseError::Mismatched {
-                            expected: TokenType::Ident,
-                            found: other_type,
-                        });
-                    }
-                };
-
-                this.consume(TokenType::Colon)?;
-
-                let ty = this.type_()?;
-                let end = ty.span.end;
-
-                Ok(Field { name, ty }.spanned(start..end))
-            },
-            TokenType::LBrace,
-            TokenType::RBrace,
-        )
+    fn fields(&mut self) -> ParseResult<()> {
+        self.in_context("field list", |this| {
+            this.delimited_list(
+                NodeKind::FieldList,
+                |this| {
+                    this.node(NodeKind::Field, |this| {
+                        match this.peek() {
+                            TokenType::Ident => this.next(),
+                            _ => return Err(this.mismatched(vec![TokenType::Ident])),
+                        };
+
+                        this.consume(TokenType::Colon)?;
+                        this.type_()
+                    })
+                },
+                TokenType::LBrace,
+                TokenType::RBrace,
+            )
+        })
     }
 }
+
+/// The root of the tree for a source `len` bytes long, which spans all of it.
+pub(super) fn file_node(children: Vec<Element>, len: usize) -> Node {
+    let mut tree = Node::new(NodeKind::File, children, 0);
+    tree.span = (0..len).into();
+    tree
+}
@@ -0,0 +1,464 @@
+//! Derives the abstract syntax tree from the concrete one.
+//!
+//! Every node outside of an error node is complete, because a construct that fails to
+//! parse takes whatever it is part of down with it, up to the nearest item or statement,
+//! which is then wrapped in an error node. So the parts of a node can be relied on here.
+
+use crate::{
+    helpers::Spanned,
+    lexer::{Number, Token, TokenType, ident_name, number_value, string_value},
+};
+
+use super::{
+    ast::{
+        Ast, Binding, BindingS, Expr, ExprS, Field, FieldInit, FieldInitS, FieldPattern,
+        FieldPatternS, FieldS, Item, ItemS, MatchArm, MatchArmS, Path, Pattern, PatternS,
+        StringPart, Type, TypeS, Unop, Variant, VariantS,
+    },
+    cst::{Element, Node, NodeKind},
+    expressions::binary_op,
+};
+
+fn text(source: &str, token: Token) -> &str {
+    &source[token.span.start..token.span.end]
+}
+
+/// The name the identifier `token` stands for.
+fn ident(source: &str, token: Token) -> String {
+    ident_name(text(source, token))
+}
+
+/// The name a node defines or refers to, which is its first identifier.
+fn name(node: &Node, source: &str) -> String {
+    ident(source, node.token(TokenType::Ident).unwrap())
+}
+
+fn first_expr(node: &Node) -> &Node {
+    node.nodes().find(|node| node.kind.is_expr()).unwrap()
+}
+
+fn first_type(node: &Node) -> Option<&Node> {
+    node.nodes().find(|node| node.kind.is_type())
+}
+
+/// The nodes in the list node of type `kind` directly within `node`, if there is one.
+fn list(node: &Node, kind: NodeKind) -> impl Iterator<Item = &Node> {
+    node.node(kind).into_iter().flat_map(Node::nodes)
+}
+
+pub fn file(node: &Node, source: &str) -> Ast {
+    node.nodes()
+        .map(|item_node| item(item_node, source))
+        .collect()
+}
+
+pub fn item(node: &Node, source: &str) -> ItemS {
+    let item = match node.kind {
+        NodeKind::ConstItem => Item::Const {
+            docs: node.docs(source),
+            name: name(node, source),
+            ty: type_(first_type(node).unwrap(), source),
+            value: expr(first_expr(node), source),
+        },
+        NodeKind::FnItem => Item::Function {
+            docs: node.docs(source),
+            name: name(node, source),
+            params: bindings(node, source),
+            return_type: first_type(node).map(|ty| type_(ty, source)),
+            body: expr(first_expr(node), source),
+        },
+        NodeKind::StructItem => Item::Struct {
+            docs: node.docs(source),
+            name: name(node, source),
+            generic_params: generic_params(node, source),
+            fields: fields(node, source),
+        },
+        NodeKind::EnumItem => Item::Enum {
+            docs: node.docs(source),
+            name: name(node, source),
+            generic_params: generic_params(node, source),
+            variants: list(node, NodeKind::VariantList)
+                .map(|variant_node| variant(variant_node, source))
+                .collect(),
+        },
+        NodeKind::Error => Item::Error,
+        kind => unreachable!("{kind:?} is not an item"),
+    };
+
+    item.spanned(node.span)
+}
+
+fn generic_params(node: &Node, source: &str) -> Vec<String> {
+    node.node(NodeKind::GenericParamList)
+        .into_iter()
+        .flat_map(Node::tokens)
+        .filter(|token| token.inner == TokenType::Ident)
+        .map(|token| ident(source, token))
+        .collect()
+}
+
+fn fields(node: &Node, source: &str) -> Vec<FieldS> {
+    list(node, NodeKind::FieldList)
+        .map(|field| {
+            Field {
+                name: name(field, source),
+                ty: type_(first_type(field).unwrap(), source),
+            }
+            .spanned(field.span)
+        })
+        .collect()
+}
+
+fn variant(node: &Node, source: &str) -> VariantS {
+    let name = name(node, source);
+
+    let variant = if node.node(NodeKind::FieldList).is_some() {
+        Variant::Struct(name, fields(node, source))
+    } else if let Some(types) = node.node(NodeKind::TypeList) {
+        Variant::Tuple(name, types.nodes().map(|ty| type_(ty, source)).collect())
+    } else {
+        Variant::Unit(name)
+    };
+
+    variant.spanned(node.span)
+}
+
+/// The bindings in the parameter list of a function or lambda.
+fn bindings(node: &Node, source: &str) -> Vec<BindingS> {
+    list(node, NodeKind::ParamList)
+        .map(|param| binding(param, source))
+        .collect()
+}
+
+/// A binding, which is a [`Binding::Var`] if its pattern is a plain name.
+fn binding(node: &Node, source: &str) -> BindingS {
+    let type_annotation = first_type(node).map(|ty| type_(ty, source));
+    let pattern = pattern(
+        node.nodes().find(|node| node.kind.is_pattern()).unwrap(),
+        source,
+    );
+
+    let binding = match pattern.inner {
+        Pattern::Binding {
+            mutable,
+            ident,
+            subpattern: None,
+        } => Binding::Var {
+            mutable,
+            ident,
+            type_annotation,
+        },
+        _ => Binding::Pattern {
+            pattern: Box::new(pattern),
+            type_annotation,
+        },
+    };
+    binding.spanned(node.span)
+}
+
+fn type_(node: &Node, source: &str) -> TypeS {
+    let types = |list: &Node| list.nodes().map(|ty| type_(ty, source)).collect();
+
+    let ty = match node.kind {
+        NodeKind::NamedType => Type::Named {
+            name: name(node, source),
+            generics: node
+                .node(NodeKind::GenericArgList)
+                .map_or_else(Vec::new, types),
+        },
+        NodeKind::ArrayType => Type::Array(Box::new(type_(first_type(node).unwrap(), source))),
+        NodeKind::TupleType => Type::Tuple(types(node)),
+        NodeKind::FnType => Type::Fn {
+            params: types(node.node(NodeKind::TypeList).unwrap()),
+            result: Box::new(type_(first_type(node).unwrap(), source)),
+        },
+        kind => unreachable!("{kind:?} is not a type"),
+    };
+
+    ty.spanned(node.span)
+}
+
+pub fn expr(node: &Node, source: &str) -> ExprS {
+    let exprs = |node: &Node| -> Vec<ExprS> {
+        node.nodes()
+            .filter(|node| node.kind.is_expr())
+            .map(|node| expr(node, source))
+            .collect()
+    };
+    let boxed = |node: &Node| Box::new(expr(node, source));
+
+    let expr = match node.kind {
+        NodeKind::Literal => literal(node.tokens().next().unwrap(), source),
+        NodeKind::NameExpr => Expr::Ident(name(node, source)),
+        NodeKind::PathExpr => Expr::Path(path(node, source)),
+        NodeKind::StructExpr => struct_literal(node, source),
+        // the parentheses are only kept in the span
+        NodeKind::ParenExpr => expr(first_expr(node), source).inner,
+        NodeKind::TupleExpr => Expr::Tuple(exprs(node)),
+        NodeKind::ArrayExpr => Expr::Array(exprs(node)),
+        NodeKind::InterpolationExpr => Expr::Interpolation(
+            node.children
+                .iter()
+                .filter_map(|child| match child {
+                    Element::Node(node) => Some(StringPart::Expr(expr(node, source))),
+                    Element::Token(token) if !token.inner.is_skipped() => {
+                        let (text, _) = string_value(*token, source);
+                        (!text.is_empty()).then_some(StringPart::Text(text))
+                    }
+                    Element::Token(_) => None,
+                })
+                .collect(),
+        ),
+        NodeKind::PrefixExpr => Expr::UnaryOp {
+            op: match node.tokens().next().unwrap().inner {
+                TokenType::Minus => Unop::Neg,
+                TokenType::Bang => Unop::Not,
+                token => unreachable!("{token} is not a prefix operator"),
+            },
+            expr: boxed(first_expr(node)),
+        },
+        NodeKind::BinaryExpr => {
+            let mut operands = node.nodes();
+            Expr::BinaryOp {
+                op: binary_op(node.tokens().next().unwrap().inner).unwrap(),
+                lhs: boxed(operands.next().unwrap()),
+                rhs: boxed(operands.next().unwrap()),
+            }
+        }
+        NodeKind::CallExpr => Expr::FnCall {
+            fun: boxed(first_expr(node)),
+            args: exprs(node.node(NodeKind::ArgList).unwrap()),
+        },
+        NodeKind::IndexExpr => {
+            let mut operands = node.nodes();
+            Expr::Index {
+                arr: boxed(operands.next().unwrap()),
+                index: boxed(operands.next().unwrap()),
+            }
+        }
+        NodeKind::FieldExpr => {
+            let field = node.token(TokenType::Ident).unwrap();
+            Expr::FieldAccess {
+                base: boxed(first_expr(node)),
+                field: Spanned::span(ident(source, field), field.span),
+            }
+        }
+        NodeKind::IfExpr => {
+            let mut parts = node.nodes();
+            Expr::If {
+                cond: boxed(parts.next().unwrap()),
+                th: boxed(parts.next().unwrap()),
+                el: parts.next().map(boxed),
+            }
+        }
+        NodeKind::LetExpr => Expr::Let {
+            binding: binding(node.node(NodeKind::Binding).unwrap(), source),
+            value: boxed(first_expr(node)),
+        },
+        NodeKind::AssignExpr => {
+            let token = node.token(TokenType::Ident).unwrap();
+            Expr::Assign {
+                ident: Spanned::span(ident(source, token), token.span),
+                value: boxed(first_expr(node)),
+            }
+        }
+        NodeKind::LambdaExpr => Expr::Lambda {
+            params: bindings(node, source),
+            return_type: first_type(node).map(|ty| type_(ty, source)),
+            body: boxed(first_expr(node)),
+        },
+        NodeKind::BlockExpr => Expr::Block {
+            exprs: exprs(node),
+            trailing: !ends_with_semicolon(node),
+        },
+        NodeKind::MatchExpr => Expr::Match {
+            scrutinee: boxed(first_expr(node)),
+            arms: match_arms(node, source),
+        },
+        NodeKind::PlaceholderExpr => Expr::Placeholder,
+        NodeKind::Error => Expr::Error,
+        kind => unreachable!("{kind:?} is not an expression"),
+    };
+
+    expr.spanned(node.span)
+}
+
+fn match_arms(node: &Node, source: &str) -> Vec<MatchArmS> {
+    node.nodes()
+        .filter(|node| node.kind == NodeKind::MatchArm)
+        .map(|arm| match_arm(arm, source))
+        .collect()
+}
+
+fn match_arm(node: &Node, source: &str) -> MatchArmS {
+    MatchArm {
+        pattern: pattern(
+            node.nodes().find(|node| node.kind.is_pattern()).unwrap(),
+            source,
+        ),
+        guard: node
+            .node(NodeKind::MatchGuard)
+            .map(|guard| expr(first_expr(guard), source)),
+        body: expr(first_expr(node), source),
+    }
+    .spanned(node.span)
+}
+
+pub fn pattern(node: &Node, source: &str) -> PatternS {
+    let patterns = |node: &Node| -> Vec<PatternS> {
+        node.nodes()
+            .filter(|node| node.kind.is_pattern())
+            .map(|node| pattern(node, source))
+            .collect()
+    };
+
+    let pattern = match node.kind {
+        NodeKind::WildcardPattern => Pattern::Wildcard,
+        NodeKind::RestPattern => Pattern::Rest,
+        NodeKind::BindingPattern => Pattern::Binding {
+            mutable: node.token(TokenType::Mut).is_some(),
+            ident: name(node, source),
+            subpattern: node
+                .nodes()
+                .next()
+                .map(|subpattern| Box::new(pattern(subpattern, source))),
+        },
+        NodeKind::LiteralPattern => Pattern::Literal(expr(node.nodes().next().unwrap(), source)),
+        NodeKind::RangePattern => {
+            let mut bounds = node
+                .nodes()
+                .map(|bound| expr(bound.nodes().next().unwrap(), source));
+            Pattern::Range {
+                start: bounds.next().unwrap(),
+                end: bounds.next().unwrap(),
+                inclusive: node.token(TokenType::DotDotEq).is_some(),
+            }
+        }
+        // the parentheses are only kept in the span
+        NodeKind::ParenPattern => pattern(node.nodes().next().unwrap(), source).inner,
+        NodeKind::TuplePattern => Pattern::Tuple(patterns(node)),
+        NodeKind::ArrayPattern => Pattern::Array(patterns(node)),
+        NodeKind::StructPattern => {
+            let fields = node.node(NodeKind::FieldPatternList).unwrap();
+            Pattern::Struct {
+                path: path(node, source),
+                fields: fields
+                    .nodes()
+                    .filter(|node| node.kind == NodeKind::FieldPattern)
+                    .map(|field| field_pattern(field, source))
+                    .collect(),
+                rest: fields.node(NodeKind::RestPattern).is_some(),
+            }
+        }
+        NodeKind::TupleVariantPattern => Pattern::TupleVariant {
+            path: path(node, source),
+            elements: patterns(node.node(NodeKind::PatternList).unwrap()),
+        },
+        NodeKind::PathPattern => Pattern::Path(path(node, source)),
+        NodeKind::OrPattern => Pattern::Or(patterns(node)),
+        kind => unreachable!("{kind:?} is not a pattern"),
+    };
+
+    pattern.spanned(node.span)
+}
+
+/// The path a pattern starts with, made of the identifiers directly within it.
+fn path(node: &Node, source: &str) -> Path {
+    let mut idents = node
+        .tokens()
+        .filter(|token| token.inner == TokenType::Ident)
+        .map(|token| ident(source, token));
+    Path {
+        name: idents.next().unwrap(),
+        variant: idents.next(),
+    }
+}
+
+fn field_pattern(node: &Node, source: &str) -> FieldPatternS {
+    let name = name(node, source);
+    // `x` and `mut x` are short for `x: x` and `x: mut x`
+    let pattern = node.nodes().next().map_or_else(
+        || {
+            Pattern::Binding {
+                mutable: node.token(TokenType::Mut).is_some(),
+                ident: name.clone(),
+                subpattern: None,
+            }
+            .spanned(node.span)
+        },
+        |pattern_node| pattern(pattern_node, source),
+    );
+
+    FieldPattern { name, pattern }.spanned(node.span)
+}
+
+fn struct_literal(node: &Node, source: &str) -> Expr {
+    let list = node.node(NodeKind::FieldInitList).unwrap();
+    Expr::Struct {
+        path: path(node, source),
+        fields: list
+            .nodes()
+            .filter(|node| node.kind == NodeKind::FieldInit)
+            .map(|field| field_init(field, source))
+            .collect(),
+        base: list
+            .nodes()
+            .find(|node| node.kind.is_expr())
+            .map(|node| Box::new(expr(node, source))),
+    }
+}
+
+fn field_init(node: &Node, source: &str) -> FieldInitS {
+    let name = name(node, source);
+    // `x` is short for `x: x`
+    let value = node.nodes().next().map_or_else(
+        || Expr::Ident(name.clone()).spanned(node.span),
+        |value| expr(value, source),
+    );
+
+    FieldInit { name, value }.spanned(node.span)
+}
+
+fn literal(token: Token, source: &str) -> Expr {
+    match token.inner {
+        // literals out of range were reported by the parser, which knows if they are negated
+        TokenType::IntLit | TokenType::FloatLit => match number_value(token, source, true).0 {
+            Number::Int(value, suffix) => Expr::Int(value, suffix),
+            Number::Float(value, suffix) => Expr::Float(value, suffix),
+        },
+        TokenType::StringLit | TokenType::RawStringLit | TokenType::MultiLineStringLit => {
+            Expr::Str(string_value(token, source).0)
+        }
+        // an empty char literal was reported by the parser
+        TokenType::CharLit => Expr::Char(
+            string_value(token, source)
+                .0
+                .chars()
+                .next()
+                .unwrap_or_default(),
+        ),
+        TokenType::True => Expr::Bool(true),
+        TokenType::False => Expr::Bool(false),
+        token => unreachable!("{token} is not a literal"),
+    }
+}
+
+/// Whether the last statement of a block is followed by a `;`, so that the block has no
+/// value.
+fn ends_with_semicolon(block: &Node) -> bool {
+    let mut parts = block
+        .children
+        .iter()
+        .rev()
+        .filter(|child| !matches!(child, Element::Token(token) if token.inner.is_skipped()));
+
+    // skip the closing brace
+    parts.next();
+    matches!(
+        parts.next(),
+        Some(Element::Token(Token {
+            inner: TokenType::Semicolon,
+            ..
+        }))
+    )
+}
@@ -1,15 +1,24 @@
 pub mod ast;
+pub mod cst;
 mod error;
 mod expressions;
 mod helpers;
+mod incremental;
 mod items;
+mod lower;
+mod patterns;
 #[cfg(test)]
 mod test;
 
-use crate::lexer::{Lexer, Token, TokenType};
-use std::iter::Peekable;
+use crate::{
+    helpers::Span,
+    lexer::{Lexer, Token, TokenType, lex_error},
+};
+use cst::{Element, Node, NodeKind};
+use std::{iter::Peekable, mem};
 
-pub use error::{ParseError, ParseResult};
+pub use error::{ParseError, ParseErrorKind, ParseResult, describe, describe_all};
+pub use incremental::ParsedFile;
 
 pub struct Parser<'input, I>
 where
@@ -17,25 +26,121 @@ where
 {
     input: &'input str,
     tokens: Peekable<I>,
+    /// Descriptions of the constructs currently being parsed, outermost first.
+    context: Vec<String>,
+    /// Errors that were recovered from, in the order they were found.
+    errors: Vec<ParseError>,
+    /// The end of the last token that was consumed.
+    last_end: usize,
+    /// Whitespace and comments read ahead of the next token, which haven't been added to
+    /// the tree yet.
+    trivia: Vec<Token>,
+    /// The children of each node being built, outermost first.
+    nodes: Vec<Vec<Element>>,
+    /// Whether a `{` after a name starts a struct literal. It doesn't in the scrutinee
+    /// of a `match`, where it starts the arms.
+    struct_literals: bool,
+}
+
+/// How many tokens after the one found instead of a missing token have to parse once
+/// the missing token is put in, for it to count as only missing.
+const INSERTION_LOOKAHEAD: usize = 3;
+
+/// Keeps the tokens that `errors` say are missing only where putting them in lets `input`
+/// parse for a few tokens past the one found instead. Where it doesn't, another token was
+/// most likely written in place of the one expected.
+pub(crate) fn confirm_insertions(input: &str, errors: &mut [ParseError]) {
+    let tokens: Vec<_> = Lexer::new(input).collect();
+
+    for err in errors {
+        let Some((at, text)) = err.insertion() else {
+            continue;
+        };
+
+        let found = tokens.partition_point(|token| token.span.start < err.span.start);
+        let checked_end = tokens
+            .get(found + INSERTION_LOOKAHEAD)
+            .map_or(input.len(), |token| token.span.start);
+
+        let patched = [&input[..at], &text, &input[at..]].concat();
+        let (_, patched_errors) = Parser::new(&patched).syntax_tree_unconfirmed();
+        let checked = at..checked_end + text.len();
+        if patched_errors
+            .iter()
+            .any(|patched| checked.contains(&patched.span.start))
+        {
+            err.missing_at = None;
+        }
+    }
 }
 
 impl<'input> Parser<'input, Lexer<'input>> {
     pub fn new(input: &'input str) -> Self {
+        Parser::resume(input, Lexer::with_trivia(input), Vec::new())
+    }
+}
+
+impl<'input, I: Iterator<Item = Token>> Parser<'input, I> {
+    /// A parser that carries on from somewhere in `input`, with `trivia` set aside from
+    /// before the first of `tokens`.
+    fn resume(input: &'input str, tokens: I, trivia: Vec<Token>) -> Self {
+        let mut tokens = tokens.peekable();
+        let start = trivia
+            .first()
+            .or_else(|| tokens.peek())
+            .map_or(0, |token| token.span.start);
         Parser {
             input,
-            tokens: Lexer::new(input).peekable(),
+            tokens,
+            context: Vec::new(),
+            errors: Vec::new(),
+            last_end: start,
+            trivia,
+            nodes: vec![Vec::new()],
+            struct_literals: true,
         }
     }
 }
 
 impl<I: Iterator<Item = Token>> Parser<'_, I> {
+    /// The next token that isn't trivia or a stray character, having set aside the ones
+    /// before it. Stray characters and unterminated comments are reported on the way.
+    fn peek_token(&mut self) -> Option<&Token> {
+        while let Some(token) = self.tokens.next_if(|token| token.inner.is_skipped()) {
+            self.check_token(token);
+            self.trivia.push(token);
+        }
+        self.tokens.peek()
+    }
+
+    /// Records an error if `token` is malformed as a whole, returning whether it was.
+    fn check_token(&mut self, token: Token) -> bool {
+        let Some(err) = lex_error(token, self.input) else {
+            return false;
+        };
+
+        self.errors.push(ParseError {
+            kind: ParseErrorKind::InvalidToken(err),
+            span: token.span,
+            context: self.context.clone(),
+            missing_at: None,
+        });
+        true
+    }
+
     /// Look-ahead one token and see what kind of token it is.
     pub(crate) fn peek(&mut self) -> TokenType {
-        self.tokens
-            .peek()
+        self.peek_token()
             .map_or(TokenType::Eof, |token| token.inner)
     }
 
+    /// The span of the next token, or an empty span at the end of the input.
+    pub(crate) fn peek_span(&mut self) -> Span {
+        let end = self.input.len();
+        self.peek_token()
+            .map_or_else(|| (end..end).into(), |token| token.span)
+    }
+
     /// Check if the next token is the same variant as another token.
     pub(crate) fn at(&mut self, token: TokenType) -> bool {
         self.peek() == token
@@ -49,22 +154,197 @@ impl<I: Iterator<Item = Token>> Parser<'_, I> {
         at
     }
 
-    /// Get the next token.
+    /// Get the next token, adding it and the trivia before it to the node being built.
     pub(crate) fn next(&mut self) -> Option<Token> {
-        self.tokens.next()
+        self.peek_token()?;
+        self.flush_trivia();
+
+        let token = self.tokens.next()?;
+        self.last_end = token.span.end;
+        self.children().push(Element::Token(token));
+        Some(token)
+    }
+
+    /// The children of the node being built.
+    fn children(&mut self) -> &mut Vec<Element> {
+        self.nodes.last_mut().unwrap()
+    }
+
+    /// Adds the trivia read so far to the node being built.
+    fn flush_trivia(&mut self) {
+        let trivia = mem::take(&mut self.trivia);
+        self.children()
+            .extend(trivia.into_iter().map(Element::Token));
+    }
+
+    /// Runs `f`, collecting the tokens it consumes into a node of type `kind`. The node
+    /// is built even if `f` fails, out of the tokens consumed before it did.
+    pub(crate) fn node<T>(
+        &mut self,
+        kind: NodeKind,
+        f: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        // leave the trivia before the node outside of it
+        self.checkpoint();
+        let empty_at = self.peek_span().start;
+        self.nodes.push(Vec::new());
+        let result = f(self);
+
+        let children = self.nodes.pop().unwrap();
+        self.children()
+            .push(Element::Node(Node::new(kind, children, empty_at)));
+
+        result
+    }
+
+    /// A point in the node being built, at the start of the next token, which
+    /// [`Self::wrap`] can later turn into the start of a node.
+    ///
+    /// The doc comments right before the next token are left to go into the node, along
+    /// with the whitespace between them.
+    pub(crate) fn checkpoint(&mut self) -> usize {
+        self.peek_token();
+
+        let docs = self
+            .trivia
+            .iter()
+            .rposition(|token| {
+                !matches!(token.inner, TokenType::DocComment | TokenType::Whitespace)
+            })
+            .map_or(0, |i| i + 1);
+        let docs = self.trivia[docs..]
+            .iter()
+            .position(|token| token.inner == TokenType::DocComment)
+            .map_or(self.trivia.len(), |i| docs + i);
+
+        let docs = self.trivia.split_off(docs);
+        self.flush_trivia();
+        self.trivia = docs;
+
+        self.children().len()
+    }
+
+    /// Wraps everything added to the node being built since `checkpoint` in a node of
+    /// type `kind`.
+    pub(crate) fn wrap(&mut self, checkpoint: usize, kind: NodeKind) {
+        let empty_at = self.peek_span().start;
+        let children = self.children().split_off(checkpoint);
+        self.children()
+            .push(Element::Node(Node::new(kind, children, empty_at)));
+    }
+
+    /// The node last added to the one being built.
+    pub(crate) fn last_node(&self) -> &Node {
+        match self.nodes.last().and_then(|children| children.last()) {
+            Some(Element::Node(node)) => node,
+            _ => unreachable!("no node has been built"),
+        }
     }
 
     /// Move forward one token in the input and check
     /// that we pass the kind of token we expect.
     pub(crate) fn consume(&mut self, expected: TokenType) -> ParseResult<Token> {
-        let next = self.next().ok_or(ParseError::Missing)?;
-        if next.inner == expected {
-            Ok(next)
+        self.consume_expecting(expected, &[])
+    }
+
+    /// Like [`Self::consume`], but `alternatives` were also valid at this point,
+    /// which is reported if the next token is neither.
+    pub(crate) fn consume_expecting(
+        &mut self,
+        expected: TokenType,
+        alternatives: &[TokenType],
+    ) -> ParseResult<Token> {
+        if self.peek_token().is_none() {
+            return Err(self.error(ParseErrorKind::Missing));
+        }
+
+        if self.at(expected) {
+            Ok(self.next().unwrap())
         } else {
-            Err(ParseError::Mismatched {
-                expected,
-                found: next.inner,
-            })
+            let mut expected = vec![expected];
+            expected.extend_from_slice(alternatives);
+            Err(self.mismatched(expected))
+        }
+    }
+
+    /// Builds an error at the next token, recording what is currently being parsed.
+    pub(crate) fn error(&mut self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            span: self.peek_span(),
+            context: self.context.clone(),
+            missing_at: None,
+        }
+    }
+
+    /// The next token is not one of `expected`.
+    pub(crate) fn mismatched(&mut self, expected: Vec<TokenType>) -> ParseError {
+        let found = self.peek();
+        // whether a single token is only missing is decided once parsing is done, see
+        // `confirm_insertions`
+        let missing_at =
+            matches!(expected.as_slice(), [token] if token.is_fixed()).then_some(self.last_end);
+        ParseError {
+            missing_at,
+            ..self.error(ParseErrorKind::Mismatched { expected, found })
+        }
+    }
+
+    /// The next token can't start or continue `expected`.
+    pub(crate) fn unexpected(&mut self, expected: impl Into<String>) -> ParseError {
+        let found = self.peek();
+        self.error(ParseErrorKind::Unexpected {
+            found,
+            expected: expected.into(),
+        })
+    }
+
+    /// Runs `f` with `context` describing the construct it parses, for use in error messages.
+    pub(crate) fn in_context<T>(
+        &mut self,
+        context: impl Into<String>,
+        f: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        self.context.push(context.into());
+        let result = f(self);
+        self.context.pop();
+        result
+    }
+
+    /// Runs `f` with struct literals allowed, or not, in what it parses.
+    pub(crate) fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let outer = mem::replace(&mut self.struct_literals, allowed);
+        let result = f(self);
+        self.struct_literals = outer;
+        result
+    }
+
+    /// Records `error` and skips ahead to the next token that `stop` accepts, or the end
+    /// of the input, without consuming it. Everything since `checkpoint`, including what
+    /// was parsed before the error, is wrapped in an error node.
+    pub(crate) fn recover(
+        &mut self,
+        error: ParseError,
+        checkpoint: usize,
+        mut stop: impl FnMut(TokenType) -> bool,
+    ) {
+        self.errors.push(error);
+
+        while !self.at(TokenType::Eof) && !stop(self.peek()) {
+            self.next();
+        }
+
+        self.wrap(checkpoint, NodeKind::Error);
+    }
+
+    /// Replaces the description of the innermost construct, once more is known about it.
+    pub(crate) fn rename_context(&mut self, context: impl Into<String>) {
+        if let Some(innermost) = self.context.last_mut() {
+            *innermost = context.into();
         }
     }
 }
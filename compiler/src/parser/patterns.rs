@@ -0,0 +1,195 @@
+use crate::lexer::{Token, TokenType};
+
+use super::{ParseResult, Parser, cst::NodeKind};
+
+impl<I: Iterator<Item = Token>> Parser<'_, I> {
+    /// Parses a pattern, with alternatives separated by `|`.
+    pub(crate) fn pattern(&mut self) -> ParseResult<()> {
+        let start = self.checkpoint();
+        self.single_pattern()?;
+
+        if self.at(TokenType::Pipe) {
+            while self.consume_at(TokenType::Pipe) {
+                self.single_pattern()?;
+            }
+            self.wrap(start, NodeKind::OrPattern);
+        }
+        Ok(())
+    }
+
+    /// Parses a pattern without alternatives, unless they are in parentheses.
+    pub(super) fn single_pattern(&mut self) -> ParseResult<()> {
+        let start = self.checkpoint();
+
+        match self.peek() {
+            TokenType::Underscore => {
+                self.next();
+                self.wrap(start, NodeKind::WildcardPattern);
+            }
+            TokenType::DotDot => {
+                self.next();
+                self.wrap(start, NodeKind::RestPattern);
+            }
+            TokenType::IntLit
+            | TokenType::FloatLit
+            | TokenType::StringLit
+            | TokenType::RawStringLit
+            | TokenType::MultiLineStringLit
+            | TokenType::CharLit
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Minus => {
+                self.literal_pattern()?;
+
+                if self.consume_at(TokenType::DotDot) || self.consume_at(TokenType::DotDotEq) {
+                    self.literal_pattern()?;
+                    self.wrap(start, NodeKind::RangePattern);
+                }
+            }
+            TokenType::LParen => {
+                self.next();
+                self.pattern()?;
+
+                let kind = if self.consume_at(TokenType::Comma) {
+                    while !self.at(TokenType::RParen) {
+                        self.pattern()?;
+
+                        if !self.consume_at(TokenType::Comma) {
+                            break;
+                        }
+                    }
+
+                    NodeKind::TuplePattern
+                } else {
+                    NodeKind::ParenPattern
+                };
+
+                self.consume(TokenType::RParen)?;
+                self.wrap(start, kind);
+            }
+            TokenType::LBracket => self.in_context("array pattern", |this| {
+                this.delimited_list(
+                    NodeKind::ArrayPattern,
+                    Self::pattern,
+                    TokenType::LBracket,
+                    TokenType::RBracket,
+                )
+            })?,
+            TokenType::Mut => {
+                self.next();
+                self.ident()?;
+                self.binding_pattern(start)?;
+            }
+            TokenType::Ident => {
+                self.next();
+
+                let path = self.consume_at(TokenType::ColonColon);
+                if path {
+                    self.ident()?;
+                }
+                match self.peek() {
+                    TokenType::LParen => {
+                        self.in_context("variant pattern", |this| {
+                            this.delimited_list(
+                                NodeKind::PatternList,
+                                Self::pattern,
+                                TokenType::LParen,
+                                TokenType::RParen,
+                            )
+                        })?;
+                        self.wrap(start, NodeKind::TupleVariantPattern);
+                    }
+                    TokenType::LBrace => {
+                        self.in_context("struct pattern", Self::field_patterns)?;
+                        self.wrap(start, NodeKind::StructPattern);
+                    }
+                    _ if path => self.wrap(start, NodeKind::PathPattern),
+                    _ => self.binding_pattern(start)?,
+                }
+            }
+            _ => return Err(self.unexpected("pattern")),
+        }
+
+        Ok(())
+    }
+
+    /// Finishes a binding pattern started at `start` after its name, parsing the pattern
+    /// after `@` if there is one.
+    fn binding_pattern(&mut self, start: usize) -> ParseResult<()> {
+        if self.consume_at(TokenType::At) {
+            self.single_pattern()?;
+        }
+        self.wrap(start, NodeKind::BindingPattern);
+        Ok(())
+    }
+
+    /// Parses a literal, or a negated number, as a pattern.
+    fn literal_pattern(&mut self) -> ParseResult<()> {
+        let literal = |this: &mut Self| {
+            this.node(NodeKind::Literal, |this| {
+                let token = this.next().unwrap();
+                this.check_literal(token);
+                Ok(())
+            })
+        };
+
+        self.node(NodeKind::LiteralPattern, |this| match this.peek() {
+            TokenType::IntLit
+            | TokenType::FloatLit
+            | TokenType::StringLit
+            | TokenType::RawStringLit
+            | TokenType::MultiLineStringLit
+            | TokenType::CharLit
+            | TokenType::True
+            | TokenType::False => literal(this),
+            TokenType::Minus => this.node(NodeKind::PrefixExpr, |this| {
+                this.next();
+                match this.peek() {
+                    TokenType::IntLit | TokenType::FloatLit => literal(this),
+                    _ => Err(this.mismatched(vec![TokenType::IntLit, TokenType::FloatLit])),
+                }
+            }),
+            _ => Err(this.unexpected("literal")),
+        })
+    }
+
+    /// Parses `{ x, y: pattern, .. }` in a struct pattern, where `..` can only come last.
+    fn field_patterns(&mut self) -> ParseResult<()> {
+        self.node(NodeKind::FieldPatternList, |this| {
+            this.consume(TokenType::LBrace)?;
+
+            let mut comma = true;
+            while !this.at(TokenType::RBrace) {
+                if this.at(TokenType::DotDot) {
+                    this.node(NodeKind::RestPattern, |this| {
+                        this.next();
+                        Ok(())
+                    })?;
+                    this.consume_at(TokenType::Comma);
+                    comma = true;
+                    break;
+                }
+
+                // `x` and `mut x` bind the field to a variable of the same name
+                this.node(NodeKind::FieldPattern, |this| {
+                    let mutable = this.consume_at(TokenType::Mut);
+                    this.ident()?;
+
+                    if !mutable && this.consume_at(TokenType::Colon) {
+                        this.pattern()?;
+                    }
+                    Ok(())
+                })?;
+
+                comma = this.consume_at(TokenType::Comma);
+                if !comma {
+                    break;
+                }
+            }
+            let alternatives: &[_] = if comma { &[] } else { &[TokenType::Comma] };
+            this.consume_expecting(TokenType::RBrace, alternatives)?;
+
+            Ok(())
+        })
+    }
+}
@@ -1,7 +1,15 @@
-use crate::helpers::Spanned;
+use crate::helpers::{Span, Spanned};
+use crate::lexer::{LexError, LiteralError, NumericSuffix, TokenType};
 
-use super::Parser;
-use super::ast::{Ast, Binding, Bop, Expr, ExprS, Field, Item, ItemS, Type, Unop, Variant};
+use super::{
+    ParseError, ParseErrorKind, ParsedFile, Parser,
+    cst::{Element, NodeKind},
+};
+
+use super::ast::{
+    Ast, Binding, BindingS, Bop, Expr, ExprS, Field, FieldInit, FieldPattern, Item, ItemS,
+    MatchArm, Path, Pattern, PatternS, StringPart, Type, Unop, Variant, partial_application,
+};
 
 fn parse_expr(input: &str) -> ExprS {
     let mut parser = Parser::new(input);
@@ -18,13 +26,18 @@ fn parse_ast(input: &str) -> Ast {
     parser.file().unwrap()
 }
 
+fn parse_error(input: &str) -> ParseError {
+    let mut parser = Parser::new(input);
+    parser.file().unwrap_err().remove(0)
+}
+
 #[test]
 fn parse_lit_expressions() {
     let expr = parse_expr("42");
-    assert_eq!(expr, Expr::Int(42).spanned(0..2));
+    assert_eq!(expr, Expr::Int(42, None).spanned(0..2));
 
     let expr = parse_expr("  2.7768");
-    assert_eq!(expr, Expr::Float(2.7768).spanned(2..8));
+    assert_eq!(expr, Expr::Float(2.7768, None).spanned(2..8));
 
     let expr = parse_expr(r#""I am a Str!""#);
     assert_eq!(expr, Expr::Str("I am a Str!".into()).spanned(0..13));
@@ -33,8 +46,8 @@ fn parse_lit_expressions() {
     assert_eq!(
         expr,
         Expr::Tuple(vec![
-            Expr::Int(42).spanned(1..3),
-            Expr::Tuple(vec![Expr::Int(2).spanned(5..6)]).spanned(4..8),
+            Expr::Int(42, None).spanned(1..3),
+            Expr::Tuple(vec![Expr::Int(2, None).spanned(5..6)]).spanned(4..8),
             Expr::Str("end".into()).spanned(9..14)
         ])
         .spanned(0..15)
@@ -44,10 +57,10 @@ fn parse_lit_expressions() {
     assert_eq!(
         expr,
         Expr::Array(vec![
-            Expr::Int(1).spanned(1..2),
-            Expr::Int(4).spanned(4..5),
-            Expr::Int(3).spanned(7..8),
-            Expr::Int(2).spanned(10..11)
+            Expr::Int(1, None).spanned(1..2),
+            Expr::Int(4, None).spanned(4..5),
+            Expr::Int(3, None).spanned(7..8),
+            Expr::Int(2, None).spanned(10..11)
         ])
         .spanned(0..12)
     );
@@ -75,7 +88,7 @@ fn parse_unop_expressions() {
             op: Unop::Neg,
             expr: Expr::UnaryOp {
                 op: Unop::Neg,
-                expr: Expr::Int(13).spanned(3..5).into(),
+                expr: Expr::Int(13, None).spanned(3..5).into(),
             }
             .spanned(1..6)
             .into()
@@ -91,11 +104,11 @@ fn parse_binop_expressions() {
         expr,
         Expr::BinaryOp {
             op: Bop::Add,
-            lhs: Expr::Int(4).spanned(0..1).into(),
+            lhs: Expr::Int(4, None).spanned(0..1).into(),
             rhs: Expr::BinaryOp {
                 op: Bop::Mul,
-                lhs: Expr::Int(2).spanned(4..5).into(),
-                rhs: Expr::Int(3).spanned(8..9).into()
+                lhs: Expr::Int(2, None).spanned(4..5).into(),
+                rhs: Expr::Int(3, None).spanned(8..9).into()
             }
             .spanned(4..9)
             .into()
@@ -110,12 +123,12 @@ fn parse_binop_expressions() {
             op: Bop::Add,
             lhs: Expr::BinaryOp {
                 op: Bop::Mul,
-                lhs: Expr::Int(4).spanned(0..1).into(),
-                rhs: Expr::Int(2).spanned(4..5).into()
+                lhs: Expr::Int(4, None).spanned(0..1).into(),
+                rhs: Expr::Int(2, None).spanned(4..5).into()
             }
             .spanned(0..5)
             .into(),
-            rhs: Expr::Int(3).spanned(8..9).into(),
+            rhs: Expr::Int(3, None).spanned(8..9).into(),
         }
         .spanned(0..9)
     );
@@ -127,12 +140,12 @@ fn parse_binop_expressions() {
             op: Bop::Sub,
             lhs: Expr::BinaryOp {
                 op: Bop::Sub,
-                lhs: Expr::Int(4).spanned(0..1).into(),
-                rhs: Expr::Int(2).spanned(4..5).into()
+                lhs: Expr::Int(4, None).spanned(0..1).into(),
+                rhs: Expr::Int(2, None).spanned(4..5).into()
             }
             .spanned(0..5)
             .into(),
-            rhs: Expr::Int(3).spanned(8..9).into(),
+            rhs: Expr::Int(3, None).spanned(8..9).into(),
         }
         .spanned(0..9)
     );
@@ -142,11 +155,11 @@ fn parse_binop_expressions() {
         expr,
         Expr::BinaryOp {
             op: Bop::Exp,
-            lhs: Expr::Int(4).spanned(0..1).into(),
+            lhs: Expr::Int(4, None).spanned(0..1).into(),
             rhs: Expr::BinaryOp {
                 op: Bop::Exp,
-                lhs: Expr::Int(2).spanned(5..6).into(),
-                rhs: Expr::Int(3).spanned(10..11).into()
+                lhs: Expr::Int(2, None).spanned(5..6).into(),
+                rhs: Expr::Int(3, None).spanned(10..11).into()
             }
             .spanned(5..11)
             .into()
@@ -161,18 +174,19 @@ fn parse_binop_expressions() {
             op: Bop::Xor,
             lhs: Expr::BinaryOp {
                 op: Bop::Xor,
-                lhs: Expr::Int(4).spanned(0..1).into(),
-                rhs: Expr::Int(2).spanned(4..5).into()
+                lhs: Expr::Int(4, None).spanned(0..1).into(),
+                rhs: Expr::Int(2, None).spanned(4..5).into()
             }
             .spanned(0..5)
             .into(),
-            rhs: Expr::Int(3).spanned(8..9).into(),
+            rhs: Expr::Int(3, None).spanned(8..9).into(),
         }
         .spanned(0..9)
     );
 }
 
 #[test]
+#[allow(clippy::too_many_lines)]
 fn parse_compound_expressions() {
     let expr = parse_expr("bar (  x, 2)");
     assert_eq!(
@@ -181,7 +195,7 @@ fn parse_compound_expressions() {
             fun: Expr::Ident("bar".into()).spanned(0..3).into(),
             args: vec![
                 Expr::Ident("x".into()).spanned(7..8),
-                Expr::Int(2).spanned(10..11),
+                Expr::Int(2, None).spanned(10..11),
             ],
         }
         .spanned(0..12)
@@ -191,7 +205,7 @@ fn parse_compound_expressions() {
     assert_eq!(
         expr,
         Expr::If {
-            cond: Expr::Float(0.5).spanned(4..7).into(),
+            cond: Expr::Float(0.5, None).spanned(4..7).into(),
             th: Expr::FnCall {
                 fun: Expr::Ident("foo".into()).spanned(9..12).into(),
                 args: Vec::new()
@@ -207,7 +221,7 @@ fn parse_compound_expressions() {
     assert_eq!(
         expr,
         Expr::If {
-            cond: Expr::Float(0.5).spanned(4..7).into(),
+            cond: Expr::Float(0.5, None).spanned(4..7).into(),
             th: Expr::Ident("foo".into()).spanned(9..12).into(),
             el: Some(Expr::Ident("bar".into()).spanned(18..21).into())
         }
@@ -251,8 +265,8 @@ fn parse_compound_expressions() {
             .spanned(0..22)
             .into(),
             args: vec![
-                Expr::Int(1).spanned(23..24).into(),
-                Expr::Int(2).spanned(26..27).into()
+                Expr::Int(1, None).spanned(23..24),
+                Expr::Int(2, None).spanned(26..27)
             ]
         }
         .spanned(0..28)
@@ -263,16 +277,16 @@ fn parse_compound_expressions() {
         expr,
         Expr::Index {
             arr: Expr::Array(vec![
-                Expr::Int(1).spanned(1..2),
-                Expr::Int(2).spanned(4..5),
-                Expr::Int(3).spanned(7..8)
+                Expr::Int(1, None).spanned(1..2),
+                Expr::Int(2, None).spanned(4..5),
+                Expr::Int(3, None).spanned(7..8)
             ])
             .spanned(0..9)
             .into(),
             index: Expr::BinaryOp {
                 op: Bop::Sub,
-                lhs: Expr::Int(1).spanned(10..11).into(),
-                rhs: Expr::Int(1).spanned(12..13).into()
+                lhs: Expr::Int(1, None).spanned(10..11).into(),
+                rhs: Expr::Int(1, None).spanned(12..13).into()
             }
             .spanned(10..13)
             .into()
@@ -308,10 +322,10 @@ fn parse_var_expresssions() {
             .spanned(4..5),
             value: Expr::BinaryOp {
                 op: Bop::Add,
-                lhs: Expr::Int(7).spanned(8..9).into(),
+                lhs: Expr::Int(7, None).spanned(8..9).into(),
                 rhs: Expr::FnCall {
                     fun: Expr::Ident("sin".into()).spanned(12..15).into(),
-                    args: vec![Expr::Float(3.0).spanned(16..18)]
+                    args: vec![Expr::Float(3.0, None).spanned(16..18)]
                 }
                 .spanned(12..19)
                 .into()
@@ -338,7 +352,7 @@ fn parse_var_expresssions() {
                 )
             }
             .spanned(4..14),
-            value: Expr::Int(7).spanned(17..18).into()
+            value: Expr::Int(7, None).spanned(17..18).into()
         }
         .spanned(0..18)
     );
@@ -353,11 +367,11 @@ fn parse_var_expresssions() {
             },
             value: Expr::BinaryOp {
                 op: Bop::Add,
-                lhs: Expr::Int(3).spanned(4..5).into(),
+                lhs: Expr::Int(3, None).spanned(4..5).into(),
                 rhs: Expr::BinaryOp {
                     op: Bop::Mul,
-                    lhs: Expr::Int(7).spanned(8..9).into(),
-                    rhs: Expr::Float(0.5).spanned(12..15).into()
+                    lhs: Expr::Int(7, None).spanned(8..9).into(),
+                    rhs: Expr::Float(0.5, None).spanned(12..15).into()
                 }
                 .spanned(8..15)
                 .into()
@@ -394,19 +408,19 @@ fn parse_block_expressions() {
                         type_annotation: None
                     }
                     .spanned(19..24),
-                    value: Expr::Int(5).spanned(27..28).into()
+                    value: Expr::Int(5, None).spanned(27..28).into()
                 }
                 .spanned(15..28),
                 Expr::BinaryOp {
                     op: Bop::Sub,
                     lhs: Expr::BinaryOp {
                         op: Bop::Add,
-                        lhs: Expr::Int(3).spanned(38..39).into(),
-                        rhs: Expr::Int(1).spanned(42..43).into()
+                        lhs: Expr::Int(3, None).spanned(38..39).into(),
+                        rhs: Expr::Int(1, None).spanned(42..43).into()
                     }
                     .spanned(38..43)
                     .into(),
-                    rhs: Expr::Int(2).spanned(46..47).into()
+                    rhs: Expr::Int(2, None).spanned(46..47).into()
                 }
                 .spanned(38..47),
                 Expr::Assign {
@@ -414,14 +428,14 @@ fn parse_block_expressions() {
                         inner: "y".into(),
                         span: (57..58).into()
                     },
-                    value: Expr::Int(1).spanned(61..62).into()
+                    value: Expr::Int(1, None).spanned(61..62).into()
                 }
                 .spanned(57..62),
                 Expr::If {
                     cond: Expr::BinaryOp {
                         op: Bop::Lt,
                         lhs: Expr::Ident("y".into()).spanned(76..77).into(),
-                        rhs: Expr::Int(3).spanned(80..81).into()
+                        rhs: Expr::Int(3, None).spanned(80..81).into()
                     }
                     .spanned(76..81)
                     .into(),
@@ -434,7 +448,7 @@ fn parse_block_expressions() {
                                     type_annotation: None
                                 }
                                 .spanned(101..102),
-                                value: Expr::Int(5).spanned(105..106).into()
+                                value: Expr::Int(5, None).spanned(105..106).into()
                             }
                             .spanned(97..106),
                             Expr::Ident("a".to_string()).spanned(120..121)
@@ -443,7 +457,7 @@ fn parse_block_expressions() {
                     }
                     .spanned(83..131)
                     .into(),
-                    el: Some(Expr::Int(32).spanned(137..139).into())
+                    el: Some(Expr::Int(32, None).spanned(137..139).into())
                 }
                 .spanned(72..139)
             ],
@@ -459,6 +473,7 @@ fn parse_const_items() {
     assert_eq!(
         item,
         Item::Const {
+            docs: vec![],
             name: "HELLO_WORLD".into(),
             ty: Type::Named {
                 name: "Str".into(),
@@ -474,15 +489,16 @@ fn parse_const_items() {
 #[test]
 fn parse_struct_items() {
     let item = parse_item(
-        r#"
+        r"
         struct Foo<T, U> {
             x: Str,
             bar: Bar<Baz<T>>
-        }"#,
+        }",
     );
     assert_eq!(
         item,
         Item::Struct {
+            docs: vec![],
             name: "Foo".into(),
             generic_params: vec!["T".into(), "U".into()],
             fields: vec![
@@ -519,22 +535,23 @@ fn parse_struct_items() {
             ]
         }
         .spanned(9..86)
-    )
+    );
 }
 
 #[test]
 fn parse_enum_items() {
     let item = parse_item(
-        r#"
+        r"
         enum Foo {
             X,
             Y(Bar),
             Z { baz:Baz, fizz: Buzz }
-        }"#,
+        }",
     );
     assert_eq!(
         item,
         Item::Enum {
+            docs: vec![],
             name: "Foo".into(),
             generic_params: vec![],
             variants: vec![
@@ -577,15 +594,16 @@ fn parse_enum_items() {
             ]
         }
         .spanned(9..102)
-    )
+    );
 }
 
 #[test]
 fn parse_function_items() {
-    let item = parse_item(r#"fn sum(mut a, b: Int) -> a + b"#);
+    let item = parse_item(r"fn sum(mut a, b: Int) -> a + b");
     assert_eq!(
         item,
         Item::Function {
+            docs: vec![],
             name: "sum".into(),
             params: vec![
                 Binding::Var {
@@ -616,13 +634,14 @@ fn parse_function_items() {
             .spanned(25..30)
         }
         .spanned(0..30)
-    )
+    );
 }
 
 #[test]
+#[allow(clippy::too_many_lines)]
 fn parse_file() {
     let items = parse_ast(
-        r#"
+        r"
         fn wow_we_did_it(mut x, bar: Bar<Baz<T>, U>): fn(Int): Int -> {
             let mut x: (Float, T) = -7.0 + sin(y);
             x = if (bar < 3) {
@@ -635,12 +654,13 @@ fn parse_file() {
         struct Foo<T, U> {
             x: Str,
             bar: Bar<Baz<T>, [U]>,
-        }"#,
+        }",
     );
 
     assert_eq!(
         items[0],
         Item::Function {
+            docs: vec![],
             name: "wow_we_did_it".into(),
             params: vec![
                 Binding::Var {
@@ -724,7 +744,7 @@ fn parse_file() {
                             op: Bop::Add,
                             lhs: Expr::UnaryOp {
                                 op: Unop::Neg,
-                                expr: Expr::Float(7.0).spanned(110..113).into()
+                                expr: Expr::Float(7.0, None).spanned(110..113).into()
                             }
                             .spanned(109..113)
                             .into(),
@@ -748,7 +768,7 @@ fn parse_file() {
                             cond: Expr::BinaryOp {
                                 op: Bop::Lt,
                                 lhs: Expr::Ident("bar".into()).spanned(144..147).into(),
-                                rhs: Expr::Int(3).spanned(150..151).into()
+                                rhs: Expr::Int(3, None).spanned(150..151).into()
                             }
                             .spanned(144..151)
                             .into(),
@@ -776,8 +796,8 @@ fn parse_file() {
                                             .into(),
                                             rhs: Expr::BinaryOp {
                                                 op: Bop::Mul,
-                                                lhs: Expr::Int(2).spanned(193..194).into(),
-                                                rhs: Expr::Int(4).spanned(197..198).into()
+                                                lhs: Expr::Int(2, None).spanned(193..194).into(),
+                                                rhs: Expr::Int(4, None).spanned(197..198).into()
                                             }
                                             .spanned(193..198)
                                             .into()
@@ -789,7 +809,7 @@ fn parse_file() {
                                     Expr::BinaryOp {
                                         op: Bop::Add,
                                         lhs: Expr::Ident("x".into()).spanned(216..217).into(),
-                                        rhs: Expr::Int(1).spanned(220..221).into()
+                                        rhs: Expr::Int(1, None).spanned(220..221).into()
                                     }
                                     .spanned(216..221)
                                 ],
@@ -802,15 +822,15 @@ fn parse_file() {
                                     cond: Expr::BinaryOp {
                                         op: Bop::Leq,
                                         lhs: Expr::Ident("bar".into()).spanned(246..249).into(),
-                                        rhs: Expr::Int(2).spanned(253..254).into()
+                                        rhs: Expr::Int(2, None).spanned(253..254).into()
                                     }
                                     .spanned(246..254)
                                     .into(),
                                     th: Expr::FnCall {
                                         fun: Expr::Ident("fizz".into()).spanned(272..276).into(),
                                         args: vec![
-                                            Expr::Int(3).spanned(277..278),
-                                            Expr::Float(5.1).spanned(280..283)
+                                            Expr::Int(3, None).spanned(277..278),
+                                            Expr::Float(5.1, None).spanned(280..283)
                                         ]
                                     }
                                     .spanned(272..284)
@@ -836,6 +856,7 @@ fn parse_file() {
     assert_eq!(
         items[1],
         Item::Struct {
+            docs: vec![],
             name: "Foo".into(),
             generic_params: vec!["T".into(), "U".into(),],
             fields: vec![
@@ -883,3 +904,1132 @@ fn parse_file() {
         .spanned(304..387)
     );
 }
+
+#[test]
+fn parse_errors() {
+    let err = parse_error("fn sum(a, b; c) -> a");
+    assert_eq!(
+        err,
+        ParseError {
+            kind: ParseErrorKind::Mismatched {
+                expected: vec![TokenType::RParen, TokenType::Comma],
+                found: TokenType::Semicolon
+            },
+            span: (11..12).into(),
+            context: vec!["fn `sum`".into(), "parameter list".into()],
+            missing_at: None
+        }
+    );
+    assert_eq!(
+        err.context_description().unwrap(),
+        "in parameter list of fn `sum`"
+    );
+
+    let err = parse_error("const x: Int = foo(1, )\nfn");
+    assert_eq!(
+        err,
+        ParseError {
+            kind: ParseErrorKind::Mismatched {
+                expected: vec![TokenType::Ident],
+                found: TokenType::Eof
+            },
+            span: (26..26).into(),
+            context: vec!["fn item".into()],
+            missing_at: None
+        }
+    );
+
+    let err = parse_error("fn f() -> { 1 + }");
+    assert_eq!(
+        err,
+        ParseError {
+            kind: ParseErrorKind::Unexpected {
+                found: TokenType::RBrace,
+                expected: "expression".into()
+            },
+            span: (16..17).into(),
+            context: vec!["fn `f`".into(), "block".into()],
+            missing_at: None
+        }
+    );
+
+    let err = parse_error("enum E { A, B[ }");
+    assert_eq!(
+        err.kind,
+        ParseErrorKind::Mismatched {
+            expected: vec![
+                TokenType::Comma,
+                TokenType::LParen,
+                TokenType::LBrace,
+                TokenType::RBrace
+            ],
+            found: TokenType::LBracket
+        }
+    );
+    assert_eq!(err.span, (13..14).into());
+
+    let err = parse_error("let");
+    assert_eq!(
+        err.to_string(),
+        "expected one of `const`, `fn`, `struct`, `enum`, found `let` at 0..3"
+    );
+}
+
+#[test]
+fn parse_recovery() {
+    let input = "fn f(a) -> {\n    let x = a +;\n    x *\n}\nconst c: = 4\nstruct S { a: Int }";
+    let (ast, errors) = Parser::new(input).partial_file();
+
+    let found: Vec<_> = errors
+        .iter()
+        .map(|err| (err.kind.clone(), err.span))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                ParseErrorKind::Unexpected {
+                    found: TokenType::Semicolon,
+                    expected: "expression".into()
+                },
+                (28..29).into()
+            ),
+            (
+                ParseErrorKind::Unexpected {
+                    found: TokenType::RBrace,
+                    expected: "expression".into()
+                },
+                (38..39).into()
+            ),
+            (
+                ParseErrorKind::Mismatched {
+                    expected: vec![
+                        TokenType::Ident,
+                        TokenType::LBracket,
+                        TokenType::LParen,
+                        TokenType::Fn
+                    ],
+                    found: TokenType::Eq
+                },
+                (49..50).into()
+            ),
+        ]
+    );
+
+    assert_eq!(ast.len(), 3);
+    let Item::Function { body, .. } = &ast[0].inner else {
+        panic!("expected a function, found {:?}", ast[0]);
+    };
+    assert_eq!(
+        body.inner,
+        Expr::Block {
+            exprs: vec![Expr::Error.spanned(17..28), Expr::Error.spanned(34..37)],
+            trailing: true
+        }
+    );
+    assert_eq!(ast[1], Item::Error.spanned(40..52));
+    assert_eq!(ast[2].inner.name(), "S");
+
+    let errors = Parser::new(input).file().unwrap_err();
+    assert_eq!(errors.len(), 3);
+
+    let (ast, errors) = Parser::new("fn f() -> {\n    1 +").partial_file();
+    assert_eq!(ast, vec![Item::Error.spanned(0..19)]);
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn parse_syntax_tree() {
+    let input = "// doc\nfn f(a: Int) -> { a + * / / 1 }\nconst c: Int = (1)  // end\n";
+    let (tree, errors) = Parser::new(input).syntax_tree();
+    assert_eq!(errors.len(), 1);
+
+    // every byte of the input is in the tree, even the parts that failed to parse
+    assert_eq!(tree.text(input), input);
+    assert_eq!(tree.span, (0..input.len()).into());
+
+    let items: Vec<_> = tree.nodes().map(|node| (node.kind, node.span)).collect();
+    assert_eq!(
+        items,
+        [
+            (NodeKind::FnItem, (7..38).into()),
+            (NodeKind::ConstItem, (39..57).into())
+        ]
+    );
+
+    let function = tree.nodes().next().unwrap();
+    let kinds: Vec<_> = function.nodes().map(|node| node.kind).collect();
+    assert_eq!(kinds, [NodeKind::ParamList, NodeKind::BlockExpr]);
+    // the broken statement in the block is kept as an error node
+    let block = function.node(NodeKind::BlockExpr).unwrap();
+    let statement = block.nodes().next().unwrap();
+    assert_eq!(statement.kind, NodeKind::Error);
+    assert_eq!(statement.text(input), "a + * / / 1");
+
+    // trivia stays outside of the nodes it is between
+    let constant = tree.nodes().nth(1).unwrap();
+    let value = constant.node(NodeKind::ParenExpr).unwrap();
+    assert_eq!(value.span, (54..57).into());
+    assert_eq!(
+        tree.children.last(),
+        Some(&Element::Token(TokenType::Whitespace.spanned(65..66)))
+    );
+}
+
+#[test]
+fn parse_doc_comments() {
+    let input = "\
+//! The file.
+//! More.
+
+// not documentation
+/// Adds one.
+///
+///     indented
+fn f(a) -> a + 1
+
+/// Detached, because of the comment after it.
+// plain
+//// also plain
+const c: Int = /** not a doc comment */ 1
+";
+    let (tree, errors) = Parser::new(input).syntax_tree();
+    assert!(errors.is_empty());
+    assert_eq!(tree.text(input), input);
+    assert_eq!(tree.docs(input), ["The file.", "More."]);
+
+    let ast = Parser::new(input).file().unwrap();
+    assert_eq!(ast[0].inner.docs(), ["Adds one.", "", "    indented"]);
+    // the doc comments are inside the item, but not part of its span
+    assert_eq!(ast[0].span.start, input.find("fn").unwrap());
+    assert!(ast[1].inner.docs().is_empty());
+}
+
+#[test]
+fn parse_escapes() {
+    let expr = parse_expr(r#""tab\t cr\r nul\0 \\ \' \" \x7F \u{1F600}""#);
+    assert_eq!(
+        expr.inner,
+        Expr::Str("tab\t cr\r nul\0 \\ ' \" \x7F \u{1F600}".into())
+    );
+    assert_eq!(parse_expr(r"'\u{e9}'").inner, Expr::Char('é'));
+    assert_eq!(parse_expr(r"'\''").inner, Expr::Char('\''));
+
+    // every invalid escape is reported, and the rest of the file still parses
+    let input = r#"const s: Str = "\q \x80 \x7 \u{110000} \u{}" const c: Char = '\u41'"#;
+    let errors = Parser::new(input).file().unwrap_err();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|err| (err.kind.clone(), &input[err.span.start..err.span.end]))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::Unknown('q')),
+                r"\q"
+            ),
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::HexOutOfRange),
+                r"\x80"
+            ),
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::MalformedHex),
+                r"\x7"
+            ),
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::InvalidUnicode(0x11_0000)),
+                r"\u{110000}"
+            ),
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::MalformedUnicode),
+                r"\u{}"
+            ),
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::MalformedUnicode),
+                r"\u"
+            ),
+        ]
+    );
+    assert_eq!(errors[0].context, ["const `s`"]);
+    assert_eq!(
+        errors[0].to_string(),
+        r"unknown character escape `\q` in const `s` at 16..18"
+    );
+}
+
+#[test]
+fn parse_unicode_identifiers() {
+    // `é` as one character and as `e` followed by a combining accent are the same name
+    let item = parse_item("fn f(e\u{301}t: Int) -> \u{e9}t + 1");
+    let Item::Function { params, body, .. } = item.inner else {
+        panic!("expected a function");
+    };
+    let Binding::Var { ident, .. } = &params[0].inner else {
+        panic!("expected a variable");
+    };
+    assert_eq!(ident, "\u{e9}t");
+    let Expr::BinaryOp { lhs, .. } = body.inner else {
+        panic!("expected a binary operation");
+    };
+    assert_eq!(lhs.inner, Expr::Ident("\u{e9}t".into()));
+    // the spans are still those of the source text
+    assert_eq!(lhs.span, Span::from(19..22));
+}
+
+#[test]
+fn parse_number_literals() {
+    let value = |input| parse_expr(input).inner;
+    assert_eq!(value("0xFF"), Expr::Int(255, None));
+    assert_eq!(value("0o17"), Expr::Int(15, None));
+    assert_eq!(value("0b1010_0101"), Expr::Int(165, None));
+    assert_eq!(value("1_000_000"), Expr::Int(1_000_000, None));
+    assert_eq!(value("0xFF_u8"), Expr::Int(255, Some(NumericSuffix::U8)));
+    assert_eq!(value("1.5f32"), Expr::Float(1.5, Some(NumericSuffix::F32)));
+    assert_eq!(value("1f32"), Expr::Float(1.0, Some(NumericSuffix::F32)));
+    assert_eq!(value("1e3"), Expr::Float(1000.0, None));
+    assert_eq!(
+        value("-128i8"),
+        Expr::UnaryOp {
+            op: Unop::Neg,
+            expr: Box::new(Expr::Int(128, Some(NumericSuffix::I8)).spanned(1..6)),
+        }
+    );
+
+    let input = "const a: U8 = 256u8 const b: I8 = -128i8 - 128i8 \
+        const c: Int = 0b102 + 0x + 3abc + 99999999999999999999999 + 1e999 + 0o7f32";
+    let errors = Parser::new(input).file().unwrap_err();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|err| (err.kind.clone(), &input[err.span.start..err.span.end]))
+        .collect();
+    let invalid = ParseErrorKind::InvalidLiteral;
+    assert_eq!(
+        found,
+        [
+            (
+                invalid(LiteralError::OutOfRange(NumericSuffix::U8)),
+                "256u8"
+            ),
+            // only a literal right after a prefix `-` can reach the minimum
+            (
+                invalid(LiteralError::OutOfRange(NumericSuffix::I8)),
+                "128i8"
+            ),
+            (
+                invalid(LiteralError::InvalidDigit {
+                    digit: '2',
+                    radix: 2
+                }),
+                "2"
+            ),
+            (invalid(LiteralError::NoDigits), "0x"),
+            (invalid(LiteralError::InvalidSuffix), "abc"),
+            (
+                invalid(LiteralError::OutOfRange(NumericSuffix::U64)),
+                "99999999999999999999999"
+            ),
+            (
+                invalid(LiteralError::OutOfRange(NumericSuffix::F64)),
+                "1e999"
+            ),
+            (invalid(LiteralError::InvalidSuffix), "f32"),
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "literal out of range for `U8` in const `a` at 14..19"
+    );
+}
+
+#[test]
+fn parse_malformed_tokens() {
+    // malformed tokens are reported, and parsing carries on as if they were well-formed
+    let input = "const a: Char = '' const b: Int = 1 $ + 2 const c: Str = \"open \\q\n\
+        const d: Char = 'xy' /* open";
+    let (ast, errors) = Parser::new(input).partial_file();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|err| (err.kind.clone(), &input[err.span.start..err.span.end]))
+        .collect();
+    let invalid = ParseErrorKind::InvalidToken;
+    assert_eq!(
+        found,
+        [
+            (invalid(LexError::EmptyChar), "''"),
+            (invalid(LexError::StrayCharacter('$')), "$"),
+            (invalid(LexError::UnterminatedString), "\"open \\q"),
+            (invalid(LexError::MultipleChars), "'xy'"),
+            (invalid(LexError::UnterminatedComment), "/* open"),
+        ]
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "stray character `$` in const `b` at 36..37"
+    );
+
+    let values: Vec<_> = ast
+        .into_iter()
+        .map(|item| match item.inner {
+            Item::Const { value, .. } => value.inner,
+            item => panic!("{item:?} is not a const"),
+        })
+        .collect();
+    assert_eq!(
+        values,
+        [
+            Expr::Char('\0'),
+            Expr::BinaryOp {
+                op: Bop::Add,
+                lhs: Box::new(Expr::Int(1, None).spanned(34..35)),
+                rhs: Box::new(Expr::Int(2, None).spanned(40..41)),
+            },
+            // the bad escape is only reported once the string is closed
+            Expr::Str("open \u{FFFD}".into()),
+            Expr::Char('x'),
+        ]
+    );
+}
+
+#[test]
+fn parse_raw_and_multi_line_strings() {
+    assert_eq!(
+        parse_expr("r#\"C:\\files\\\"quoted\"\"#").inner,
+        Expr::Str(r#"C:\files\"quoted""#.into())
+    );
+
+    let input = "\
+fn f() -> {
+    \"\"\"
+        SELECT *
+
+          FROM \\\"\"\"users\\\"\"\"\r
+        \t
+        WHERE id = 1\\t
+        \"\"\"
+}";
+    let ast = parse_ast(input);
+    let Item::Function { body, .. } = &ast[0].inner else {
+        panic!("not a function");
+    };
+    let Expr::Block { exprs, .. } = &body.inner else {
+        panic!("not a block");
+    };
+    assert_eq!(
+        exprs[0].inner,
+        Expr::Str("SELECT *\n\n  FROM \"\"\"users\"\"\"\n\t\nWHERE id = 1\t".into())
+    );
+    assert_eq!(
+        parse_expr("\"\"\"\n  \"\"\"").inner,
+        Expr::Str(String::new())
+    );
+
+    let input = "const s: Str = \"\"\"text\n      indented\n    under\n    \\q \"\"\"";
+    let errors = Parser::new(input).file().unwrap_err();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|err| (err.kind.clone(), &input[err.span.start..err.span.end]))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::TextAfterOpening),
+                "text"
+            ),
+            (
+                ParseErrorKind::InvalidLiteral(LiteralError::TextBeforeClosing),
+                "    \\q "
+            ),
+        ]
+    );
+    let found = parse_error("const s: Str = \"\"\"\n    a\n  b\n    \"\"\"");
+    assert_eq!(
+        found.kind,
+        ParseErrorKind::InvalidLiteral(LiteralError::Underindented)
+    );
+    assert_eq!(found.span, (25..28).into());
+}
+
+#[test]
+fn parse_interpolation() {
+    let expr = parse_expr(r#""sum is {a + b}, \{not} {"{c}"}""#);
+    assert_eq!(
+        expr,
+        Expr::Interpolation(vec![
+            StringPart::Text("sum is ".into()),
+            StringPart::Expr(
+                Expr::BinaryOp {
+                    op: Bop::Add,
+                    lhs: Box::new(Expr::Ident("a".into()).spanned(9..10)),
+                    rhs: Box::new(Expr::Ident("b".into()).spanned(13..14)),
+                }
+                .spanned(9..14)
+            ),
+            StringPart::Text(", {not} ".into()),
+            StringPart::Expr(
+                Expr::Interpolation(vec![StringPart::Expr(
+                    Expr::Ident("c".into()).spanned(27..28)
+                )])
+                .spanned(25..30)
+            ),
+        ])
+        .spanned(0..32)
+    );
+
+    let err = parse_error(r#"const s: Str = "{a b}""#);
+    assert_eq!(
+        err.to_string(),
+        "expected an operator or the end of the expression, found identifier in string \
+         interpolation of const `s` at 19..20"
+    );
+    let err = parse_error(r#"const s: Str = "{}""#);
+    assert_eq!(
+        err.to_string(),
+        "expected expression, found `}` in string interpolation of const `s` at 17..19"
+    );
+}
+
+#[test]
+fn parse_closures() {
+    let expr = parse_expr("|| -> 1");
+    assert_eq!(
+        expr,
+        Expr::Lambda {
+            params: vec![],
+            return_type: None,
+            body: Expr::Int(1, None).spanned(6..7).into(),
+        }
+        .spanned(0..7)
+    );
+
+    let expr = parse_expr("|mut a, b: Int|: Int -> a");
+    assert_eq!(
+        expr,
+        Expr::Lambda {
+            params: vec![
+                Binding::Var {
+                    mutable: true,
+                    ident: "a".into(),
+                    type_annotation: None,
+                }
+                .spanned(1..6),
+                Binding::Var {
+                    mutable: false,
+                    ident: "b".into(),
+                    type_annotation: Some(
+                        Type::Named {
+                            name: "Int".into(),
+                            generics: vec![],
+                        }
+                        .spanned(11..14)
+                    ),
+                }
+                .spanned(8..14),
+            ],
+            return_type: Some(
+                Type::Named {
+                    name: "Int".into(),
+                    generics: vec![],
+                }
+                .spanned(17..20)
+            ),
+            body: Expr::Ident("a".into()).spanned(24..25).into(),
+        }
+        .spanned(0..25)
+    );
+
+    // after an operand `|` and `||` are operators, and in place of one they start closures
+    let op = |expr: &ExprS| match &expr.inner {
+        Expr::BinaryOp { op, .. } => Some(*op),
+        _ => None,
+    };
+    let operands = |expr: ExprS| match expr.inner {
+        Expr::BinaryOp { lhs, rhs, .. } => (*lhs, *rhs),
+        _ => panic!("expected a binary operator, found {expr:?}"),
+    };
+    assert_eq!(op(&parse_expr("a | b")), Some(Bop::BOr));
+    assert_eq!(op(&parse_expr("a || b")), Some(Bop::Or));
+
+    let (_, rhs) = operands(parse_expr("a | |b| -> b"));
+    assert!(matches!(rhs.inner, Expr::Lambda { .. }));
+    let (_, rhs) = operands(parse_expr("a || || -> b"));
+    assert!(matches!(rhs.inner, Expr::Lambda { .. }));
+
+    // the body of a closure goes on as far as it can
+    let Expr::Lambda { body, .. } = parse_expr("|a| -> a | b || c").inner else {
+        panic!("expected a closure");
+    };
+    assert_eq!(op(&body), Some(Bop::Or));
+    let (lhs, _) = operands(*body);
+    assert_eq!(op(&lhs), Some(Bop::BOr));
+
+    let expr = parse_expr("f(|x| -> x, 1)");
+    let Expr::FnCall { args, .. } = expr.inner else {
+        panic!("expected a call");
+    };
+    assert!(matches!(args[0].inner, Expr::Lambda { .. }));
+    assert_eq!(args[1], Expr::Int(1, None).spanned(12..13));
+
+    // the closing pipe is needed
+    let err = parse_error("const f: fn(Int): Int = |a -> a");
+    assert_eq!(
+        err,
+        ParseError {
+            kind: ParseErrorKind::Mismatched {
+                expected: vec![TokenType::Pipe, TokenType::Comma],
+                found: TokenType::Arrow
+            },
+            span: (27..29).into(),
+            context: vec!["const `f`".into(), "closure parameter list".into()],
+            missing_at: None
+        }
+    );
+}
+
+#[test]
+fn parse_placeholders() {
+    let expr = parse_expr("sum(1, _)");
+    let Expr::FnCall { fun, args } = &expr.inner else {
+        panic!("expected a call, found {expr:?}");
+    };
+    assert_eq!(
+        *args,
+        [
+            Expr::Int(1, None).spanned(4..5),
+            Expr::Placeholder.spanned(7..8)
+        ]
+    );
+
+    // each `_` becomes a parameter of a lambda around the call
+    let param = |ident: &str, span: Span| {
+        Binding::Var {
+            mutable: false,
+            ident: ident.into(),
+            type_annotation: None,
+        }
+        .spanned(span)
+    };
+    assert_eq!(
+        partial_application(fun, args, expr.span),
+        Some(Expr::Lambda {
+            params: vec![param("<_0>", (7..8).into())],
+            return_type: None,
+            body: Expr::FnCall {
+                fun: fun.clone(),
+                args: vec![
+                    Expr::Int(1, None).spanned(4..5),
+                    Expr::Ident("<_0>".into()).spanned(7..8)
+                ],
+            }
+            .spanned(0..9)
+            .into(),
+        })
+    );
+    let expr = parse_expr("f(_, x, _)");
+    let Expr::FnCall { fun, args } = &expr.inner else {
+        panic!("expected a call, found {expr:?}");
+    };
+    let Some(Expr::Lambda { params, .. }) = partial_application(fun, args, expr.span) else {
+        panic!("expected a lambda");
+    };
+    assert_eq!(
+        params,
+        [param("<_0>", (2..3).into()), param("<_1>", (8..9).into())]
+    );
+    assert_eq!(partial_application(fun, &args[1..2], expr.span), None);
+
+    // `_` parses anywhere an expression can go, and is rejected by the type checker
+    let expr = parse_expr("_ + 1");
+    assert_eq!(
+        expr,
+        Expr::BinaryOp {
+            op: Bop::Add,
+            lhs: Expr::Placeholder.spanned(0..1).into(),
+            rhs: Expr::Int(1, None).spanned(4..5).into(),
+        }
+        .spanned(0..5)
+    );
+}
+
+#[test]
+fn parse_match() {
+    let expr = parse_expr("match x { (0, -1) => a, n @ 1 | n if n > 2 => { b } _ => c }");
+    let binding = |ident: &str, subpattern: Option<PatternS>, span: (usize, usize)| {
+        Pattern::Binding {
+            mutable: false,
+            ident: ident.into(),
+            subpattern: subpattern.map(Box::new),
+        }
+        .spanned(span.0..span.1)
+    };
+    assert_eq!(
+        expr,
+        Expr::Match {
+            scrutinee: Expr::Ident("x".into()).spanned(6..7).into(),
+            arms: vec![
+                MatchArm {
+                    pattern: Pattern::Tuple(vec![
+                        Pattern::Literal(Expr::Int(0, None).spanned(11..12)).spanned(11..12),
+                        Pattern::Literal(
+                            Expr::UnaryOp {
+                                op: Unop::Neg,
+                                expr: Expr::Int(1, None).spanned(15..16).into(),
+                            }
+                            .spanned(14..16)
+                        )
+                        .spanned(14..16),
+                    ])
+                    .spanned(10..17),
+                    guard: None,
+                    body: Expr::Ident("a".into()).spanned(21..22),
+                }
+                .spanned(10..22),
+                MatchArm {
+                    pattern: Pattern::Or(vec![
+                        binding(
+                            "n",
+                            Some(
+                                Pattern::Literal(Expr::Int(1, None).spanned(28..29))
+                                    .spanned(28..29)
+                            ),
+                            (24, 29)
+                        ),
+                        binding("n", None, (32, 33)),
+                    ])
+                    .spanned(24..33),
+                    guard: Some(
+                        Expr::BinaryOp {
+                            op: Bop::Gt,
+                            lhs: Expr::Ident("n".into()).spanned(37..38).into(),
+                            rhs: Expr::Int(2, None).spanned(41..42).into(),
+                        }
+                        .spanned(37..42)
+                    ),
+                    body: Expr::Block {
+                        exprs: vec![Expr::Ident("b".into()).spanned(48..49)],
+                        trailing: true,
+                    }
+                    .spanned(46..51),
+                }
+                .spanned(24..51),
+                MatchArm {
+                    pattern: Pattern::Wildcard.spanned(52..53),
+                    guard: None,
+                    body: Expr::Ident("c".into()).spanned(57..58),
+                }
+                .spanned(52..58),
+            ],
+        }
+        .spanned(0..60)
+    );
+
+    // arms are separated by commas, which can be left out after a block
+    let err = parse_error("const a: Int = match x { 1 => a; 2 => b }");
+    assert_eq!(
+        err,
+        ParseError {
+            kind: ParseErrorKind::Mismatched {
+                expected: vec![TokenType::Comma, TokenType::RBrace],
+                found: TokenType::Semicolon
+            },
+            span: (31..32).into(),
+            context: vec!["const `a`".into(), "match".into()],
+            missing_at: None
+        }
+    );
+
+    // `..` can only end a struct pattern
+    let err = parse_error("const a: Int = match x { P { .., y } => 1 }");
+    assert_eq!(
+        err,
+        ParseError {
+            kind: ParseErrorKind::Mismatched {
+                expected: vec![TokenType::RBrace],
+                found: TokenType::Ident
+            },
+            span: (33..34).into(),
+            context: vec!["const `a`".into(), "match".into(), "struct pattern".into()],
+            missing_at: None
+        }
+    );
+}
+
+#[test]
+fn parse_patterns() {
+    let binding = |ident: &str, subpattern: Option<PatternS>, span: (usize, usize)| {
+        Pattern::Binding {
+            mutable: false,
+            ident: ident.into(),
+            subpattern: subpattern.map(Box::new),
+        }
+        .spanned(span.0..span.1)
+    };
+
+    // the pattern of `match x { <pattern> => 0 }`, which starts at offset 10
+    let pattern = |pattern: &str| {
+        let Expr::Match { mut arms, .. } =
+            parse_expr(&format!("match x {{ {pattern} => 0 }}")).inner
+        else {
+            panic!("expected a match");
+        };
+        arms.remove(0).inner.pattern
+    };
+    let path = |name: &str, variant: Option<&str>| Path {
+        name: name.into(),
+        variant: variant.map(Into::into),
+    };
+
+    assert_eq!(
+        pattern("Rect { w, mut h, d: [first, rest @ ..], .. }"),
+        Pattern::Struct {
+            path: path("Rect", None),
+            fields: vec![
+                FieldPattern {
+                    name: "w".into(),
+                    pattern: binding("w", None, (17, 18)),
+                }
+                .spanned(17..18),
+                FieldPattern {
+                    name: "h".into(),
+                    pattern: Pattern::Binding {
+                        mutable: true,
+                        ident: "h".into(),
+                        subpattern: None,
+                    }
+                    .spanned(20..25),
+                }
+                .spanned(20..25),
+                FieldPattern {
+                    name: "d".into(),
+                    pattern: Pattern::Array(vec![
+                        binding("first", None, (31, 36)),
+                        binding("rest", Some(Pattern::Rest.spanned(45..47)), (38, 47)),
+                    ])
+                    .spanned(30..48),
+                }
+                .spanned(27..48),
+            ],
+            rest: true,
+        }
+        .spanned(10..54)
+    );
+    assert_eq!(
+        pattern("Shape::Circle(r) | Shape::Empty"),
+        Pattern::Or(vec![
+            Pattern::TupleVariant {
+                path: path("Shape", Some("Circle")),
+                elements: vec![binding("r", None, (24, 25))],
+            }
+            .spanned(10..26),
+            Pattern::Path(path("Shape", Some("Empty"))).spanned(29..41),
+        ])
+        .spanned(10..41)
+    );
+    // parentheses group alternatives, and are only kept in the span
+    assert_eq!(pattern("(_)"), Pattern::Wildcard.spanned(10..13));
+    assert!(matches!(
+        pattern("x @ (1 | 2)").inner,
+        Pattern::Binding { subpattern: Some(subpattern), .. }
+            if matches!(subpattern.inner, Pattern::Or(_))
+    ));
+    assert_eq!(
+        pattern("-5..=10"),
+        Pattern::Range {
+            start: Expr::UnaryOp {
+                op: Unop::Neg,
+                expr: Box::new(Expr::Int(5, None).spanned(11..12)),
+            }
+            .spanned(10..12),
+            end: Expr::Int(10, None).spanned(15..17),
+            inclusive: true,
+        }
+        .spanned(10..17)
+    );
+    assert!(matches!(
+        pattern("'a'..'z'").inner,
+        Pattern::Range {
+            inclusive: false,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn parse_destructuring() {
+    let binding = |ident: &str, span: (usize, usize)| {
+        Pattern::Binding {
+            mutable: false,
+            ident: ident.into(),
+            subpattern: None,
+        }
+        .spanned(span.0..span.1)
+    };
+
+    assert_eq!(
+        parse_expr("let (a, _) = pair"),
+        Expr::Let {
+            binding: Binding::Pattern {
+                pattern: Box::new(
+                    Pattern::Tuple(vec![binding("a", (5, 6)), Pattern::Wildcard.spanned(8..9)])
+                        .spanned(4..10)
+                ),
+                type_annotation: None,
+            }
+            .spanned(4..10),
+            value: Expr::Ident("pair".into()).spanned(13..17).into(),
+        }
+        .spanned(0..17)
+    );
+
+    let Item::Function { params, .. } =
+        parse_item("fn f(Point { x, .. }: Point, mut n) -> x").inner
+    else {
+        panic!("expected a function");
+    };
+    assert_eq!(
+        params[0],
+        Binding::Pattern {
+            pattern: Box::new(
+                Pattern::Struct {
+                    path: Path {
+                        name: "Point".into(),
+                        variant: None,
+                    },
+                    fields: vec![
+                        FieldPattern {
+                            name: "x".into(),
+                            pattern: binding("x", (13, 14)),
+                        }
+                        .spanned(13..14)
+                    ],
+                    rest: true,
+                }
+                .spanned(5..20)
+            ),
+            type_annotation: Some(
+                Type::Named {
+                    name: "Point".into(),
+                    generics: vec![],
+                }
+                .spanned(22..27)
+            ),
+        }
+        .spanned(5..27)
+    );
+    // a plain name is still a variable
+    assert_eq!(
+        params[1],
+        Binding::Var {
+            mutable: true,
+            ident: "n".into(),
+            type_annotation: None,
+        }
+        .spanned(29..34)
+    );
+
+    // the pipes around a closure's parameters aren't taken as alternatives
+    let Expr::Lambda { params, .. } = parse_expr("|(a, b), [c, ..]| -> a").inner else {
+        panic!("expected a lambda");
+    };
+    assert!(matches!(
+        &params[..],
+        [
+            BindingS {
+                inner: Binding::Pattern { .. },
+                ..
+            },
+            BindingS {
+                inner: Binding::Pattern { .. },
+                ..
+            },
+        ]
+    ));
+}
+
+/// Makes an edit to `file`, checking that the result is the same as parsing the edited
+/// source from scratch.
+fn edit_checked(file: &mut ParsedFile, edit: Span, replacement: &str) {
+    let old = file.source().to_owned();
+    file.edit(edit, replacement);
+
+    let new = file.source();
+    assert_eq!(
+        new,
+        [&old[..edit.start], replacement, &old[edit.end..]].concat()
+    );
+    let from_scratch = ParsedFile::new(new.into());
+    assert_eq!(*file, from_scratch, "editing {old:?} into {new:?}");
+
+    let (tree, errors) = Parser::new(new).syntax_tree();
+    assert_eq!(*file.tree(), tree);
+    assert_eq!(file.errors(), errors);
+    assert_eq!(file.ast(), Parser::new(new).partial_file().0);
+}
+
+#[test]
+fn parse_incrementally() {
+    let mut file = ParsedFile::new(
+        "const a: Int = 1\nfn f(x: Int) -> Int = x + a\nconst b: Str = \"b\"\n".into(),
+    );
+    assert!(file.errors().is_empty());
+
+    // within an item
+    edit_checked(&mut file, (15..16).into(), "42");
+    edit_checked(&mut file, (41..42).into(), "a * 2");
+    // breaking an item, and fixing it again
+    edit_checked(&mut file, (18..20).into(), "");
+    assert_eq!(file.errors().len(), 1);
+    edit_checked(&mut file, (18..18).into(), "fn");
+    assert!(file.errors().is_empty());
+    // adding and removing items
+    edit_checked(&mut file, (0..0).into(), "struct S { s: Str }\n");
+    edit_checked(&mut file, (0..20).into(), "");
+    edit_checked(&mut file, (18..18).into(), "} $ const");
+    // an edit running on into the rest of the file
+    edit_checked(&mut file, (30..30).into(), "\"");
+    edit_checked(&mut file, (30..31).into(), "");
+    edit_checked(&mut file, (0..0).into(), "/*");
+    edit_checked(&mut file, (0..2).into(), "");
+}
+
+#[test]
+fn parse_incrementally_many_edits() {
+    const SNIPPETS: &[&str] = &[
+        "",
+        " ",
+        "\n",
+        "x",
+        "1",
+        "\"",
+        "'",
+        "(",
+        ")",
+        "{",
+        "}",
+        "/*",
+        "*/",
+        "$",
+        ",",
+        ":",
+        "=",
+        "->",
+        "fn ",
+        "const ",
+        "struct ",
+        "enum ",
+        "let y = 2\n",
+        "if a then b else c",
+    ];
+    let mut file = ParsedFile::new(
+        "const a: Int = 1\n\
+        fn f(x: Int, y: [Int]) -> Int = {\n    let z = x + y[0]\n    z * 2\n}\n\
+        struct P { x: Float, y: Float }\n\
+        enum E { A, B(Int) }\n\
+        fn g() -> Str = \"g {f(1, [2])}\" // done\n"
+            .into(),
+    );
+
+    // a fixed pseudo-random sequence of edits, each made to the result of the last
+    let mut state = 0x9e37_79b9_u32;
+    let mut next = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as usize % bound
+    };
+    for _ in 0..300 {
+        let source = file.source();
+        let start = next(source.len() + 1);
+        let end = (start + next(5)).min(source.len());
+        let replacement = SNIPPETS[next(SNIPPETS.len())];
+        edit_checked(&mut file, (start..end).into(), replacement);
+    }
+}
+
+#[test]
+fn parse_struct_literals() {
+    let point = Path {
+        name: "Point".into(),
+        variant: None,
+    };
+    assert_eq!(
+        parse_expr("Point { x: 1, y, ..p }"),
+        Expr::Struct {
+            path: point.clone(),
+            fields: vec![
+                FieldInit {
+                    name: "x".into(),
+                    value: Expr::Int(1, None).spanned(11..12),
+                }
+                .spanned(8..12),
+                // `y` is short for `y: y`
+                FieldInit {
+                    name: "y".into(),
+                    value: Expr::Ident("y".into()).spanned(14..15),
+                }
+                .spanned(14..15),
+            ],
+            base: Some(Expr::Ident("p".into()).spanned(19..20).into()),
+        }
+        .spanned(0..22)
+    );
+    assert_eq!(
+        parse_expr("Point {}"),
+        Expr::Struct {
+            path: point,
+            fields: vec![],
+            base: None,
+        }
+        .spanned(0..8)
+    );
+
+    // a variant with fields is called like a function
+    let circle = Path {
+        name: "Shape".into(),
+        variant: Some("Circle".into()),
+    };
+    assert_eq!(
+        parse_expr("Shape::Circle(3.0)"),
+        Expr::FnCall {
+            fun: Expr::Path(circle.clone()).spanned(0..13).into(),
+            args: vec![Expr::Float(3.0, None).spanned(14..17)],
+        }
+        .spanned(0..18)
+    );
+    assert_eq!(
+        parse_expr("Shape::Circle"),
+        Expr::Path(circle).spanned(0..13)
+    );
+
+    // the braces after the scrutinee of a `match` are its arms, unless they are
+    // between delimiters
+    let Expr::Match { scrutinee, arms } = parse_expr("match x { _ => 0 }").inner else {
+        panic!("expected a match");
+    };
+    assert_eq!(scrutinee.inner, Expr::Ident("x".into()));
+    assert_eq!(arms.len(), 1);
+    let Expr::Match { scrutinee, .. } = parse_expr("match f(Point { x }) { _ => 0 }").inner else {
+        panic!("expected a match");
+    };
+    assert!(matches!(scrutinee.inner, Expr::FnCall { .. }));
+    let Expr::Match { scrutinee, .. } = parse_expr("match (Point { x }) { _ => 0 }").inner else {
+        panic!("expected a match");
+    };
+    assert!(matches!(scrutinee.inner, Expr::Struct { .. }));
+    // blocks in the arms can have struct literals in them
+    assert!(matches!(
+        parse_expr("match x { _ => { Point { x } } }").inner,
+        Expr::Match { .. }
+    ));
+
+    // `..base` comes last
+    let err = parse_error("fn f(p) -> Point { ..p, x }");
+    assert_eq!(
+        err.kind,
+        ParseErrorKind::Mismatched {
+            expected: vec![TokenType::RBrace],
+            found: TokenType::Ident
+        }
+    );
+}
@@ -1,4 +1,4 @@
-use crate::span;
+use crate::{helpers::Span, span};
 
 use super::Type;
 use std::{error::Error, fmt::Display};
@@ -6,41 +6,264 @@ use std::{error::Error, fmt::Display};
 pub type TypeResult<T = Type> = Result<T, TypeErrorS>;
 
 span! { TypeError as TypeErrorS }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeError {
     UnboundIdent(String),
-    MismatchedTypes(String, String),
-    WrongArgCount { needed: usize, provided: usize },
+    MismatchedTypes {
+        expected: Type,
+        found: Type,
+        /// Where `expected` was written out, if it came from a type annotation.
+        annotation: Option<Span>,
+    },
+    WrongArgCount {
+        needed: usize,
+        provided: usize,
+    },
+    NotNumeric(Type),
+    NotInteger(Type),
+    NotSigned(Type),
+    NotCallable(Type),
+    /// Embedded in an interpolated string, which needs it to have a textual form.
+    NotDisplayable(Type),
+    NoField {
+        ty: Type,
+        field: String,
+    },
     CantInfer,
     Mutation(String),
     Infinite,
+    /// `_` anywhere but as an argument of a call.
+    MisplacedPlaceholder,
+    /// A pattern naming a struct or enum that isn't defined.
+    UnknownType(String),
+    NoVariant {
+        ty: String,
+        variant: String,
+    },
+    /// A tuple variant pattern with a different number of elements than the variant.
+    PatternArity {
+        path: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A struct or struct variant matched by a pattern, or used as a value, without
+    /// braces.
+    NeedsBraces(String),
+    /// A struct pattern without `..` that leaves out some of the fields.
+    MissingFields {
+        path: String,
+        fields: Vec<String>,
+    },
+    /// A name bound by some alternatives of an or-pattern but not by the others.
+    OrPatternBindings(String),
+    DuplicateBinding(String),
+    /// `..` outside of an array pattern, or more than once in one.
+    MisplacedRest,
+    /// A `match` that some values of its scrutinee get through, such as the ones matched
+    /// by each of `missing`.
+    NonExhaustive {
+        missing: Vec<String>,
+    },
+    /// A range pattern whose start is past its end.
+    EmptyRange,
+    /// A pattern in a `let` or a parameter list that doesn't match some values of its
+    /// type, such as the ones matched by each of `missing`.
+    Refutable {
+        missing: Vec<String>,
+    },
+    /// A struct literal without `..base` that leaves out some of the fields.
+    MissingFieldValues {
+        path: String,
+        fields: Vec<String>,
+    },
+    /// A field given a value more than once in a struct literal.
+    DuplicateField(String),
+    /// A literal of an enum variant taking the rest of its fields from `..base`.
+    VariantUpdate(String),
+    /// An integer literal without a suffix that doesn't fit in the type it is given.
+    LiteralOutOfRange(Type),
 }
 
-impl Display for TypeErrorS {
+impl TypeError {
+    /// The stable code identifying this kind of error, see [`crate::diagnostics::explain`].
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UnboundIdent(_) => "E0201",
+            Self::MismatchedTypes { .. } => "E0202",
+            Self::WrongArgCount { .. } => "E0203",
+            Self::NotNumeric(_) => "E0204",
+            Self::NotInteger(_) => "E0205",
+            Self::NotSigned(_) => "E0206",
+            Self::NotCallable(_) => "E0207",
+            Self::NoField { .. } => "E0208",
+            Self::CantInfer => "E0209",
+            Self::Mutation(_) => "E0210",
+            Self::Infinite => "E0211",
+            Self::NotDisplayable(_) => "E0212",
+            Self::MisplacedPlaceholder => "E0213",
+            Self::UnknownType(_) => "E0214",
+            Self::NoVariant { .. } => "E0215",
+            Self::PatternArity { .. } => "E0216",
+            Self::NeedsBraces(_) => "E0217",
+            Self::MissingFields { .. } => "E0218",
+            Self::OrPatternBindings(_) => "E0219",
+            Self::DuplicateBinding(_) => "E0220",
+            Self::MisplacedRest => "E0221",
+            Self::NonExhaustive { .. } => "E0222",
+            Self::EmptyRange => "E0223",
+            Self::Refutable { .. } => "E0224",
+            Self::MissingFieldValues { .. } => "E0225",
+            Self::DuplicateField(_) => "E0226",
+            Self::VariantUpdate(_) => "E0227",
+            // the same problem as a literal too large for its suffix
+            Self::LiteralOutOfRange(_) => "E0005",
+        }
+    }
+}
+
+impl Display for TypeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.inner {
-            TypeError::UnboundIdent(ident) => {
-                write!(f, "identifider `{ident}` at {} is unbound", self.span)
+        match self {
+            Self::UnboundIdent(ident) => write!(f, "identifier `{ident}` is unbound"),
+            Self::MismatchedTypes {
+                expected, found, ..
+            } => write!(
+                f,
+                "mismatched types, expected `{expected}`, found `{found}`"
+            ),
+            Self::WrongArgCount { needed, provided } => write!(
+                f,
+                "function call has the wrong number of arguments, needs {needed}, provides {provided}"
+            ),
+            Self::NotNumeric(ty) => write!(f, "expected a numeric type, found `{ty}`"),
+            Self::NotInteger(ty) => write!(f, "expected an integer type, found `{ty}`"),
+            Self::NotSigned(ty) => write!(f, "cannot negate a value of type `{ty}`"),
+            Self::NotCallable(ty) => write!(f, "value of type `{ty}` is not a function"),
+            Self::NotDisplayable(ty) => {
+                write!(f, "value of type `{ty}` can't be embedded in a string")
+            }
+            Self::NoField { ty, field } => write!(f, "type `{ty}` has no field `{field}`"),
+            Self::CantInfer => "can't infer the type of this expression".fmt(f),
+            Self::Mutation(name) => write!(f, "attempted mutation of immutable variable `{name}`"),
+            Self::Infinite => "expression would have an infinitely sized type".fmt(f),
+            Self::MisplacedPlaceholder => "`_` can only stand in for an argument".fmt(f),
+            Self::UnknownType(name) => write!(f, "cannot find struct or enum `{name}`"),
+            Self::NoVariant { ty, variant } => write!(f, "`{ty}` has no variant `{variant}`"),
+            Self::PatternArity {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{path}` has {expected} field{}, but the pattern has {found}",
+                if *expected == 1 { "" } else { "s" }
+            ),
+            Self::NeedsBraces(path) => {
+                write!(f, "`{path}` has named fields, which go between braces")
+            }
+            Self::MissingFields { path, fields } => write!(
+                f,
+                "pattern does not mention {} of `{path}`",
+                field_list(fields)
+            ),
+            Self::OrPatternBindings(name) => {
+                write!(
+                    f,
+                    "`{name}` is not bound in every alternative of the pattern"
+                )
             }
-            TypeError::MismatchedTypes(type_a, type_b) => write!(
+            Self::DuplicateBinding(name) => {
+                write!(f, "`{name}` is bound more than once in the same pattern")
+            }
+            Self::MisplacedRest => "`..` can only be used once, in an array pattern".fmt(f),
+            Self::NonExhaustive { missing } => {
+                write!(
+                    f,
+                    "non-exhaustive patterns: {} not covered",
+                    patterns(missing)
+                )
+            }
+            Self::EmptyRange => "range pattern matches no values".fmt(f),
+            Self::Refutable { missing } => write!(
                 f,
-                "mismatched types `{type_a}` and `{type_b}` at {}",
-                self.span
+                "refutable pattern in binding: {} not covered",
+                patterns(missing)
             ),
-            TypeError::WrongArgCount { needed, provided } => write!(
+            Self::MissingFieldValues { path, fields } => write!(
                 f,
-                "function call at {} has the wrong number of arguments, needs {needed}, provides {provided}",
-                self.span
+                "struct literal gives no value for {} of `{path}`",
+                field_list(fields)
             ),
-            TypeError::CantInfer => write!(f, "can't infer type of expression at {}", self.span),
-            TypeError::Mutation(name) => write!(
+            Self::DuplicateField(name) => write!(f, "field `{name}` is given more than once"),
+            Self::LiteralOutOfRange(ty) => write!(f, "literal out of range for `{ty}`"),
+            Self::VariantUpdate(path) => write!(
                 f,
-                "attempted mutation of immutable variable {name} at {}",
-                self.span
+                "`{path}` is an enum variant, which can't take its fields from another value"
             ),
-            TypeError::Infinite => todo!(),
         }
     }
 }
 
+/// Lists every one of `fields`, as in "fields `x`, `y`".
+fn field_list(fields: &[String]) -> String {
+    format!(
+        "field{} {}",
+        if fields.len() == 1 { "" } else { "s" },
+        fields
+            .iter()
+            .map(|field| format!("`{field}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Lists the first few of `patterns`, as in "`A`, `B` and `C`".
+fn patterns(patterns: &[String]) -> String {
+    const SHOWN: usize = 3;
+
+    let quoted: Vec<_> = patterns
+        .iter()
+        .take(SHOWN)
+        .map(|pattern| format!("`{pattern}`"))
+        .collect();
+    match (patterns.len(), quoted.split_last()) {
+        (_, None) => String::new(),
+        (1, _) => quoted[0].clone(),
+        (len, Some((last, rest))) if len <= SHOWN => format!("{} and {last}", rest.join(", ")),
+        (len, _) => format!("{} and {} more", quoted.join(", "), len - SHOWN),
+    }
+}
+
+impl Display for TypeErrorS {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.inner, self.span)
+    }
+}
+
 impl Error for TypeErrorS {}
+
+span! { TypeWarning as TypeWarningS }
+/// Something that type checks, but is likely a mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeWarning {
+    /// A `match` arm that no value gets to, because the arms before it match every value
+    /// its pattern does.
+    UnreachableArm,
+}
+
+impl TypeWarning {
+    /// The stable code identifying this kind of warning, see [`crate::diagnostics::explain`].
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UnreachableArm => "E0228",
+        }
+    }
+}
+
+impl Display for TypeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnreachableArm => "unreachable match arm".fmt(f),
+        }
+    }
+}
@@ -0,0 +1,630 @@
+//! Checking that a `match` has an arm for every value of its scrutinee, and that each of
+//! its arms is reached by some value. Patterns in a `let` or a parameter list are held to
+//! matching every value on their own.
+//!
+//! Both come down to whether a pattern is useful after some others, that is whether it
+//! matches a value none of them do, as worked out in Maranget's "Warnings for pattern
+//! matching". Patterns are first reduced to constructors applied to the patterns of their
+//! fields. The values of a type are then split into constructors that each pattern matches
+//! either all or none of, like the ranges between the bounds of integer patterns, so that
+//! the patterns for each constructor can be looked at on their own.
+
+use std::{collections::HashMap, iter, slice};
+
+use crate::{
+    helpers::Span,
+    parser::ast::{Expr, ExprS, MatchArmS, Path, Pattern, PatternS, Unop},
+};
+
+use super::{EnumInfo, Type, TypeChecker, TypeError, TypeResult, TypeWarning, VariantFields};
+
+/// What a pattern requires of a value, besides what it requires of the value's fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Constructor {
+    /// The only constructor of a struct or tuple.
+    Single,
+    /// The enum variant at this index.
+    Variant(usize),
+    Bool(bool),
+    /// The integers from the first to the second, both included.
+    Range(i128, i128),
+    /// Arrays of exactly this length.
+    Array(usize),
+    /// Arrays of at least this length, which is longer than any array pattern without a
+    /// `..` in the same place.
+    ArrayAtLeast(usize),
+    /// An array pattern with a `..`, and this many elements before and after it.
+    Slice {
+        prefix: usize,
+        suffix: usize,
+    },
+    /// A literal of a type with too many values to list, like a string.
+    Opaque(String),
+}
+
+impl Constructor {
+    /// Whether a pattern with this constructor matches every value `other` builds.
+    fn covers(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Range(start, end), Self::Range(other_start, other_end)) => {
+                start <= other_start && other_end <= end
+            }
+            (Self::Slice { prefix, suffix }, Self::Array(len) | Self::ArrayAtLeast(len)) => {
+                prefix + suffix <= *len
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// A pattern reduced to what the checks need.
+#[derive(Debug, Clone)]
+enum Pat {
+    /// A pattern that matches anything, like `_` or a name.
+    Wild,
+    Ctor(Constructor, Vec<Self>),
+    Or(Vec<Self>),
+}
+
+impl Pat {
+    const fn constructor(&self) -> Option<&Constructor> {
+        match self {
+            Self::Ctor(ctor, _) => Some(ctor),
+            Self::Wild | Self::Or(_) => None,
+        }
+    }
+
+    /// Whether the pattern matches every value `ctor` builds, as long as it matches their
+    /// fields.
+    fn covers(&self, ctor: &Constructor) -> bool {
+        match self {
+            Self::Wild => true,
+            Self::Ctor(own, _) => own.covers(ctor),
+            Self::Or(alternatives) => alternatives
+                .iter()
+                .any(|alternative| alternative.covers(ctor)),
+        }
+    }
+}
+
+/// The constructors of a type, split so that each of the patterns they were split for
+/// matches either all or none of the values of each one.
+struct Split {
+    ctors: Vec<Constructor>,
+    /// Whether there are values none of `ctors` build, which is the case for types with
+    /// too many values to list.
+    infinite: bool,
+}
+
+impl TypeChecker {
+    /// Checks that `arms` match every value of `ty`, the type of the scrutinee at `span`,
+    /// warning about the arms that no value gets to.
+    pub(super) fn check_arms(
+        &mut self,
+        arms: &[MatchArmS],
+        ty: &Type,
+        span: Span,
+    ) -> TypeResult<()> {
+        let mut rows = Vec::new();
+
+        for arm in arms {
+            let row = vec![self.lower(&arm.inner.pattern, ty)];
+            if self.witnesses(&rows, &row, slice::from_ref(ty)).is_empty() {
+                self.warnings
+                    .push(TypeWarning::UnreachableArm.spanned(arm.inner.pattern.span));
+            }
+
+            // an arm with a guard might not match the values its pattern does
+            if arm.inner.guard.is_none() {
+                rows.push(row);
+            }
+        }
+
+        let missing = self.missing(&rows, ty);
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(TypeError::NonExhaustive { missing }.spanned(span))
+        }
+    }
+
+    /// Checks that `pattern`, which binds a value of type `ty` in a `let` or a parameter
+    /// list, matches every value of it.
+    pub(super) fn check_irrefutable(&mut self, pattern: &PatternS, ty: &Type) -> TypeResult<()> {
+        let ty = self.resolve(ty);
+        let missing = self.missing(&[vec![self.lower(pattern, &ty)]], &ty);
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(TypeError::Refutable { missing }.spanned(pattern.span))
+        }
+    }
+
+    /// Patterns for the values of type `ty` that no row matches, where each row has a
+    /// single column.
+    fn missing(&self, rows: &[Vec<Pat>], ty: &Type) -> Vec<String> {
+        self.witnesses(rows, &[Pat::Wild], slice::from_ref(ty))
+            .iter()
+            .map(|witness| self.show(&witness[0], ty))
+            .collect()
+    }
+
+    /// The values that `query` matches but none of `rows` do, as a pattern for each
+    /// column. Each row, like `query`, has a pattern for each of `types`.
+    fn witnesses(&self, rows: &[Vec<Pat>], query: &[Pat], types: &[Type]) -> Vec<Vec<Pat>> {
+        let Some((ty, tail_types)) = types.split_first() else {
+            return if rows.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        };
+
+        // a row starting with an or-pattern stands for a row for each alternative
+        let rows: Vec<_> = rows.iter().flat_map(|row| expand(row)).collect();
+        let (head, tail) = query
+            .split_first()
+            .expect("the query has a pattern per column");
+
+        if let Pat::Or(alternatives) = head {
+            return alternatives
+                .iter()
+                .flat_map(|alternative| {
+                    let query: Vec<_> = iter::once(alternative.clone())
+                        .chain(tail.iter().cloned())
+                        .collect();
+                    self.witnesses(&rows, &query, types)
+                })
+                .collect();
+        }
+
+        let heads: Vec<_> = rows
+            .iter()
+            .map(|row| &row[0])
+            .chain(iter::once(head))
+            .filter_map(Pat::constructor)
+            .collect();
+        let split = self.split(ty, &heads);
+
+        // a wildcard leaves out the constructors no row names, unless it's the only
+        // pattern in the column
+        let missing: Vec<_> = split
+            .ctors
+            .iter()
+            .filter(|ctor| matches!(head, Pat::Wild) && !rows.iter().any(|row| row[0].covers(ctor)))
+            .collect();
+
+        let mut witnesses: Vec<Vec<Pat>> = split
+            .ctors
+            .iter()
+            .filter(|ctor| head.covers(ctor) && !missing.contains(ctor))
+            .flat_map(|ctor| {
+                let fields = self.fields(ty, ctor);
+                let arity = fields.len();
+                let rows: Vec<_> = rows
+                    .iter()
+                    .filter_map(|row| specialize(row, ctor, arity))
+                    .collect();
+                let query = specialize(query, ctor, arity).expect("the query covers `ctor`");
+                let types: Vec<_> = fields
+                    .into_iter()
+                    .map(|(_, ty)| ty)
+                    .chain(tail_types.iter().cloned())
+                    .collect();
+
+                self.witnesses(&rows, &query, &types)
+                    .into_iter()
+                    .map(move |mut witness| {
+                        let tail = witness.split_off(arity);
+                        iter::once(Pat::Ctor(ctor.clone(), witness))
+                            .chain(tail)
+                            .collect()
+                    })
+            })
+            .collect();
+
+        // the values of the constructors left out are only matched by the rows that match
+        // anything, so they are looked at together
+        if matches!(head, Pat::Wild) && (split.infinite || !missing.is_empty()) {
+            let default: Vec<_> = rows
+                .iter()
+                .filter(|row| matches!(row[0], Pat::Wild))
+                .map(|row| row[1..].to_vec())
+                .collect();
+            let tails = self.witnesses(&default, tail, tail_types);
+
+            // the constructors left out are only listed if some of the others are named
+            let heads: Vec<_> =
+                if split.infinite || rows.iter().all(|row| matches!(row[0], Pat::Wild)) {
+                    vec![Pat::Wild]
+                } else {
+                    merge_ranges(missing)
+                        .into_iter()
+                        .map(|ctor| {
+                            let arity = self.fields(ty, &ctor).len();
+                            Pat::Ctor(ctor, vec![Pat::Wild; arity])
+                        })
+                        .collect()
+                };
+
+            for head in heads {
+                witnesses.extend(
+                    tails
+                        .iter()
+                        .map(|tail| iter::once(head.clone()).chain(tail.clone()).collect()),
+                );
+            }
+        }
+
+        witnesses
+    }
+
+    /// Splits the values of `ty` into constructors that each of `heads` covers either
+    /// entirely or not at all.
+    fn split(&self, ty: &Type, heads: &[&Constructor]) -> Split {
+        let list = |ctors| Split {
+            ctors,
+            infinite: false,
+        };
+
+        let Type::Named(name, _) = ty else {
+            return opaque(heads);
+        };
+        if *ty == Type::bool() {
+            list(vec![Constructor::Bool(true), Constructor::Bool(false)])
+        } else if name == "$Tuple" || self.structs.contains_key(name) {
+            list(vec![Constructor::Single])
+        } else if let Some(info) = self.enums.get(name) {
+            list((0..info.variants.len()).map(Constructor::Variant).collect())
+        } else if name == "$Array" {
+            // arrays longer than every pattern without a `..` are matched alike
+            let longest = heads
+                .iter()
+                .filter_map(|head| match head {
+                    Constructor::Array(len) => Some(*len),
+                    Constructor::Slice { prefix, suffix } => Some(prefix + suffix),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0);
+            list(
+                (0..=longest)
+                    .map(Constructor::Array)
+                    .chain(iter::once(Constructor::ArrayAtLeast(longest + 1)))
+                    .collect(),
+            )
+        } else if let Some((min, max)) = ty.integer_range() {
+            // every range starts at one of these and ends right before the next
+            let mut bounds = vec![min, max + 1];
+            for head in heads {
+                if let Constructor::Range(start, end) = head
+                    && *start <= max
+                    && *end >= min
+                {
+                    bounds.extend([(*start).max(min), (*end).min(max) + 1]);
+                }
+            }
+            bounds.sort_unstable();
+            bounds.dedup();
+
+            list(
+                bounds
+                    .windows(2)
+                    .map(|bounds| Constructor::Range(bounds[0], bounds[1] - 1))
+                    .collect(),
+            )
+        } else {
+            opaque(heads)
+        }
+    }
+
+    /// The names and types of the fields of a value of type `ty` built by `ctor`. The
+    /// fields of tuples and tuple variants are named after their positions.
+    fn fields(&self, ty: &Type, ctor: &Constructor) -> Vec<(String, Type)> {
+        let Type::Named(name, args) = ty else {
+            return Vec::new();
+        };
+        let positional = |types: Vec<Type>| {
+            types
+                .into_iter()
+                .enumerate()
+                .map(|(i, ty)| (i.to_string(), ty))
+                .collect()
+        };
+
+        match ctor {
+            Constructor::Single if name == "$Tuple" => positional(args.clone()),
+            Constructor::Single => self.structs.get(name).map_or_else(Vec::new, |info| {
+                info.fields
+                    .iter()
+                    .map(|(field, ty)| (field.clone(), ty.substitute(&info.generic_params, args)))
+                    .collect()
+            }),
+            Constructor::Variant(i) => {
+                let info = &self.enums[name];
+                match info.variants[*i].1.substitute(&info.generic_params, args) {
+                    VariantFields::Unit => Vec::new(),
+                    VariantFields::Tuple(types) => positional(types),
+                    VariantFields::Struct(fields) => fields,
+                }
+            }
+            Constructor::Array(len) | Constructor::ArrayAtLeast(len) => {
+                positional(vec![args[0].clone(); *len])
+            }
+            Constructor::Slice { prefix, suffix } => {
+                positional(vec![args[0].clone(); prefix + suffix])
+            }
+            Constructor::Bool(_) | Constructor::Range(..) | Constructor::Opaque(_) => Vec::new(),
+        }
+    }
+
+    /// Reduces `pattern`, which matches values of type `ty`, to its constructors.
+    fn lower(&self, pattern: &PatternS, ty: &Type) -> Pat {
+        let ctor = |ctor: Constructor, patterns: &[PatternS]| {
+            let fields = self
+                .fields(ty, &ctor)
+                .iter()
+                .zip(patterns)
+                .map(|((_, ty), pattern)| self.lower(pattern, ty))
+                .collect();
+            Pat::Ctor(ctor, fields)
+        };
+
+        match &pattern.inner {
+            Pattern::Wildcard
+            | Pattern::Rest
+            | Pattern::Binding {
+                subpattern: None, ..
+            } => Pat::Wild,
+            Pattern::Binding {
+                subpattern: Some(subpattern),
+                ..
+            } => self.lower(subpattern, ty),
+            Pattern::Literal(literal) => {
+                let ctor = match (&literal.inner, int_value(literal)) {
+                    (Expr::Bool(value), _) => Constructor::Bool(*value),
+                    (_, Some(value)) if ty.integer_range().is_some() => {
+                        Constructor::Range(value, value)
+                    }
+                    _ => Constructor::Opaque(literal_key(literal)),
+                };
+                Pat::Ctor(ctor, Vec::new())
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => match (int_value(start), int_value(end)) {
+                (Some(start), Some(end)) => Pat::Ctor(
+                    Constructor::Range(start, if *inclusive { end } else { end - 1 }),
+                    Vec::new(),
+                ),
+                _ => Pat::Wild,
+            },
+            Pattern::Tuple(elements) => ctor(Constructor::Single, elements),
+            Pattern::Array(elements) => {
+                let Some(rest) = elements.iter().position(is_rest) else {
+                    return ctor(Constructor::Array(elements.len()), elements);
+                };
+                let elements: Vec<_> = elements[..rest]
+                    .iter()
+                    .chain(&elements[rest + 1..])
+                    .cloned()
+                    .collect();
+                let slice = Constructor::Slice {
+                    prefix: rest,
+                    suffix: elements.len() - rest,
+                };
+                ctor(slice, &elements)
+            }
+            Pattern::Struct { path, fields, .. } => {
+                let ctor = path_constructor(&self.enums, path);
+                let fields = self
+                    .fields(ty, &ctor)
+                    .iter()
+                    .map(|(name, ty)| {
+                        fields
+                            .iter()
+                            .find(|field| field.inner.name == *name)
+                            .map_or(Pat::Wild, |field| self.lower(&field.inner.pattern, ty))
+                    })
+                    .collect();
+                Pat::Ctor(ctor, fields)
+            }
+            Pattern::TupleVariant { path, elements } => {
+                ctor(path_constructor(&self.enums, path), elements)
+            }
+            Pattern::Path(path) => Pat::Ctor(path_constructor(&self.enums, path), Vec::new()),
+            Pattern::Or(alternatives) => Pat::Or(
+                alternatives
+                    .iter()
+                    .map(|alternative| self.lower(alternative, ty))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Writes out a pattern found by [`Self::witnesses`] for a value of type `ty`.
+    fn show(&self, pattern: &Pat, ty: &Type) -> String {
+        let (Pat::Ctor(ctor, fields), Type::Named(name, _)) = (pattern, ty) else {
+            return "_".into();
+        };
+        let field_types = self.fields(ty, ctor);
+        let shown: Vec<_> = iter::zip(fields, &field_types)
+            .map(|(field, (_, ty))| self.show(field, ty))
+            .collect();
+        let names: Vec<_> = field_types.iter().map(|(name, _)| name.as_str()).collect();
+
+        match ctor {
+            Constructor::Single if name == "$Tuple" && shown.len() == 1 => {
+                format!("({},)", shown[0])
+            }
+            Constructor::Single if name == "$Tuple" => format!("({})", shown.join(", ")),
+            Constructor::Single => braces(name, &names, &shown),
+            Constructor::Variant(i) => {
+                let (variant, fields) = &self.enums[name].variants[*i];
+                let path = format!("{name}::{variant}");
+                match fields {
+                    VariantFields::Unit => path,
+                    VariantFields::Tuple(_) => format!("{path}({})", shown.join(", ")),
+                    VariantFields::Struct(_) => braces(&path, &names, &shown),
+                }
+            }
+            Constructor::Bool(value) => value.to_string(),
+            // every value of the type
+            Constructor::Range(start, end) if ty.integer_range() == Some((*start, *end)) => {
+                "_".into()
+            }
+            Constructor::Range(start, end) if start == end => start.to_string(),
+            Constructor::Range(start, end) => format!("{start}..={end}"),
+            Constructor::Array(_) => format!("[{}]", shown.join(", ")),
+            Constructor::ArrayAtLeast(_) | Constructor::Slice { .. } => {
+                let elements: Vec<_> = shown.into_iter().chain(iter::once("..".into())).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Constructor::Opaque(key) => key.clone(),
+        }
+    }
+}
+
+/// The value of an integer literal pattern, which may be negated.
+pub(super) fn int_value(literal: &ExprS) -> Option<i128> {
+    match &literal.inner {
+        Expr::Int(value, _) => Some((*value).into()),
+        Expr::UnaryOp {
+            op: Unop::Neg,
+            expr,
+        } => int_value(expr).map(|value| -value),
+        _ => None,
+    }
+}
+
+/// Tells literals of types with too many values to list apart.
+fn literal_key(literal: &ExprS) -> String {
+    match &literal.inner {
+        Expr::Str(value) => format!("{value:?}"),
+        Expr::Char(value) => format!("{value:?}"),
+        Expr::Float(value, _) => value.to_string(),
+        Expr::Int(value, _) => value.to_string(),
+        Expr::UnaryOp { expr, .. } => format!("-{}", literal_key(expr)),
+        expr => format!("{expr:?}"),
+    }
+}
+
+/// Whether `pattern` is a `..` in an array pattern, which may be bound with `@`.
+fn is_rest(pattern: &PatternS) -> bool {
+    match &pattern.inner {
+        Pattern::Rest => true,
+        Pattern::Binding {
+            subpattern: Some(subpattern),
+            ..
+        } => subpattern.inner == Pattern::Rest,
+        _ => false,
+    }
+}
+
+fn path_constructor(enums: &HashMap<String, EnumInfo>, path: &Path) -> Constructor {
+    let Some(variant) = &path.variant else {
+        return Constructor::Single;
+    };
+    Constructor::Variant(
+        enums[&path.name]
+            .variants
+            .iter()
+            .position(|(name, _)| name == variant)
+            .expect("patterns are checked before their arms"),
+    )
+}
+
+/// The constructors of the literals among `heads`, for a type with too many values to
+/// list.
+fn opaque(heads: &[&Constructor]) -> Split {
+    let mut ctors: Vec<Constructor> = Vec::new();
+    for head in heads {
+        if let Constructor::Opaque(_) = head
+            && !ctors.contains(head)
+        {
+            ctors.push((*head).clone());
+        }
+    }
+
+    Split {
+        ctors,
+        infinite: true,
+    }
+}
+
+/// The rows `row` stands for, one for each alternative of an or-pattern at its start.
+fn expand(row: &[Pat]) -> Vec<Vec<Pat>> {
+    match row.split_first() {
+        Some((Pat::Or(alternatives), tail)) => alternatives
+            .iter()
+            .flat_map(|alternative| {
+                let row: Vec<_> = iter::once(alternative.clone())
+                    .chain(tail.iter().cloned())
+                    .collect();
+                expand(&row)
+            })
+            .collect(),
+        _ => vec![row.to_vec()],
+    }
+}
+
+/// The fields of the value `row` starts with followed by the rest of `row`, if the
+/// pattern it starts with matches values `ctor` builds, which have `arity` fields.
+fn specialize(row: &[Pat], ctor: &Constructor, arity: usize) -> Option<Vec<Pat>> {
+    let (head, tail) = row.split_first()?;
+
+    let fields = match head {
+        Pat::Wild => vec![Pat::Wild; arity],
+        Pat::Ctor(own, fields) if own.covers(ctor) => match own {
+            // the elements the `..` stands for match anything
+            Constructor::Slice { prefix, suffix } => {
+                let mut fields = fields.clone();
+                let suffix_fields = fields.split_off(*prefix);
+                fields.extend(vec![Pat::Wild; arity - prefix - suffix]);
+                fields.extend(suffix_fields);
+                fields
+            }
+            _ => fields.clone(),
+        },
+        Pat::Ctor(..) => return None,
+        Pat::Or(_) => unreachable!("or-patterns are expanded before specializing"),
+    };
+
+    Some(fields.into_iter().chain(tail.iter().cloned()).collect())
+}
+
+/// `ctors` in order, with the integer ranges that are next to each other joined up.
+fn merge_ranges(ctors: Vec<&Constructor>) -> Vec<Constructor> {
+    let mut merged: Vec<Constructor> = Vec::new();
+    for ctor in ctors {
+        match (merged.last_mut(), ctor) {
+            (Some(Constructor::Range(_, end)), Constructor::Range(start, next_end))
+                if *end + 1 == *start =>
+            {
+                *end = *next_end;
+            }
+            _ => merged.push(ctor.clone()),
+        }
+    }
+    merged
+}
+
+/// `path { a: x, .. }`, leaving out the fields that are `_`.
+fn braces(path: &str, names: &[&str], shown: &[String]) -> String {
+    let mut fields: Vec<_> = iter::zip(names, shown)
+        .filter(|(_, shown)| *shown != "_")
+        .map(|(name, shown)| format!("{name}: {shown}"))
+        .collect();
+    if fields.len() < names.len() {
+        fields.push("..".into());
+    }
+
+    if fields.is_empty() {
+        format!("{path} {{}}")
+    } else {
+        format!("{path} {{ {} }}", fields.join(", "))
+    }
+}
@@ -1,21 +1,24 @@
 mod error;
+mod exhaustiveness;
+mod patterns;
 #[cfg(test)]
 mod test;
 mod types;
 
-use std::{collections::HashMap, iter, slice};
+use std::{collections::HashMap, iter, mem};
 
 use crate::{
     helpers::{Span, Spanned},
-    parser::ast::{Ast, Binding, BindingS, Bop, Expr, ExprS, Item, Unop},
-    typecheck::{
-        error::TypeErrorS,
-        types::{Type, TypeId},
+    parser::ast::{
+        Ast, Binding, BindingS, Bop, Expr, ExprS, FieldInitS, FieldS, Item, ItemS, MatchArmS, Path,
+        StringPart, TypeS, Unop, Variant, partial_application,
     },
 };
 
-use ena::unify::{InPlace, UnificationTable, UnifyKey};
-use error::{TypeError, TypeResult};
+use ena::unify::{InPlace, UnificationTable};
+pub use error::{TypeError, TypeErrorS, TypeResult, TypeWarning, TypeWarningS};
+use types::Bound;
+pub use types::{Type, TypeId};
 
 #[derive(Clone)]
 pub struct BindingInfo {
@@ -23,137 +26,552 @@ pub struct BindingInfo {
     mutable: bool,
 }
 
+#[derive(Clone)]
+struct StructInfo {
+    generic_params: Vec<String>,
+    fields: Vec<(String, Type)>,
+}
+
+#[derive(Clone)]
+struct EnumInfo {
+    generic_params: Vec<String>,
+    variants: Vec<(String, VariantFields)>,
+}
+
+/// The fields of an enum variant, or of a struct as [`Self::Struct`].
+#[derive(Clone)]
+enum VariantFields {
+    Unit,
+    Tuple(Vec<Type>),
+    Struct(Vec<(String, Type)>),
+}
+
+impl VariantFields {
+    fn substitute(&self, params: &[String], args: &[Type]) -> Self {
+        match self {
+            Self::Unit => Self::Unit,
+            Self::Tuple(types) => {
+                Self::Tuple(types.iter().map(|ty| ty.substitute(params, args)).collect())
+            }
+            Self::Struct(fields) => Self::Struct(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), ty.substitute(params, args)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The fields by name, where those of a tuple variant are named after their position.
+    fn named(self) -> Vec<(String, Type)> {
+        match self {
+            Self::Unit => Vec::new(),
+            Self::Tuple(types) => types
+                .into_iter()
+                .enumerate()
+                .map(|(i, ty)| (i.to_string(), ty))
+                .collect(),
+            Self::Struct(fields) => fields,
+        }
+    }
+}
+
+/// Why two types failed to unify.
+enum UnifyError {
+    Mismatch,
+    Infinite,
+    /// A negated integer literal unified with an unsigned type.
+    Unsigned(Type),
+}
+
+/// A check that a type satisfies `predicate`, failing with `error` at `span`.
+#[derive(Clone)]
+struct Requirement {
+    span: Span,
+    ty: Type,
+    predicate: fn(&Type) -> bool,
+    error: fn(Type) -> TypeError,
+}
+
 #[derive(Clone, Default)]
 pub struct TypeChecker {
     env: HashMap<String, BindingInfo>,
+    structs: HashMap<String, StructInfo>,
+    enums: HashMap<String, EnumInfo>,
     table: UnificationTable<InPlace<TypeId>>,
+    /// The type of every expression and binding checked so far, as found at the time.
+    spans: Vec<(Span, Type)>,
+    warnings: Vec<TypeWarningS>,
+    /// The value of every integer literal without a suffix checked so far, with its
+    /// span and type, to check it fits once the type is known.
+    literals: Vec<(Span, i128, Type)>,
+    /// The checks [`Self::require`] couldn't make yet because the type was unknown, to
+    /// make them once inference is done.
+    requirements: Vec<Requirement>,
 }
 
 impl TypeChecker {
-    fn occurs(&mut self, var: TypeId, ty: &Type) -> bool {
-        if let Some(n_ty) = self.normalize(ty) {
-            return self.occurs(var, &n_ty);
-        };
+    fn fresh(&mut self) -> Type {
+        Type::Var(self.table.new_key(Bound::Unknown))
+    }
 
+    /// The type of an integer literal without a suffix, whose value is `value`.
+    fn integer_literal(&mut self, value: i128, span: Span) -> Type {
+        let ty = Type::Var(self.table.new_key(Bound::Integer { signed: value < 0 }));
+        self.literals.push((span, value, ty.clone()));
+        ty
+    }
+
+    /// The type of a float literal without a suffix.
+    fn float_literal(&mut self) -> Type {
+        Type::Var(self.table.new_key(Bound::Float))
+    }
+
+    /// Follows type variables until reaching a named type or an unbound variable.
+    fn shallow(&mut self, ty: &Type) -> Type {
         match ty {
-            Type::Named(_, args) => args.iter().any(|ty| self.occurs(var, ty)),
-            Type::Var(id) => self.table.unioned(var.index(), *id),
+            Type::Var(id) => match self.table.probe_value(*id) {
+                Bound::Known(ty) => self.shallow(&ty),
+                Bound::Unknown | Bound::Integer { .. } | Bound::Float => {
+                    Type::Var(self.table.find(*id))
+                }
+            },
+            Type::Named(..) => ty.clone(),
         }
     }
 
-    fn normalize(&mut self, ty: &Type) -> Option<Type> {
-        ty.id().and_then(|var| match self.table.probe_value(var) {
-            Type::Var(_) => None,
-            ty @ Type::Named(..) => Some(ty.clone()),
-        })
+    /// Substitutes every bound type variable in `ty`, defaulting integer literals to `Int`
+    /// and float literals to `Float`.
+    pub fn resolve(&mut self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(id) => match self.table.probe_value(*id) {
+                Bound::Known(ty) => self.resolve(&ty),
+                bound => bound
+                    .default_type()
+                    .unwrap_or_else(|| Type::Var(self.table.find(*id))),
+            },
+            Type::Named(name, args) => Type::Named(
+                name.clone(),
+                args.iter().map(|ty| self.resolve(ty)).collect(),
+            ),
+        }
     }
 
-    pub fn unify(&mut self, a: &Type, b: &Type) -> Result<(), TypeError> {
-        if let Some(n_a) = self.normalize(&a) {
-            return self.unify(&n_a, b);
-        } else if let Some(n_b) = self.normalize(&b) {
-            return self.unify(a, &n_b);
+    fn occurs(&mut self, var: TypeId, ty: &Type) -> bool {
+        match self.shallow(ty) {
+            Type::Named(_, args) => args.iter().any(|ty| self.occurs(var, ty)),
+            Type::Var(id) => self.table.unioned(var, id),
         }
+    }
 
-        match (a, b) {
+    fn unify_inner(&mut self, a: &Type, b: &Type) -> Result<(), UnifyError> {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+
+        match (&a, &b) {
             (Type::Var(a_id), Type::Var(b_id)) => {
-                Ok(self.table.unify_var_var(*a_id, *b_id).expect("infallible"))
+                if let (Bound::Integer { .. }, Bound::Float)
+                | (Bound::Float, Bound::Integer { .. }) =
+                    (self.table.probe_value(*a_id), self.table.probe_value(*b_id))
+                {
+                    return Err(UnifyError::Mismatch);
+                }
+                self.table
+                    .unify_var_var(*a_id, *b_id)
+                    .unwrap_or_else(|never| match never {});
+                Ok(())
             }
-            (Type::Var(id), ty @ Type::Named(..)) | (ty @ Type::Named(..), Type::Var(id)) => {
-                if !self.occurs((*id).into(), &ty) {
-                    Ok(self.table.unify_var_value(*id, ty.clone()).expect("infallible"))
-                } else {
-                    Err(TypeError::Infinite)
+            (Type::Var(id), ty) | (ty, Type::Var(id)) => {
+                if self.occurs(*id, ty) {
+                    return Err(UnifyError::Infinite);
+                }
+                match self.table.probe_value(*id) {
+                    // a negated literal can't become an unsigned integer
+                    Bound::Integer { signed: true } if ty.is_integer() && !ty.is_signed() => {
+                        return Err(UnifyError::Unsigned(ty.clone()));
+                    }
+                    bound if !bound.admits(ty) => return Err(UnifyError::Mismatch),
+                    _ => {}
                 }
+
+                self.table
+                    .unify_var_value(*id, ty.clone().into())
+                    .unwrap_or_else(|never| match never {});
+                Ok(())
             }
             (Type::Named(name_a, args_a), Type::Named(name_b, args_b)) => {
-                if name_a == name_b {
-                    iter::zip(args_a, args_b).try_for_each(|(a, b)| self.unify(a, b))
+                if name_a == name_b && args_a.len() == args_b.len() {
+                    iter::zip(args_a, args_b).try_for_each(|(a, b)| self.unify_inner(a, b))
                 } else {
-                    Err(TypeError::MismatchedTypes(name_a, name_b))
+                    Err(UnifyError::Mismatch)
                 }
             }
         }
     }
 
+    pub fn unify(&mut self, expected: &Type, found: &Type) -> Result<(), TypeError> {
+        self.unify_inner(expected, found).map_err(|err| match err {
+            UnifyError::Mismatch => TypeError::MismatchedTypes {
+                expected: self.resolve(expected),
+                found: self.resolve(found),
+                annotation: None,
+            },
+            UnifyError::Infinite => TypeError::Infinite,
+            UnifyError::Unsigned(ty) => TypeError::NotSigned(ty),
+        })
+    }
+
+    /// Unifies `expected` and `found`, blaming `span` if they don't match.
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span) -> TypeResult<()> {
+        self.unify(expected, found).map_err(|err| err.spanned(span))
+    }
+
+    /// Like [`Self::expect`], but `expected` was written out by the user at `annotation`.
+    fn expect_annotated(
+        &mut self,
+        expected: &Type,
+        found: &Type,
+        span: Span,
+        annotation: Option<Span>,
+    ) -> TypeResult<()> {
+        self.expect(expected, found, span).map_err(|mut err| {
+            if let TypeError::MismatchedTypes {
+                annotation: slot, ..
+            } = &mut err.inner
+            {
+                *slot = annotation;
+            }
+            err
+        })
+    }
+
+    /// Checks that `ty` satisfies `predicate`. Types that are still unknown are checked by
+    /// [`Self::check_deferred`] instead, and those of literals as the type they default to.
+    fn require(
+        &mut self,
+        ty: &Type,
+        predicate: fn(&Type) -> bool,
+        error: fn(Type) -> TypeError,
+        span: Span,
+    ) -> TypeResult<()> {
+        let ty = match self.shallow(ty) {
+            Type::Var(id) => {
+                let Some(ty) = self.table.probe_value(id).default_type() else {
+                    self.requirements.push(Requirement {
+                        span,
+                        ty: Type::Var(id),
+                        predicate,
+                        error,
+                    });
+                    return Ok(());
+                };
+                ty
+            }
+            ty @ Type::Named(..) => ty,
+        };
+
+        if predicate(&ty) {
+            Ok(())
+        } else {
+            Err(error(self.resolve(&ty)).spanned(span))
+        }
+    }
+
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let env = self.env.clone();
+        let result = f(self);
+        self.env = env;
+        result
+    }
+
+    fn annotation_or_fresh(&mut self, annotation: Option<&TypeS>) -> Type {
+        annotation.map_or_else(|| self.fresh(), |ty| Type::from(&ty.inner))
+    }
+
+    fn binding_type(&mut self, binding: &BindingS) -> Type {
+        self.annotation_or_fresh(binding.inner.type_annotation())
+    }
+
+    /// Binds the names in `binding` to the parts of a value of type `ty` they match.
+    fn bind(&mut self, binding: &BindingS, ty: Type) -> TypeResult<()> {
+        match &binding.inner {
+            Binding::Var { mutable, ident, .. } => {
+                self.define(ident, *mutable, ty, binding.span);
+                Ok(())
+            }
+            Binding::Pattern { pattern, .. } => {
+                self.check_pattern(pattern, &ty)?;
+                self.check_irrefutable(pattern, &ty)
+            }
+        }
+    }
+
+    /// Binds `ident` to a value of type `ty`, defined at `span`.
+    fn define(&mut self, ident: &str, mutable: bool, ty: Type, span: Span) {
+        self.spans.push((span, ty.clone()));
+        self.env
+            .insert(ident.to_owned(), BindingInfo { ty, mutable });
+    }
+
     pub fn new(ast: &Ast) -> Self {
         let mut new = Self {
             env: HashMap::with_capacity(ast.len() * 2 / 3),
-            table: UnificationTable::new(),
+            ..Self::default()
         };
 
-        // for item in ast {
-        //     match &item.inner {
-        //         Item::Const { name, ty, value } => {
-        //             new.env.insert(name.clone(), BindingInfo { ty: Type::from(&ty.inner), mutable: false });
-        //         },
-        //         Item::Function {
-        //             name,
-        //             params,
-        //             return_type,
-        //             body,
-        //         } => {
-        //             new.env.insert(name.clone(), BindingInfo { ty: Type::Fn { params: params.iter().map(|Spanned { inner: Binding::Var { mutable,  }, .. }| b.inner.), result: Box::new(Type::from(&return_type.unwrap().inner)) }, mutable: false });
-        //         },
-        //         Item::Struct {
-        //             name,
-        //             generic_params,
-        //             fields,
-        //         } => todo!(),
-        //         Item::Enum {
-        //             name,
-        //             generic_params,
-        //             variants,
-        //         } => todo!(),
-        //     }
-        // }
+        for item in ast {
+            match &item.inner {
+                Item::Const { name, ty, .. } => {
+                    new.env.insert(
+                        name.clone(),
+                        BindingInfo {
+                            ty: Type::from(&ty.inner),
+                            mutable: false,
+                        },
+                    );
+                }
+                Item::Function {
+                    name,
+                    params,
+                    return_type,
+                    ..
+                } => {
+                    let params = params.iter().map(|p| new.binding_type(p)).collect();
+                    let result = new.annotation_or_fresh(return_type.as_ref());
+
+                    new.env.insert(
+                        name.clone(),
+                        BindingInfo {
+                            ty: Type::function(params, result),
+                            mutable: false,
+                        },
+                    );
+                }
+                Item::Struct {
+                    name,
+                    generic_params,
+                    fields,
+                    ..
+                } => {
+                    new.structs.insert(
+                        name.clone(),
+                        StructInfo {
+                            generic_params: generic_params.clone(),
+                            fields: field_types(fields),
+                        },
+                    );
+                }
+                Item::Enum {
+                    name,
+                    generic_params,
+                    variants,
+                    ..
+                } => {
+                    let variants = variants
+                        .iter()
+                        .map(|variant| match &variant.inner {
+                            Variant::Unit(name) => (name.clone(), VariantFields::Unit),
+                            Variant::Tuple(name, types) => (
+                                name.clone(),
+                                VariantFields::Tuple(
+                                    types.iter().map(|ty| Type::from(&ty.inner)).collect(),
+                                ),
+                            ),
+                            Variant::Struct(name, fields) => {
+                                (name.clone(), VariantFields::Struct(field_types(fields)))
+                            }
+                        })
+                        .collect();
+
+                    new.enums.insert(
+                        name.clone(),
+                        EnumInfo {
+                            generic_params: generic_params.clone(),
+                            variants,
+                        },
+                    );
+                }
+                Item::Error => {}
+            }
+        }
 
         new
     }
 
+    /// Checks every item of `ast`, returning the type of each one in order.
+    pub fn check_items(&mut self, ast: &Ast) -> TypeResult<Vec<Type>> {
+        let types = ast
+            .iter()
+            .map(|item| self.check_item(item))
+            .collect::<TypeResult<Vec<_>>>()?;
+
+        self.check_deferred()?;
+
+        Ok(types.iter().map(|ty| self.resolve(ty)).collect())
+    }
+
+    /// Makes the checks that depend on types only known once everything that can decide
+    /// them is checked: that every integer literal without a suffix fits in the type it was
+    /// given, and what [`Self::require`] asked of types that were unknown at the time.
+    ///
+    /// A type that is still unknown, like that of a parameter of a function that is never
+    /// called, satisfies any requirement.
+    pub fn check_deferred(&mut self) -> TypeResult<()> {
+        for (span, value, ty) in mem::take(&mut self.literals) {
+            let ty = self.resolve(&ty);
+            if let Some((min, max)) = ty.integer_range()
+                && !(min..=max).contains(&value)
+            {
+                return Err(TypeError::LiteralOutOfRange(ty).spanned(span));
+            }
+        }
+
+        for requirement in mem::take(&mut self.requirements) {
+            let ty = self.resolve(&requirement.ty);
+            if ty.id().is_none() && !(requirement.predicate)(&ty) {
+                return Err((requirement.error)(ty).spanned(requirement.span));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a single item of the file this checker was created for.
+    pub fn check_item(&mut self, item: &ItemS) -> TypeResult {
+        match &item.inner {
+            Item::Const { ty, value, .. } => {
+                let declared = Type::from(&ty.inner);
+                let found = self.type_of(value)?;
+                self.expect_annotated(&declared, &found, value.span, Some(ty.span))?;
+
+                Ok(declared)
+            }
+            Item::Function {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                let fn_ty = self.env[name].ty.clone();
+                let (param_tys, result) = fn_ty
+                    .as_function()
+                    .expect("function items are registered with function types");
+                let (param_tys, result) = (param_tys.to_vec(), result.clone());
+
+                self.scoped(|this| {
+                    iter::zip(params, param_tys)
+                        .try_for_each(|(param, ty)| this.bind(param, ty))?;
+
+                    let body_ty = this.type_of(body)?;
+                    this.expect_annotated(
+                        &result,
+                        &body_ty,
+                        body.span,
+                        return_type.as_ref().map(|ty| ty.span),
+                    )
+                })?;
+
+                Ok(fn_ty)
+            }
+            Item::Struct {
+                name,
+                generic_params,
+                ..
+            }
+            | Item::Enum {
+                name,
+                generic_params,
+                ..
+            } => Ok(Type::Named(
+                name.clone(),
+                generic_params
+                    .iter()
+                    .map(|param| Type::named(param))
+                    .collect(),
+            )),
+            Item::Error => Ok(self.fresh()),
+        }
+    }
+
+    /// Checks `exprs` in order against a copy of this checker, so any bindings they
+    /// introduce are discarded afterwards.
     pub fn check(&self, exprs: &[ExprS]) -> TypeResult<Vec<Type>> {
         let mut env = self.clone();
 
-        let mut types = Vec::with_capacity(exprs.len());
+        let types = exprs
+            .iter()
+            .map(|expr| env.type_of(expr))
+            .collect::<TypeResult<Vec<_>>>()?;
+        env.check_deferred()?;
 
-        for expr in exprs {
-            types.push(env.type_of(expr)?);
-        }
+        Ok(types.iter().map(|ty| env.resolve(ty)).collect())
+    }
+
+    /// The warnings about everything checked so far.
+    pub fn warnings(&self) -> &[TypeWarningS] {
+        &self.warnings
+    }
 
-        Ok(types)
+    /// The types of every expression and binding checked so far, with the span of each.
+    pub fn span_types(&mut self) -> Vec<(Span, Type)> {
+        let spans = self.spans.clone();
+        spans
+            .into_iter()
+            .map(|(span, ty)| (span, self.resolve(&ty)))
+            .collect()
     }
 
     pub fn type_of(&mut self, expr: &ExprS) -> TypeResult {
+        let ty = self.infer(expr)?;
+        self.spans.push((expr.span, ty.clone()));
+        Ok(ty)
+    }
+
+    fn infer(&mut self, expr: &ExprS) -> TypeResult {
         match &expr.inner {
             Expr::Ident(ident) => self.type_of_ident(Spanned {
                 inner: ident,
                 span: expr.span,
             }),
-            Expr::Int(v) => Ok(if *v > i64::MAX as u64 {
-                Type::UInt
+            Expr::Int(_, Some(suffix)) | Expr::Float(_, Some(suffix)) => Ok((*suffix).into()),
+            Expr::Int(v, None) => Ok(if i64::try_from(*v).is_err() {
+                Type::uint()
             } else {
-                Type::GInt
+                self.integer_literal((*v).into(), expr.span)
             }),
-            Expr::Float(_) => Ok(Type::float()),
+            Expr::Float(_, None) => Ok(self.float_literal()),
             Expr::Str(_) => Ok(Type::str()),
+            Expr::Interpolation(parts) => self.type_of_interpolation(parts),
             Expr::Char(_) => Ok(Type::char()),
             Expr::Bool(_) => Ok(Type::bool()),
             Expr::Array(vals) => self.type_of_array(vals),
             Expr::Tuple(vals) => self.type_of_tuple(vals),
-            Expr::FnCall { fun, args } => self.type_of_fn_call(fun, args, expr.span),
+            Expr::FnCall { fun, args } => match partial_application(fun, args, expr.span) {
+                Some(lambda) => self.infer(&lambda.spanned(expr.span)),
+                None => self.type_of_fn_call(fun, args, expr.span),
+            },
             Expr::BinaryOp { op, lhs, rhs } => self.type_of_binary_op(*op, lhs, rhs),
-            Expr::UnaryOp { op, expr } => self.type_of_unary_op(*op, expr),
+            Expr::UnaryOp { op, expr: operand } => self.type_of_unary_op(*op, operand, expr.span),
             Expr::Index { arr, index } => self.type_of_index(arr, index),
-            Expr::FieldAccess { base, field } => todo!(),
-            Expr::If { cond, th, el } => self.type_of_if(cond, th, el.as_deref(), &expr.span),
+            Expr::FieldAccess { base, field } => self.type_of_field_access(base, field.as_deref()),
+            Expr::If { cond, th, el } => self.type_of_if(cond, th, el.as_deref(), expr.span),
             Expr::Let { binding, value } => self.type_of_let(binding, value),
             Expr::Assign { ident, value } => self.type_of_assign(ident.as_deref(), value),
             Expr::Lambda {
                 params,
                 return_type,
                 body,
-            } => todo!(),
+            } => self.type_of_lambda(params, return_type.as_ref(), body),
             Expr::Block { exprs, trailing } => self.type_of_block(exprs, *trailing),
+            Expr::Match { scrutinee, arms } => self.type_of_match(scrutinee, arms),
+            Expr::Path(path) => self.type_of_path(path, expr.span),
+            Expr::Struct { path, fields, base } => {
+                self.type_of_struct(path, fields, base.as_deref(), expr.span)
+            }
+            // arguments that are `_` were taken care of with their call
+            Expr::Placeholder => Err(TypeError::MisplacedPlaceholder.spanned(expr.span)),
+            // the parser already reported the problem, so accept any use of it
+            Expr::Error => Ok(self.fresh()),
         }
     }
 
@@ -166,25 +584,30 @@ impl TypeChecker {
     }
 
     fn type_of_array(&mut self, vals: &[ExprS]) -> TypeResult {
-        let ty = match vals.first() {
-            Some(e) => self.type_of(e)?,
-            None => Type::Any,
-        };
+        let ty = self.fresh();
 
-        vals[1..].iter().try_for_each(|v| {
-            let this_ty = self.type_of(v)?;
-            if this_ty == ty {
-                Ok(())
-            } else {
-                Err(TypeError::MismatchedTypes {
-                    found: this_ty,
-                    expected: ty.clone(),
-                }
-                .spanned(v.span))
+        for val in vals {
+            let val_ty = self.type_of(val)?;
+            self.expect(&ty, &val_ty, val.span)?;
+        }
+
+        Ok(Type::array(ty))
+    }
+
+    fn type_of_interpolation(&mut self, parts: &[StringPart]) -> TypeResult {
+        for part in parts {
+            if let StringPart::Expr(expr) = part {
+                let ty = self.type_of(expr)?;
+                self.require(
+                    &ty,
+                    Type::is_displayable,
+                    TypeError::NotDisplayable,
+                    expr.span,
+                )?;
             }
-        })?;
+        }
 
-        Ok(Type::array(ty.into()))
+        Ok(Type::str())
     }
 
     fn type_of_tuple(&mut self, vals: &[ExprS]) -> TypeResult {
@@ -195,137 +618,230 @@ impl TypeChecker {
         ))
     }
 
-    fn type_of_fn_call(&mut self, fun: &ExprS, args: &Vec<ExprS>, span: Span) -> TypeResult {
-        let (param_tys, result_ty) = match self.type_of(fun)? {
-            Type::Fn { params, result } => (params, *result),
-            other => return Err(todo!()),
-        };
+    fn type_of_fn_call(&mut self, fun: &ExprS, args: &[ExprS], span: Span) -> TypeResult {
+        let fun_ty = self.type_of(fun)?;
+        let fun_ty = self.shallow(&fun_ty);
 
-        if param_tys.len() != args.len() {
-            return Err(TypeError::WrongArgCount {
-                needed: param_tys.len(),
-                provided: args.len(),
-            }
-            .spanned(span));
-        }
+        let arg_tys = args
+            .iter()
+            .map(|a| self.type_of(a))
+            .collect::<TypeResult<Vec<_>>>()?;
 
-        iter::zip(param_tys, args).try_for_each(|(p, a)| {
-            let arg_ty = self.type_of(a)?;
-
-            if p == arg_ty {
-                Ok(())
-            } else {
-                Err(TypeError::MismatchedTypes {
-                    found: p,
-                    expected: arg_ty,
+        if let Some((param_tys, result_ty)) = fun_ty.as_function() {
+            if param_tys.len() != args.len() {
+                return Err(TypeError::WrongArgCount {
+                    needed: param_tys.len(),
+                    provided: args.len(),
                 }
-                .spanned(a.span))
+                .spanned(span));
             }
-        })?;
 
-        Ok(result_ty)
+            let (param_tys, result_ty) = (param_tys.to_vec(), result_ty.clone());
+
+            iter::zip(param_tys, arg_tys)
+                .zip(args)
+                .try_for_each(|((param_ty, arg_ty), arg)| {
+                    self.expect(&param_ty, &arg_ty, arg.span)
+                })?;
+
+            Ok(result_ty)
+        } else if fun_ty.id().is_some() {
+            let result_ty = self.fresh();
+            self.expect(
+                &fun_ty,
+                &Type::function(arg_tys, result_ty.clone()),
+                fun.span,
+            )?;
+
+            Ok(result_ty)
+        } else {
+            Err(TypeError::NotCallable(self.resolve(&fun_ty)).spanned(fun.span))
+        }
+    }
+
+    /// Types both operands and makes sure they agree, returning the shared type.
+    fn type_of_operands(&mut self, lhs: &ExprS, rhs: &ExprS) -> TypeResult {
+        let (lhs_ty, rhs_ty) = (self.type_of(lhs)?, self.type_of(rhs)?);
+        self.expect(&lhs_ty, &rhs_ty, rhs.span)?;
+
+        Ok(lhs_ty)
     }
 
     fn type_of_binary_op(&mut self, op: Bop, lhs: &ExprS, rhs: &ExprS) -> TypeResult {
         match op {
             Bop::Add | Bop::Sub | Bop::Mul | Bop::Div | Bop::Exp => {
-                let (lhs_ty, rhs_ty) = (self.type_of(lhs)?, self.type_of(rhs)?);
+                let ty = self.type_of_operands(lhs, rhs)?;
+                self.require(&ty, Type::is_numeric, TypeError::NotNumeric, lhs.span)?;
 
-                if !lhs_ty.is_numeric() {
-                    return Err(TypeError::NotNumeric(lhs_ty).spanned(lhs.span));
-                }
-
-                if !rhs_ty.is_numeric() {
-                    return Err(TypeError::NotNumeric(rhs_ty).spanned(rhs.span));
-                }
-
-                if lhs_ty != rhs_ty {
-                    return Err(TypeError::MismatchedTypes {
-                        found: rhs_ty,
-                        expected: lhs_ty,
-                    }
-                    .spanned(rhs.span));
-                }
-
-                Ok(lhs_ty)
+                Ok(ty)
             }
             Bop::And | Bop::Or | Bop::Xor => {
-                check_type!(self, lhs, Type::Bool);
-                check_type!(self, rhs, Type::Bool);
-                Ok(Type::Bool)
-            }
-            Bop::BOr | Bop::BAnd => {
-                let (lhs_ty, rhs_ty) = (self.type_of(lhs)?, self.type_of(rhs)?);
-
-                if !lhs_ty.is_integer() {
-                    return Err(TypeError::NotInteger(lhs_ty).spanned(lhs.span));
-                }
-
-                if !rhs_ty.is_integer() {
-                    return Err(TypeError::NotInteger(rhs_ty).spanned(rhs.span));
+                for operand in [lhs, rhs] {
+                    let ty = self.type_of(operand)?;
+                    self.expect(&Type::bool(), &ty, operand.span)?;
                 }
 
-                if lhs_ty != rhs_ty {
-                    return Err(TypeError::MismatchedTypes {
-                        found: rhs_ty,
-                        expected: lhs_ty,
-                    }
-                    .spanned(rhs.span));
-                }
+                Ok(Type::bool())
+            }
+            Bop::BOr | Bop::BAnd => {
+                let ty = self.type_of_operands(lhs, rhs)?;
+                self.require(&ty, Type::is_integer, TypeError::NotInteger, lhs.span)?;
 
-                Ok(lhs_ty)
+                Ok(ty)
             }
             Bop::Eqq | Bop::Neq => {
-                let (lhs_ty, rhs_ty) = (self.type_of(lhs)?, self.type_of(rhs)?);
+                self.type_of_operands(lhs, rhs)?;
 
-                if lhs_ty == rhs_ty {
-                    Ok(Type::Bool)
-                } else {
-                    Err(TypeError::MismatchedTypes {
-                        found: rhs_ty,
-                        expected: lhs_ty,
-                    }
-                    .spanned(rhs.span))
-                }
+                Ok(Type::bool())
             }
             Bop::Gt | Bop::Lt | Bop::Geq | Bop::Leq => {
-                let (lhs_ty, rhs_ty) = (self.type_of(lhs)?, self.type_of(rhs)?);
-
-                if !lhs_ty.is_numeric() {
-                    return Err(TypeError::NotNumeric(lhs_ty).spanned(lhs.span));
-                }
-
-                if !rhs_ty.is_numeric() {
-                    return Err(TypeError::NotNumeric(rhs_ty).spanned(rhs.span));
-                }
+                let ty = self.type_of_operands(lhs, rhs)?;
+                self.require(&ty, Type::is_numeric, TypeError::NotNumeric, lhs.span)?;
 
-                Ok(Type::Bool)
+                Ok(Type::bool())
             }
         }
     }
 
-    fn type_of_unary_op(&mut self, op: Unop, expr: &ExprS) -> TypeResult {
+    fn type_of_unary_op(&mut self, op: Unop, expr: &ExprS, span: Span) -> TypeResult {
+        // a negated literal is checked as a whole, so that `-128` fits in an `I8`
+        if let (Unop::Neg, Expr::Int(value, None)) = (op, &expr.inner) {
+            let ty = self.integer_literal(-i128::from(*value), span);
+            self.spans.push((expr.span, ty.clone()));
+            return Ok(ty);
+        }
+
+        let ty = self.type_of(expr)?;
+
         match op {
             Unop::Not => {
-                check_type!(self, expr, Type::Bool);
-                Ok(Type::Bool)
+                self.expect(&Type::bool(), &ty, expr.span)?;
+
+                Ok(Type::bool())
+            }
+            Unop::Neg => {
+                // negating an integer literal keeps it from becoming an unsigned type
+                if let Some(id) = self.shallow(&ty).id()
+                    && let Bound::Integer { .. } = self.table.probe_value(id)
+                {
+                    self.table
+                        .unify_var_value(id, Bound::Integer { signed: true })
+                        .unwrap_or_else(|never| match never {});
+                }
+
+                self.require(&ty, Type::is_numeric, TypeError::NotNumeric, expr.span)?;
+                self.require(&ty, Type::is_signed, TypeError::NotSigned, expr.span)?;
+
+                Ok(ty)
             }
-            Unop::Neg => match self.type_of(expr)? {
-                Type::GInt => Ok(Type::Int),
-                ty @ (Type::Int | Type::Float) => Ok(ty),
-                other => Err(todo!()),
-            },
         }
     }
 
     fn type_of_index(&mut self, arr: &ExprS, index: &ExprS) -> TypeResult {
-        check_type!(self, index, Type::UInt);
+        let index_ty = self.type_of(index)?;
+        self.expect(&Type::uint(), &index_ty, index.span)?;
 
-        let Type::Array(ref inner) = self.type_of(arr)? else {
-            return Err(todo!());
-        };
+        let elem_ty = self.fresh();
+        let arr_ty = self.type_of(arr)?;
+        self.expect(&Type::array(elem_ty.clone()), &arr_ty, arr.span)?;
 
-        Ok(*inner.clone())
+        Ok(elem_ty)
+    }
+
+    fn type_of_field_access(&mut self, base: &ExprS, field: Spanned<&str>) -> TypeResult {
+        let base_ty = self.type_of(base)?;
+
+        match self.shallow(&base_ty) {
+            Type::Var(_) => Err(TypeError::CantInfer.spanned(base.span)),
+            Type::Named(name, args) => self
+                .structs
+                .get(&name)
+                .and_then(|info| {
+                    info.fields
+                        .iter()
+                        .find(|(name, _)| name == field.inner)
+                        .map(|(_, ty)| ty.substitute(&info.generic_params, &args))
+                })
+                .ok_or_else(|| {
+                    TypeError::NoField {
+                        ty: self.resolve(&base_ty),
+                        field: field.inner.to_owned(),
+                    }
+                    .spanned(field.span)
+                }),
+        }
+    }
+
+    /// The type of an enum variant, which for one with fields is a function from them to
+    /// the enum.
+    fn type_of_path(&mut self, path: &Path, span: Span) -> TypeResult {
+        let ty = self.fresh();
+
+        match self.path_type(path, &ty, span)? {
+            VariantFields::Unit => Ok(ty),
+            VariantFields::Tuple(types) => Ok(Type::function(types, ty)),
+            VariantFields::Struct(_) => Err(TypeError::NeedsBraces(path.to_string()).spanned(span)),
+        }
+    }
+
+    /// Checks that a struct literal gives each field of `path` one value of its type,
+    /// unless `base` gives the ones it leaves out.
+    fn type_of_struct(
+        &mut self,
+        path: &Path,
+        fields: &[FieldInitS],
+        base: Option<&ExprS>,
+        span: Span,
+    ) -> TypeResult {
+        let ty = self.fresh();
+        let declared = self.path_type(path, &ty, span)?.named();
+
+        for (i, field) in fields.iter().enumerate() {
+            if fields[..i]
+                .iter()
+                .any(|other| other.inner.name == field.inner.name)
+            {
+                return Err(TypeError::DuplicateField(field.inner.name.clone()).spanned(field.span));
+            }
+
+            let field_ty = declared
+                .iter()
+                .find(|(name, _)| *name == field.inner.name)
+                .map(|(_, ty)| ty.clone())
+                .ok_or_else(|| {
+                    TypeError::NoField {
+                        ty: self.resolve(&ty),
+                        field: field.inner.name.clone(),
+                    }
+                    .spanned(field.span)
+                })?;
+            let value_ty = self.type_of(&field.inner.value)?;
+            self.expect(&field_ty, &value_ty, field.inner.value.span)?;
+        }
+
+        if let Some(base) = base {
+            // the other values of an enum may not have the fields of this variant
+            if path.variant.is_some() {
+                return Err(TypeError::VariantUpdate(path.to_string()).spanned(base.span));
+            }
+            let base_ty = self.type_of(base)?;
+            self.expect(&ty, &base_ty, base.span)?;
+        } else {
+            let missing: Vec<_> = declared
+                .into_iter()
+                .filter(|(name, _)| !fields.iter().any(|field| field.inner.name == *name))
+                .map(|(name, _)| name)
+                .collect();
+            if !missing.is_empty() {
+                return Err(TypeError::MissingFieldValues {
+                    path: path.to_string(),
+                    fields: missing,
+                }
+                .spanned(span));
+            }
+        }
+
+        Ok(ty)
     }
 
     fn type_of_if(
@@ -333,83 +849,128 @@ impl TypeChecker {
         cond: &ExprS,
         th: &ExprS,
         el: Option<&ExprS>,
-        span: &Span,
+        span: Span,
     ) -> TypeResult {
-        let cond_type = self.type_of(cond)?;
-        self.unify(&cond_type, &Type::bool());
+        let cond_ty = self.type_of(cond)?;
+        self.expect(&Type::bool(), &cond_ty, cond.span)?;
 
-        let th_type = self.type_of(th)?;
+        let th_ty = self.type_of(th)?;
 
-        let el_type = match el {
-            Some(el) => self.type_of(el)?,
-            None => Type::unit(),
+        let (el_ty, el_span) = match el {
+            Some(el) => (self.type_of(el)?, el.span),
+            None => (Type::unit(), span),
         };
 
-        self.unify(&th_type, &el_type);
-        Ok(th_type)
+        self.expect(&th_ty, &el_ty, el_span)?;
+
+        Ok(th_ty)
     }
 
-    fn type_of_let(&mut self, binding: &BindingS, value: &ExprS) -> TypeResult {
-        let Binding::Var {
-            mutable,
-            ident,
-            type_annotation,
-        } = &binding.inner;
-
-        let ty = match self.type_of(value)? {
-            Type::GInt => {
-                if let Some(ty) = type_annotation.as_ref().map(Type::from)
-                    && ty.is_integer()
-                {
-                    ty
-                } else {
-                    Type::GInt
+    fn type_of_match(&mut self, scrutinee: &ExprS, arms: &[MatchArmS]) -> TypeResult {
+        let scrutinee_ty = self.type_of(scrutinee)?;
+        let ty = self.fresh();
+
+        for arm in arms {
+            self.scoped(|this| {
+                this.check_pattern(&arm.inner.pattern, &scrutinee_ty)?;
+
+                if let Some(guard) = &arm.inner.guard {
+                    let guard_ty = this.type_of(guard)?;
+                    this.expect(&Type::bool(), &guard_ty, guard.span)?;
                 }
-            }
-            ty => ty,
-        };
 
-        self.env.insert(
-            ident.to_owned(),
-            BindingInfo {
-                ty,
-                mutable: *mutable,
-            },
-        );
+                let body_ty = this.type_of(&arm.inner.body)?;
+                this.expect(&ty, &body_ty, arm.inner.body.span)
+            })?;
+        }
+
+        let scrutinee_ty = self.resolve(&scrutinee_ty);
+        self.check_arms(arms, &scrutinee_ty, scrutinee.span)?;
+
+        Ok(ty)
+    }
+
+    fn type_of_let(&mut self, binding: &BindingS, value: &ExprS) -> TypeResult {
+        let value_ty = self.type_of(value)?;
 
-        Ok(Unit!())
+        let ty = self.binding_type(binding);
+        self.expect_annotated(
+            &ty,
+            &value_ty,
+            value.span,
+            binding.inner.type_annotation().map(|ty| ty.span),
+        )?;
+
+        self.bind(binding, ty)?;
+
+        Ok(Type::unit())
     }
 
     fn type_of_assign(&mut self, ident: Spanned<&str>, value: &ExprS) -> TypeResult {
         let assigned_ty = self.type_of(value)?;
 
-        let info = self
-            .env
-            .get(ident.inner)
-            .ok_or_else(|| TypeError::UnboundIdent(ident.inner.to_owned()).spanned(ident.span))?;
+        let info =
+            self.env.get(ident.inner).cloned().ok_or_else(|| {
+                TypeError::UnboundIdent(ident.inner.to_owned()).spanned(ident.span)
+            })?;
 
         if !info.mutable {
             return Err(TypeError::Mutation(ident.inner.to_owned()).spanned(ident.span));
         }
 
-        if info.ty != assigned_ty {
-            return Err(TypeError::MismatchedTypes {
-                found: assigned_ty,
-                expected: info.ty.clone(),
-            }
-            .spanned(value.span));
-        }
+        self.expect(&info.ty, &assigned_ty, value.span)?;
+
+        Ok(Type::unit())
+    }
 
-        Ok(Unit!())
+    fn type_of_lambda(
+        &mut self,
+        params: &[BindingS],
+        return_type: Option<&TypeS>,
+        body: &ExprS,
+    ) -> TypeResult {
+        self.scoped(|this| {
+            let param_tys = params
+                .iter()
+                .map(|param| {
+                    let ty = this.binding_type(param);
+                    this.bind(param, ty.clone())?;
+                    Ok(ty)
+                })
+                .collect::<TypeResult<Vec<_>>>()?;
+
+            let body_ty = this.type_of(body)?;
+            let result_ty = this.annotation_or_fresh(return_type);
+            this.expect_annotated(
+                &result_ty,
+                &body_ty,
+                body.span,
+                return_type.map(|ty| ty.span),
+            )?;
+
+            Ok(Type::function(param_tys, result_ty))
+        })
     }
 
-    fn type_of_block(&self, exprs: &[ExprS], trailing: bool) -> TypeResult {
-        let types = self.check(exprs)?;
+    fn type_of_block(&mut self, exprs: &[ExprS], trailing: bool) -> TypeResult {
+        self.scoped(|this| {
+            let types = exprs
+                .iter()
+                .map(|expr| this.type_of(expr))
+                .collect::<TypeResult<Vec<_>>>()?;
 
-        Ok(if trailing && let Some(last) = types.last().cloned() {
-            last
-        } else {
-            Unit!()
+            Ok(if trailing && let Some(last) = types.last().cloned() {
+                last
+            } else {
+                Type::unit()
+            })
         })
     }
 }
+
+fn field_types(fields: &[FieldS]) -> Vec<(String, Type)> {
+    fields
+        .iter()
+        .map(|field| (field.inner.name.clone(), Type::from(&field.inner.ty.inner)))
+        .collect()
+}
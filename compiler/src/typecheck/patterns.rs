@@ -0,0 +1,342 @@
+//! Checking patterns against the type of the value they match, and binding the names
+//! in them.
+
+use std::iter;
+
+use crate::{
+    helpers::{Span, Spanned},
+    parser::ast::{ExprS, FieldPatternS, Path, Pattern, PatternS},
+};
+
+use super::{Type, TypeChecker, TypeError, TypeResult, VariantFields, exhaustiveness::int_value};
+
+impl TypeChecker {
+    /// Checks that `pattern` can match a value of type `expected`, binding each name in
+    /// it to the part of the value it matches.
+    pub(super) fn check_pattern(&mut self, pattern: &PatternS, expected: &Type) -> TypeResult<()> {
+        let names = bound_names(pattern);
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].iter().any(|other| other.inner == name.inner) {
+                return Err(TypeError::DuplicateBinding(name.inner.to_owned()).spanned(name.span));
+            }
+        }
+
+        self.pattern(pattern, expected)
+    }
+
+    fn pattern(&mut self, pattern: &PatternS, expected: &Type) -> TypeResult<()> {
+        let span = pattern.span;
+
+        match &pattern.inner {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Rest => Err(TypeError::MisplacedRest.spanned(span)),
+            Pattern::Binding {
+                mutable,
+                ident,
+                subpattern,
+            } => {
+                if let Some(subpattern) = subpattern {
+                    self.pattern(subpattern, expected)?;
+                }
+                self.define(ident, *mutable, expected.clone(), span);
+                Ok(())
+            }
+            Pattern::Literal(literal) => {
+                let ty = self.type_of(literal)?;
+                self.expect(expected, &ty, span)?;
+                self.check_in_range(literal, expected)
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                for bound in [start, end] {
+                    let ty = self.type_of(bound)?;
+                    self.expect(expected, &ty, bound.span)?;
+                    self.check_in_range(bound, expected)?;
+                }
+                self.require(expected, Type::is_integer, TypeError::NotInteger, span)?;
+
+                match (int_value(start), int_value(end)) {
+                    (Some(start), Some(end)) if start > end || start == end && !inclusive => {
+                        Err(TypeError::EmptyRange.spanned(span))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Pattern::Tuple(elements) => {
+                let types: Vec<_> = elements.iter().map(|_| self.fresh()).collect();
+                self.expect(expected, &Type::tuple(types.clone()), span)?;
+
+                iter::zip(elements, &types).try_for_each(|(element, ty)| self.pattern(element, ty))
+            }
+            Pattern::Array(elements) => self.array_pattern(elements, expected, span),
+            Pattern::Struct { path, fields, rest } => {
+                self.struct_pattern(path, fields, *rest, expected, span)
+            }
+            Pattern::TupleVariant { path, elements } => {
+                let fields = self.path_type(path, expected, span)?;
+                let types = match fields {
+                    VariantFields::Unit => Vec::new(),
+                    VariantFields::Tuple(types) => types,
+                    VariantFields::Struct(_) => {
+                        return Err(TypeError::NeedsBraces(path.to_string()).spanned(span));
+                    }
+                };
+
+                if types.len() != elements.len() {
+                    return Err(TypeError::PatternArity {
+                        path: path.to_string(),
+                        expected: types.len(),
+                        found: elements.len(),
+                    }
+                    .spanned(span));
+                }
+                iter::zip(elements, &types).try_for_each(|(element, ty)| self.pattern(element, ty))
+            }
+            Pattern::Path(path) => match self.path_type(path, expected, span)? {
+                VariantFields::Unit => Ok(()),
+                VariantFields::Tuple(types) => Err(TypeError::PatternArity {
+                    path: path.to_string(),
+                    expected: types.len(),
+                    found: 0,
+                }
+                .spanned(span)),
+                VariantFields::Struct(_) => {
+                    Err(TypeError::NeedsBraces(path.to_string()).spanned(span))
+                }
+            },
+            Pattern::Or(alternatives) => self.or_pattern(alternatives, expected),
+        }
+    }
+
+    /// Checks that an integer literal fits in `ty`, if that is known already, so that the
+    /// exhaustiveness of the `match` isn't worked out from values it can't have.
+    fn check_in_range(&mut self, literal: &ExprS, ty: &Type) -> TypeResult<()> {
+        let ty = self.shallow(ty);
+        match (int_value(literal), ty.integer_range()) {
+            (Some(value), Some((min, max))) if !(min..=max).contains(&value) => {
+                Err(TypeError::LiteralOutOfRange(ty).spanned(literal.span))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks an array pattern, where a `..` standing for the elements that no other
+    /// pattern matches can be bound with `@` to an array of them.
+    fn array_pattern(
+        &mut self,
+        elements: &[PatternS],
+        expected: &Type,
+        span: Span,
+    ) -> TypeResult<()> {
+        let element_ty = self.fresh();
+        self.expect(expected, &Type::array(element_ty.clone()), span)?;
+
+        let mut rest = false;
+        for element in elements {
+            match &element.inner {
+                Pattern::Rest => {}
+                Pattern::Binding {
+                    mutable,
+                    ident,
+                    subpattern: Some(subpattern),
+                } if subpattern.inner == Pattern::Rest => {
+                    self.define(ident, *mutable, expected.clone(), element.span);
+                }
+                _ => {
+                    self.pattern(element, &element_ty)?;
+                    continue;
+                }
+            }
+
+            if rest {
+                return Err(TypeError::MisplacedRest.spanned(element.span));
+            }
+            rest = true;
+        }
+
+        Ok(())
+    }
+
+    fn struct_pattern(
+        &mut self,
+        path: &Path,
+        fields: &[FieldPatternS],
+        rest: bool,
+        expected: &Type,
+        span: Span,
+    ) -> TypeResult<()> {
+        let declared = self.path_type(path, expected, span)?.named();
+
+        for field in fields {
+            let ty = declared
+                .iter()
+                .find(|(name, _)| *name == field.inner.name)
+                .map(|(_, ty)| ty.clone())
+                .ok_or_else(|| {
+                    TypeError::NoField {
+                        ty: self.resolve(expected),
+                        field: field.inner.name.clone(),
+                    }
+                    .spanned(field.span)
+                })?;
+            self.pattern(&field.inner.pattern, &ty)?;
+        }
+
+        let missing: Vec<_> = declared
+            .into_iter()
+            .filter(|(name, _)| !fields.iter().any(|field| field.inner.name == *name))
+            .map(|(name, _)| name)
+            .collect();
+        if !rest && !missing.is_empty() {
+            return Err(TypeError::MissingFields {
+                path: path.to_string(),
+                fields: missing,
+            }
+            .spanned(span));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every alternative binds the same names, to values of the same types.
+    fn or_pattern(&mut self, alternatives: &[PatternS], expected: &Type) -> TypeResult<()> {
+        let (first, others) = alternatives
+            .split_first()
+            .expect("or-patterns have at least two alternatives");
+        self.pattern(first, expected)?;
+
+        let names = bound_names(first);
+        let types: Vec<_> = names
+            .iter()
+            .map(|name| self.env[name.inner].ty.clone())
+            .collect();
+
+        for alternative in others {
+            self.pattern(alternative, expected)?;
+
+            let alternative_names = bound_names(alternative);
+            if let Some(name) = names.iter().find(|name| {
+                !alternative_names
+                    .iter()
+                    .any(|other| other.inner == name.inner)
+            }) {
+                return Err(
+                    TypeError::OrPatternBindings(name.inner.to_owned()).spanned(alternative.span)
+                );
+            }
+            if let Some(name) = alternative_names
+                .iter()
+                .find(|name| !names.iter().any(|other| other.inner == name.inner))
+            {
+                return Err(TypeError::OrPatternBindings(name.inner.to_owned()).spanned(first.span));
+            }
+
+            for name in &alternative_names {
+                let index = names.iter().position(|other| other.inner == name.inner);
+                let alternative_ty = self.env[name.inner].ty.clone();
+                self.expect(&types[index.unwrap()], &alternative_ty, name.span)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the struct or enum variant `path` names has type `expected`, giving
+    /// the types of its fields.
+    pub(super) fn path_type(
+        &mut self,
+        path: &Path,
+        expected: &Type,
+        span: Span,
+    ) -> TypeResult<VariantFields> {
+        let (generic_params, fields) = match &path.variant {
+            None => {
+                let info = self
+                    .structs
+                    .get(&path.name)
+                    .ok_or_else(|| TypeError::UnknownType(path.name.clone()).spanned(span))?;
+                (
+                    info.generic_params.clone(),
+                    VariantFields::Struct(info.fields.clone()),
+                )
+            }
+            Some(variant) => {
+                let info = self.enums.get(&path.name).ok_or_else(|| {
+                    if self.structs.contains_key(&path.name) {
+                        TypeError::NoVariant {
+                            ty: path.name.clone(),
+                            variant: variant.clone(),
+                        }
+                    } else {
+                        TypeError::UnknownType(path.name.clone())
+                    }
+                    .spanned(span)
+                })?;
+                let fields = info
+                    .variants
+                    .iter()
+                    .find(|(name, _)| name == variant)
+                    .map(|(_, fields)| fields.clone())
+                    .ok_or_else(|| {
+                        TypeError::NoVariant {
+                            ty: path.name.clone(),
+                            variant: variant.clone(),
+                        }
+                        .spanned(span)
+                    })?;
+                (info.generic_params.clone(), fields)
+            }
+        };
+
+        let args: Vec<_> = generic_params.iter().map(|_| self.fresh()).collect();
+        self.expect(
+            expected,
+            &Type::Named(path.name.clone(), args.clone()),
+            span,
+        )?;
+
+        Ok(fields.substitute(&generic_params, &args))
+    }
+}
+
+/// The names `pattern` binds, in order, with the span of the pattern binding each. Only
+/// the first alternative of an or-pattern is looked at.
+fn bound_names(pattern: &PatternS) -> Vec<Spanned<&str>> {
+    fn walk<'a>(pattern: &'a PatternS, names: &mut Vec<Spanned<&'a str>>) {
+        match &pattern.inner {
+            Pattern::Wildcard
+            | Pattern::Rest
+            | Pattern::Literal(_)
+            | Pattern::Range { .. }
+            | Pattern::Path(_) => {}
+            Pattern::Binding {
+                ident, subpattern, ..
+            } => {
+                names.push(Spanned::span(ident.as_str(), pattern.span));
+                if let Some(subpattern) = subpattern {
+                    walk(subpattern, names);
+                }
+            }
+            Pattern::Tuple(elements)
+            | Pattern::Array(elements)
+            | Pattern::TupleVariant { elements, .. } => {
+                for element in elements {
+                    walk(element, names);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for field in fields {
+                    walk(&field.inner.pattern, names);
+                }
+            }
+            Pattern::Or(alternatives) => walk(&alternatives[0], names),
+        }
+    }
+
+    let mut names = Vec::new();
+    walk(pattern, &mut names);
+    names
+}
@@ -1,9 +1,28 @@
-use super::{Type, TypeChecker};
+use std::ops::Range;
+
+use super::{Type, TypeChecker, TypeError};
 use crate::parser::Parser;
 
+fn type_of(input: &str) -> Result<Type, TypeError> {
+    let mut parser = Parser::new(input);
+    let expr = parser.expression().unwrap();
+    TypeChecker::default()
+        .check(&[expr])
+        .map(|mut types| types.remove(0))
+        .map_err(|err| err.inner)
+}
+
+fn check_file(input: &str) -> Result<Vec<Type>, TypeError> {
+    let ast = Parser::new(input).file().unwrap();
+    TypeChecker::new(&ast)
+        .check_items(&ast)
+        .map_err(|err| err.inner)
+}
+
 #[test]
 fn typecheck_block() {
-    let input = "
+    let ty = type_of(
+        "
     {
         let mut y: Int = 5;
         3 + 1 - 2;
@@ -12,10 +31,761 @@ fn typecheck_block() {
             let a = -5;
             a
         } else 32;
-    }";
-    let mut parser = Parser::new(input);
-    let expr = parser.expression().unwrap();
-    //let types = TypeChecker::default().type_of(&expr).unwrap();
+    }",
+    );
+
+    assert_eq!(ty, Ok(Type::unit()));
+}
+
+#[test]
+fn typecheck_literals() {
+    assert_eq!(
+        type_of("(1, 2.5, true)"),
+        Ok(Type::tuple(vec![Type::int(), Type::float(), Type::bool()]))
+    );
+    assert_eq!(type_of("18446744073709551615"), Ok(Type::uint()));
+    assert_eq!(type_of("[1, 2][0]"), Ok(Type::int()));
+    assert_eq!(
+        type_of("[1, 2.5]"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::int(),
+            found: Type::float(),
+            annotation: None
+        })
+    );
+}
+
+#[test]
+fn typecheck_number_suffixes() {
+    assert_eq!(
+        type_of("(10u8, 1.5f32, 7i64, 2f64)"),
+        Ok(Type::tuple(vec![
+            Type::named("$U8"),
+            Type::named("$F32"),
+            Type::int(),
+            Type::float()
+        ]))
+    );
+    assert!(
+        check_file("const a: U16 = 10 const b: F32 = 1f32 + 2.5f32 const c: I8 = -128i8").is_ok()
+    );
+    assert_eq!(
+        type_of("1u8 + 1u16"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::named("$U8"),
+            found: Type::named("$U16"),
+            annotation: None
+        })
+    );
+    assert_eq!(
+        type_of("-1u32"),
+        Err(TypeError::NotSigned(Type::named("$U32")))
+    );
+}
+
+#[test]
+fn typecheck_literal_ranges() {
+    fn out_of_range<T>(ty: &str) -> Result<T, TypeError> {
+        Err(TypeError::LiteralOutOfRange(Type::named(ty)))
+    }
+
+    assert_eq!(check_file("fn f(): U8 -> 300"), out_of_range("$U8"));
+    assert_eq!(check_file("const c: U8 = 256"), out_of_range("$U8"));
+    assert_eq!(check_file("const c: I8 = -129"), out_of_range("$I8"));
+    assert_eq!(check_file("fn f(x: U8) -> x + 1000"), out_of_range("$U8"));
+    // the type can be decided after the literal is checked
+    assert_eq!(
+        check_file(
+            "fn f() -> 300
+fn g(): U8 -> f()"
+        ),
+        out_of_range("$U8")
+    );
+    assert_eq!(type_of("-9223372036854775809"), out_of_range("$Int"));
+    // patterns are checked before the arms they leave out are worked out
+    assert_eq!(
+        check_file("fn f(x: U8) -> match x { 0..=300 => 0 }"),
+        out_of_range("$U8")
+    );
+    let input = "fn f(x: U8) -> match x { 300 => 0, _ => 1 }";
+    let ast = Parser::new(input).file().unwrap();
+    let mut checker = TypeChecker::new(&ast);
+    assert_eq!(
+        checker.check_items(&ast).map_err(|err| err.inner),
+        out_of_range("$U8")
+    );
+    assert!(checker.warnings().is_empty());
+    assert_eq!(
+        check_file("fn f(x: I8) -> match x { -129..=0 => 0, _ => 1 }"),
+        out_of_range("$I8")
+    );
+
+    assert!(check_file("const a: U8 = 255 const b: I8 = -128 const c: U32 = 4294967295").is_ok());
+    assert_eq!(type_of("-9223372036854775808"), Ok(Type::int()));
+    assert_eq!(type_of("18446744073709551615"), Ok(Type::uint()));
+}
+
+#[test]
+fn typecheck_float_literals() {
+    let types = check_file(
+        "fn half(): F32 -> 0.5
+        const c: F32 = -1.5 * 2.0
+        fn scale(x: F32) -> x * 2.5",
+    )
+    .unwrap();
+    let f32 = Type::named("$F32");
+    assert_eq!(types[0], Type::function(vec![], f32.clone()));
+    assert_eq!(types[1], f32);
+    assert_eq!(types[2], Type::function(vec![f32.clone()], f32));
+    assert_eq!(type_of("2.5 * 2.0"), Ok(Type::float()));
+
+    // a float literal only becomes a float type
+    assert_eq!(
+        check_file("const c: I32 = 1.5"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::named("$I32"),
+            found: Type::float(),
+            annotation: Some((9..12).into())
+        })
+    );
+    assert_eq!(
+        type_of("1.5 + 1"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::float(),
+            found: Type::int(),
+            annotation: None
+        })
+    );
+    assert_eq!(
+        type_of("1.5 | 2.5"),
+        Err(TypeError::NotInteger(Type::float()))
+    );
+}
+
+#[test]
+fn typecheck_negative_literals() {
+    let types = check_file(
+        "const a: I8 = -1
+        const b: I32 = -2147483648
+        fn c(): I16 -> -(1)
+        fn d(x: I16) -> match x { -1 => 0, -300..=-2 => 1, _ => 2 }
+        fn e(x: I8) -> x * -3",
+    )
+    .unwrap();
+    assert_eq!(types[0], Type::named("$I8"));
+    assert_eq!(types[1], Type::named("$I32"));
+    assert_eq!(
+        types[4],
+        Type::function(vec![Type::named("$I8")], Type::named("$I8"))
+    );
+    assert_eq!(type_of("-1"), Ok(Type::int()));
+
+    // a negated literal can't become unsigned, whenever that is decided
+    assert_eq!(
+        check_file("const a: U8 = -1"),
+        Err(TypeError::NotSigned(Type::named("$U8")))
+    );
+    assert_eq!(
+        type_of("{ let x = -1; let y: U16 = x; y }"),
+        Err(TypeError::NotSigned(Type::named("$U16")))
+    );
+}
+
+#[test]
+fn typecheck_errors() {
+    assert_eq!(type_of("x"), Err(TypeError::UnboundIdent("x".into())));
+    assert_eq!(
+        type_of("{ let x = 1; x = 2 }"),
+        Err(TypeError::Mutation("x".into()))
+    );
+    assert_eq!(
+        type_of("true + false"),
+        Err(TypeError::NotNumeric(Type::bool()))
+    );
+    assert_eq!(
+        type_of("-(1.5 < 2.5)"),
+        Err(TypeError::NotNumeric(Type::bool()))
+    );
+    assert_eq!(
+        type_of("(fn(a, b) -> a)(1)"),
+        Err(TypeError::WrongArgCount {
+            needed: 2,
+            provided: 1
+        })
+    );
+}
+
+#[test]
+fn typecheck_items() {
+    let types = check_file(
+        "
+        fn sum(a, b) -> a + b
+        fn twice(f: fn(Int): Int, x) -> f(f(x))
+        const four: Int = twice(fn(x) -> sum(x, 1), 2)
+        struct Point { x: Float, y: Float }
+        fn norm(p: Point): Float -> p.x * p.x + p.y * p.y",
+    )
+    .unwrap();
+
+    assert_eq!(
+        types[0],
+        Type::function(vec![Type::int(), Type::int()], Type::int())
+    );
+    assert_eq!(
+        types[1],
+        Type::function(
+            vec![Type::function(vec![Type::int()], Type::int()), Type::int()],
+            Type::int()
+        )
+    );
+    assert_eq!(types[2], Type::int());
+    assert_eq!(types[3], Type::named("Point"));
+    assert_eq!(
+        types[4],
+        Type::function(vec![Type::named("Point")], Type::float())
+    );
+
+    assert_eq!(
+        check_file("struct Point { x: Float } fn f(p: Point) -> p.z"),
+        Err(TypeError::NoField {
+            ty: Type::named("Point"),
+            field: "z".into()
+        })
+    );
+
+    // what an operator needs of an unannotated parameter is checked once it is known
+    let cases = [
+        (
+            "fn sum(a, b) -> a + b  const x: Bool = sum(true, false)",
+            TypeError::NotNumeric(Type::bool()),
+        ),
+        (
+            "fn neg(a) -> -a  const x: Str = neg(\"s\")",
+            TypeError::NotNumeric(Type::str()),
+        ),
+        (
+            "fn neg(a) -> -a  const x: U8 = neg(1u8)",
+            TypeError::NotSigned(Type::named("$U8")),
+        ),
+        (
+            "fn mask(a, b) -> a & b  const x: Float = mask(1.5, 2.5)",
+            TypeError::NotInteger(Type::float()),
+        ),
+        (
+            "fn less(a, b) -> a < b  const x: Bool = less(\"a\", \"b\")",
+            TypeError::NotNumeric(Type::str()),
+        ),
+    ];
+    for (input, err) in cases {
+        assert_eq!(check_file(input), Err(err), "{input}");
+    }
+    // calling one is checked by unifying it with a function type
+    assert!(matches!(
+        check_file("fn apply(f) -> f(1)  const x: Int = apply(true)"),
+        Err(TypeError::MismatchedTypes { found, .. }) if found == Type::bool()
+    ));
+    // or never, if nothing decides it
+    assert!(check_file("fn neg(a) -> -a").is_ok());
+}
+
+#[test]
+fn typecheck_interpolation() {
+    assert_eq!(
+        type_of(r#"{ let a = 1; let b = 'c'; "{a + 1} {b}{true}{2.5}{"s"}" }"#),
+        Ok(Type::str())
+    );
+    // parameters whose type is never decided are accepted
+    assert!(check_file(r#"fn f(x) -> "x is {x}""#).is_ok());
+
+    for input in [
+        r#"fn f(xs: [Int]) -> "the values are {xs}""#,
+        // the type of a parameter is checked once a call decides it
+        r#"fn f(xs) -> "the values are {xs}"  const a: Str = f([1])"#,
+    ] {
+        let ast = Parser::new(input).file().unwrap();
+        let err = TypeChecker::new(&ast).check_items(&ast).unwrap_err();
+        assert_eq!(
+            err.inner,
+            TypeError::NotDisplayable(Type::array(Type::int()))
+        );
+        // the error points into the string
+        assert_eq!(&input[err.span.start..err.span.end], "xs");
+    }
+}
+
+#[test]
+fn typecheck_placeholders() {
+    let types = check_file(
+        "fn f(a: Int, b: Str, c: Bool): Int -> a
+        const g: fn(Str): Int = f(1, _, true)
+        fn h() -> f(_, \"x\", _)
+        fn apply(x: Int) -> [f, f][0](x, _, false)",
+    )
+    .unwrap();
+    assert_eq!(types[1], Type::function(vec![Type::str()], Type::int()));
+    assert_eq!(
+        types[2],
+        Type::function(
+            vec![],
+            Type::function(vec![Type::int(), Type::bool()], Type::int())
+        )
+    );
+    assert_eq!(
+        types[3],
+        Type::function(
+            vec![Type::int()],
+            Type::function(vec![Type::str()], Type::int())
+        )
+    );
+    // the types of the left out arguments are inferred from the function
+    assert_eq!(
+        type_of("{ let add = fn(a, b) -> a + b; add(1.5, _) }"),
+        Ok(Type::function(vec![Type::float()], Type::float()))
+    );
+    assert_eq!(
+        check_file("fn f(a: Int): Int -> a const g: fn(Str): Int = f(_)"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::function(vec![Type::str()], Type::int()),
+            found: Type::function(vec![Type::int()], Type::int()),
+            annotation: Some((32..44).into()),
+        })
+    );
+
+    // anywhere but as an argument, `_` is an error
+    let inputs = [
+        "_",
+        "_ + 1",
+        "_(1)",
+        "[1][_]",
+        "{ let x = _; x }",
+        "{ let f = fn(x) -> x; f(_ + 1) }",
+    ];
+    for input in inputs {
+        assert_eq!(
+            type_of(input),
+            Err(TypeError::MisplacedPlaceholder),
+            "{input}"
+        );
+    }
+}
+
+#[test]
+fn typecheck_match() {
+    let types = check_file(
+        "enum Shape { Circle(Float), Rect { w: Float, h: Float }, Empty }
+        enum Option<T> { Some(T), None }
+        struct Point { x: Int, y: Int }
+        fn area(s: Shape) -> match s {
+            Shape::Circle(r) => 3.14 * r * r,
+            Shape::Rect { w, h } => w * h,
+            Shape::Empty => 0.0,
+        }
+        fn unwrap_or(o: Option<Int>, d: Int) -> match o { Option::Some(x) if x > 0 => x, _ => d }
+        fn on_axis(p: Point) -> match p {
+            Point { x: 0, .. } | Point { y: 0, .. } => true,
+            _ => false,
+        }
+        fn sign(n: Int) -> match (n, n > 0) { (0, _) => 0, (_, true) => 1, (_, false) => -1 }
+        fn tail(xs: [Str]) -> match xs { [_, rest @ ..] => rest, _ => xs }
+        fn first(o) -> match o {
+            Option::Some(n @ (1 | 2)) | Option::Some(n) => n,
+            Option::None => 0,
+        }",
+    )
+    .unwrap();
+    let named = |name: &str, args| Type::Named(name.into(), args);
+    assert_eq!(
+        types[3],
+        Type::function(vec![named("Shape", vec![])], Type::float())
+    );
+    assert_eq!(
+        types[4],
+        Type::function(
+            vec![named("Option", vec![Type::int()]), Type::int()],
+            Type::int()
+        )
+    );
+    assert_eq!(
+        types[5],
+        Type::function(vec![named("Point", vec![])], Type::bool())
+    );
+    assert_eq!(types[6], Type::function(vec![Type::int()], Type::int()));
+    assert_eq!(
+        types[7],
+        Type::function(vec![Type::array(Type::str())], Type::array(Type::str()))
+    );
+    // the type of the scrutinee is inferred from the patterns
+    assert_eq!(
+        types[8],
+        Type::function(vec![named("Option", vec![Type::int()])], Type::int())
+    );
+
+    // the names bound by a pattern are only in scope in its arm
+    assert_eq!(
+        type_of("{ match 1 { x => x }; x }"),
+        Err(TypeError::UnboundIdent("x".into()))
+    );
+    assert_eq!(
+        type_of("match 1 { 0 => \"zero\", _ => 1 }"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::str(),
+            found: Type::int(),
+            annotation: None
+        })
+    );
+    assert_eq!(
+        type_of("match 1 { \"one\" => 1, _ => 2 }"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::int(),
+            found: Type::str(),
+            annotation: None
+        })
+    );
+    assert_eq!(
+        type_of("match 1 { x if x => 1, _ => 2 }"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::bool(),
+            found: Type::int(),
+            annotation: None
+        })
+    );
+}
+
+#[test]
+fn typecheck_pattern_errors() {
+    let check = |pattern: &str| {
+        check_file(&format!(
+            "enum Shape {{ Circle(Float), Rect {{ w: Float, h: Float }}, Empty }}
+            struct Point {{ x: Int, y: Int }}
+            fn f(value) -> match value {{ {pattern} => 1 }}"
+        ))
+        .unwrap_err()
+    };
+
+    assert_eq!(check("Size::Big"), TypeError::UnknownType("Size".into()));
+    assert_eq!(check("Line { .. }"), TypeError::UnknownType("Line".into()));
+    assert_eq!(
+        check("Shape::Square(s)"),
+        TypeError::NoVariant {
+            ty: "Shape".into(),
+            variant: "Square".into()
+        }
+    );
+    assert_eq!(
+        check("Shape::Circle(x, y)"),
+        TypeError::PatternArity {
+            path: "Shape::Circle".into(),
+            expected: 1,
+            found: 2
+        }
+    );
+    assert_eq!(
+        check("Shape::Circle"),
+        TypeError::PatternArity {
+            path: "Shape::Circle".into(),
+            expected: 1,
+            found: 0
+        }
+    );
+    assert_eq!(
+        check("Shape::Rect(w, h)"),
+        TypeError::NeedsBraces("Shape::Rect".into())
+    );
+    assert_eq!(check("Point(x, y)"), TypeError::NeedsBraces("Point".into()));
+    assert_eq!(
+        check("Point { x }"),
+        TypeError::MissingFields {
+            path: "Point".into(),
+            fields: vec!["y".into()]
+        }
+    );
+    assert_eq!(
+        check("Point { z, .. }"),
+        TypeError::NoField {
+            ty: Type::Named("Point".into(), vec![]),
+            field: "z".into()
+        }
+    );
+    assert_eq!(
+        check("(x, 0) | (0, y)"),
+        TypeError::OrPatternBindings("x".into())
+    );
+    assert_eq!(
+        check("(0, x) | (x, true)"),
+        TypeError::MismatchedTypes {
+            expected: Type::bool(),
+            found: Type::int(),
+            annotation: None
+        }
+    );
+    assert_eq!(check("(x, x)"), TypeError::DuplicateBinding("x".into()));
+    assert_eq!(check("(.., 1)"), TypeError::MisplacedRest);
+    assert_eq!(check("[.., a @ ..]"), TypeError::MisplacedRest);
+    assert_eq!(
+        check("Point { x: 0, y: 0 } | Shape::Empty"),
+        TypeError::MismatchedTypes {
+            expected: Type::Named("Point".into(), vec![]),
+            found: Type::Named("Shape".into(), vec![]),
+            annotation: None
+        }
+    );
+}
+
+#[test]
+fn typecheck_exhaustiveness() {
+    let input = |body: &str| {
+        format!(
+            "enum Option<T> {{ Some(T), None }}
+            enum Shape {{ Circle(Float), Rect {{ w: Float, h: Float }}, Empty }}
+            fn f(o: Option<Int>, ob: Option<Bool>, b: Bool, c: U8, t: Str, s: Shape, xs: [Int]) ->
+                {body}"
+        )
+    };
+    let missing = |body: &str| match check_file(&input(body)) {
+        Ok(_) => Vec::new(),
+        Err(TypeError::NonExhaustive { missing }) => missing,
+        Err(err) => panic!("expected missing patterns, found {err:?}"),
+    };
+
+    assert_eq!(
+        missing("match o { Option::None => 0 }"),
+        ["Option::Some(_)"]
+    );
+    assert_eq!(
+        missing("match ob { Option::Some(true) => 0, Option::None => 1 }"),
+        ["Option::Some(false)"]
+    );
+    assert_eq!(
+        missing("match s { Shape::Circle(_) => 0, Shape::Empty => 1 }"),
+        ["Shape::Rect { .. }"]
+    );
+    assert_eq!(missing("match b { true => 0 }"), ["false"]);
+    assert_eq!(
+        missing("match (b, b) { (true, _) => 0, (_, true) => 1 }"),
+        ["(false, false)"]
+    );
+    assert_eq!(missing("match c { 0 => 0, 2..=254 => 1 }"), ["1", "255"]);
+    assert_eq!(missing("match c { 0..=9 => 0, 10 => 1 }"), ["11..=255"]);
+    assert_eq!(
+        missing("match (b, c) { (true, 0..=9) => 0 }"),
+        ["(true, 10..=255)", "(false, _)"]
+    );
+    assert_eq!(missing("match t { \"a\" => 0 }"), ["_"]);
+    assert_eq!(missing("match xs { [] => 0, [x] => x }"), ["[_, _, ..]"]);
+    // a guard may be false, so its arm doesn't count
+    assert_eq!(
+        missing("match b { true if c > 0 => 0, false => 1 }"),
+        ["true"]
+    );
+
+    // a field that every value of gets through is left out, like one matched by `_`
+    for arms in [
+        "P { y: true, .. } => 0",
+        "P { x: -9223372036854775808..=9223372036854775807, y: true } => 0",
+        "P { x, y: true } if x > 0 => 0, P { y: true, .. } => 1",
+    ] {
+        assert_eq!(
+            check_file(&format!(
+                "struct P {{ x: Int, y: Bool }}\nfn f(p: P) -> match p {{ {arms} }}"
+            )),
+            Err(TypeError::NonExhaustive {
+                missing: vec!["P { y: false, .. }".into()]
+            })
+        );
+    }
+
+    assert!(missing("match c { 0..=127 => 0, 128..=255 => 1 }").is_empty());
+    assert!(missing("match o { Option::Some(_) | Option::None => 0 }").is_empty());
+    assert!(missing("match xs { [] => 0, [x, ..] => x }").is_empty());
+    assert!(missing("match t { \"a\" => 0, _ => 1 }").is_empty());
+
+    let unreachable = |body: &str| {
+        let input = input(body);
+        let ast = Parser::new(&input).file().unwrap();
+        let mut checker = TypeChecker::new(&ast);
+        checker.check_items(&ast).unwrap();
+        checker
+            .warnings()
+            .iter()
+            .map(|warning| input[Range::from(warning.span)].to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(unreachable("match b { _ => 0, true => 1 }"), ["true"]);
+    assert_eq!(unreachable("match c { 0..=9 => 0, 5 => 1, _ => 2 }"), ["5"]);
+    assert_eq!(unreachable("match xs { [..] => 0, [] => 1 }"), ["[]"]);
+    assert_eq!(
+        unreachable("match (b, b) { (true, _) | (_, true) => 0, (true, true) => 1, _ => 2 }"),
+        ["(true, true)"]
+    );
+    assert!(
+        unreachable("match o { Option::Some(x) if x > 0 => x, Option::Some(1) => 1, _ => 0 }")
+            .is_empty()
+    );
+
+    assert_eq!(
+        type_of("match 1 { 5..=1 => 0, _ => 1 }"),
+        Err(TypeError::EmptyRange)
+    );
+    assert_eq!(
+        type_of("match 1 { 1..1 => 0, _ => 1 }"),
+        Err(TypeError::EmptyRange)
+    );
+    assert_eq!(
+        type_of("match 1.5 { 1.0..2.0 => 0, _ => 1 }"),
+        Err(TypeError::NotInteger(Type::float()))
+    );
+    assert_eq!(type_of("match -3 { -5..0 => 0, _ => 1 }"), Ok(Type::int()));
+}
+
+#[test]
+fn typecheck_destructuring() {
+    let types = check_file(
+        "struct Point { x: Int, y: Int }
+        enum Wrapper<T> { Wrap(T) }
+        fn swap((a, b)) -> (b, a)
+        fn norm(Point { x, y }: Point) -> x * x + y * y
+        fn unwrap(Wrapper::Wrap(value)) -> value
+        fn sum(p: Point) -> { let Point { x, .. } = p; let (y, _) = (p.y, true); x + y }
+        fn apply() -> (|(a, b)| -> a + b)((1, 2))",
+    )
+    .unwrap();
+    let point = Type::Named("Point".into(), vec![]);
+    assert_eq!(types[3], Type::function(vec![point.clone()], Type::int()));
+    assert_eq!(types[5], Type::function(vec![point], Type::int()));
+    assert_eq!(types[6], Type::function(vec![], Type::int()));
+
+    let refutable = |binding: &str| match check_file(&format!(
+        "enum Option<T> {{ Some(T), None }}
+        fn f(o: Option<Int>, b: Bool) -> {{ let {binding}; 0 }}"
+    )) {
+        Err(TypeError::Refutable { missing }) => missing,
+        other => panic!("expected a refutable pattern, found {other:?}"),
+    };
+    assert_eq!(refutable("Option::Some(x) = o"), ["Option::None"]);
+    assert_eq!(refutable("(true, x) = (b, 1)"), ["(false, _)"]);
+    assert_eq!(refutable("true = b"), ["false"]);
+    assert_eq!(
+        check_file("fn f(Option::None) -> 0\nenum Option<T> { Some(T), None }"),
+        Err(TypeError::Refutable {
+            missing: vec!["Option::Some(_)".into()]
+        })
+    );
+    // the names in a pattern are bound like those in a `match` arm
+    assert_eq!(
+        type_of("{ let (x, x) = (1, 2); x }"),
+        Err(TypeError::DuplicateBinding("x".into()))
+    );
+    assert_eq!(
+        type_of("{ let (a, b): (Int, Bool) = (1, 2); a }"),
+        Err(TypeError::MismatchedTypes {
+            expected: Type::tuple(vec![Type::int(), Type::bool()]),
+            found: Type::tuple(vec![Type::int(), Type::int()]),
+            annotation: Some((14..25).into())
+        })
+    );
+}
+
+#[test]
+fn typecheck_struct_literals() {
+    let types = check_file(
+        "struct Point { x: Int, y: Int }
+        enum Shape { Circle(Float), Rect { w: Float, h: Float }, Empty }
+        enum Option<T> { Some(T), None }
+        fn origin() -> Point { x: 0, y: 0 }
+        fn at(x, y) -> Point { y, x }
+        fn shift(p: Point) -> Point { x: p.x + 1, ..p }
+        fn shapes() -> [Shape::Circle(3.0), Shape::Rect { w: 1.0, h: 2.0 }, Shape::Empty]
+        fn circle() -> Shape::Circle
+        fn some() -> Option::Some(1)
+        fn scrutinee() -> match (Point { x: 1, y: 2 }) { Point { x, .. } => x }",
+    )
+    .unwrap();
+    let point = Type::Named("Point".into(), vec![]);
+    let shape = Type::Named("Shape".into(), vec![]);
+    assert_eq!(types[3], Type::function(vec![], point.clone()));
+    assert_eq!(
+        types[4],
+        Type::function(vec![Type::int(), Type::int()], point.clone())
+    );
+    assert_eq!(types[5], Type::function(vec![point.clone()], point));
+    assert_eq!(types[6], Type::function(vec![], Type::array(shape.clone())));
+    assert_eq!(
+        types[7],
+        Type::function(vec![], Type::function(vec![Type::float()], shape))
+    );
+    assert_eq!(
+        types[8],
+        Type::function(vec![], Type::Named("Option".into(), vec![Type::int()]))
+    );
+    assert_eq!(types[9], Type::function(vec![], Type::int()));
 
-    //assert_eq!(types, Type::Tuple(vec![]));
+    let check = |expr: &str| {
+        check_file(&format!(
+            "struct Point {{ x: Int, y: Int }}
+            enum Shape {{ Circle(Float), Rect {{ w: Float, h: Float }}, Empty }}
+            fn f(p: Point, s: Shape) -> {expr}"
+        ))
+        .unwrap_err()
+    };
+    assert_eq!(
+        check("Point { x: 1 }"),
+        TypeError::MissingFieldValues {
+            path: "Point".into(),
+            fields: vec!["y".into()]
+        }
+    );
+    assert_eq!(
+        check("Point { x: 1, y: 2, x: 3 }"),
+        TypeError::DuplicateField("x".into())
+    );
+    assert_eq!(
+        check("Point { x: 1, z: 2, ..p }"),
+        TypeError::NoField {
+            ty: Type::Named("Point".into(), vec![]),
+            field: "z".into()
+        }
+    );
+    assert_eq!(
+        check("Point { x: true, y: 2 }"),
+        TypeError::MismatchedTypes {
+            expected: Type::int(),
+            found: Type::bool(),
+            annotation: None
+        }
+    );
+    assert_eq!(
+        check("Point { x: 1, ..s }"),
+        TypeError::MismatchedTypes {
+            expected: Type::Named("Point".into(), vec![]),
+            found: Type::Named("Shape".into(), vec![]),
+            annotation: None
+        }
+    );
+    assert_eq!(
+        check("Shape::Rect { w: 1.0, ..s }"),
+        TypeError::VariantUpdate("Shape::Rect".into())
+    );
+    assert_eq!(
+        check("Shape::Rect"),
+        TypeError::NeedsBraces("Shape::Rect".into())
+    );
+    assert_eq!(
+        check("Shape::Circle(1.0, 2.0)"),
+        TypeError::WrongArgCount {
+            needed: 1,
+            provided: 2
+        }
+    );
+    assert_eq!(
+        check("Shape::Square"),
+        TypeError::NoVariant {
+            ty: "Shape".into(),
+            variant: "Square".into()
+        }
+    );
+    assert_eq!(
+        check("Line { a: p, b: p }"),
+        TypeError::UnknownType("Line".into())
+    );
 }
@@ -1,65 +1,168 @@
-use std::{cmp, convert::Infallible, iter};
+use std::{convert::Infallible, fmt::Display};
 
 use ena::unify::{UnifyKey, UnifyValue};
 
-use crate::parser::ast::Type as AstType;
+use crate::{helpers::concat, lexer::NumericSuffix, parser::ast::Type as AstType};
 
-#[derive(Debug, Clone, PartialEq)]
+const INTEGERS: [&str; 8] = [
+    "$Int", "$UInt", "$I8", "$I16", "$I32", "$U8", "$U16", "$U32",
+];
+const FLOATS: [&str; 2] = ["$Float", "$F32"];
+const SIGNED: [&str; 6] = ["$Int", "$Float", "$I8", "$I16", "$I32", "$F32"];
+
+/// Names that annotations use for the builtin types, and the internal name each one maps to.
+const PRIMITIVES: [(&str, &str); 13] = [
+    ("Int", "$Int"),
+    ("UInt", "$UInt"),
+    ("Float", "$Float"),
+    ("I8", "$I8"),
+    ("I16", "$I16"),
+    ("I32", "$I32"),
+    ("U8", "$U8"),
+    ("U16", "$U16"),
+    ("U32", "$U32"),
+    ("F32", "$F32"),
+    ("Bool", "$Bool"),
+    ("Char", "$Char"),
+    ("Str", "$Str"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     Var(TypeId),
-    Named(String, Vec<Type>),
+    Named(String, Vec<Self>),
 }
 
 impl Type {
-    pub fn id(&self) -> Option<TypeId> {
+    pub const fn id(&self) -> Option<TypeId> {
         match self {
-            Type::Var(id) => Some(*id),
-            Type::Named(..) => None,
+            Self::Var(id) => Some(*id),
+            Self::Named(..) => None,
         }
     }
-}
 
-impl From<AstType> for Type {
-    fn from(value: AstType) -> Self {
-        match value {
-            AstType::Named { name, generics } => Type::Named(
-                name,
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Self::Named(name, _) if INTEGERS.contains(&name.as_str()))
+    }
+
+    /// The smallest and largest values of an integer type.
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        let Self::Named(name, _) = self else {
+            return None;
+        };
+
+        let range = match name.as_str() {
+            "$Int" => (i64::MIN.into(), i64::MAX.into()),
+            "$UInt" => (0, u64::MAX.into()),
+            "$I8" => (i8::MIN.into(), i8::MAX.into()),
+            "$I16" => (i16::MIN.into(), i16::MAX.into()),
+            "$I32" => (i32::MIN.into(), i32::MAX.into()),
+            "$U8" => (0, u8::MAX.into()),
+            "$U16" => (0, u16::MAX.into()),
+            "$U32" => (0, u32::MAX.into()),
+            _ => return None,
+        };
+        Some(range)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::Named(name, _) if FLOATS.contains(&name.as_str()))
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::Named(name, _) if SIGNED.contains(&name.as_str()))
+    }
+
+    /// Whether values of this type can be embedded in an interpolated string.
+    pub fn is_displayable(&self) -> bool {
+        self.is_numeric() || [Self::bool(), Self::char(), Self::str()].contains(self)
+    }
+
+    /// Replaces every named type that appears in `params` with the matching type from `args`.
+    #[must_use]
+    pub fn substitute(&self, params: &[String], args: &[Self]) -> Self {
+        match self {
+            Self::Var(_) => self.clone(),
+            Self::Named(name, generics) if generics.is_empty() => params
+                .iter()
+                .position(|param| param == name)
+                .and_then(|i| args.get(i))
+                .map_or_else(|| self.clone(), Clone::clone),
+            Self::Named(name, generics) => Self::Named(
+                name.clone(),
                 generics
-                    .into_iter()
-                    .map(|type_s| type_s.inner.into())
+                    .iter()
+                    .map(|ty| ty.substitute(params, args))
                     .collect(),
             ),
-            AstType::Array(ty) => Type::Named("$Array".to_string(), vec![ty.inner.into()]),
-            AstType::Tuple(tys) => Type::Named(
-                "$Tuple".to_string(),
-                tys.into_iter().map(|type_s| type_s.inner.into()).collect(),
-            ),
-            AstType::Fn { params, result } => {
-                let type_args: Vec<Type> = params
-                    .into_iter()
-                    .map(|type_s| type_s.inner.into())
-                    .chain(iter::once(result.inner.into()))
-                    .collect();
-                Type::Named("$Function".to_string(), type_args)
-            }
         }
     }
 }
 
-impl UnifyValue for Type {
-    type Error = Infallible;
-
-    fn unify_values(a: &Self, b: &Self) -> Result<Self, Self::Error> {
-        match (a, b) {
-            (Type::Var(id_a), Type::Var(id_b)) => {
-                Ok(Type::Var(cmp::min(id_a.index(), id_b.index()).into()))
-            }
-            (ty @ Type::Named(..), Type::Var(_)) | (Type::Var(_), ty @ Type::Named(..)) => {
-                Ok(ty.clone())
+impl From<&AstType> for Type {
+    fn from(value: &AstType) -> Self {
+        match value {
+            AstType::Named { name, generics } => {
+                let name = PRIMITIVES
+                    .iter()
+                    .find(|(source, _)| source == name)
+                    .map_or(name.as_str(), |(_, internal)| internal);
+                Self::Named(
+                    name.to_string(),
+                    generics
+                        .iter()
+                        .map(|type_s| (&type_s.inner).into())
+                        .collect(),
+                )
             }
-            (Type::Named(..), Type::Named(..)) => {
-                panic!("shouldn't be unifying two concrete types")
+            AstType::Array(ty) => Self::array((&ty.inner).into()),
+            AstType::Tuple(tys) => {
+                Self::tuple(tys.iter().map(|type_s| (&type_s.inner).into()).collect())
             }
+            AstType::Fn { params, result } => Self::function(
+                params.iter().map(|type_s| (&type_s.inner).into()).collect(),
+                (&result.inner).into(),
+            ),
+        }
+    }
+}
+
+/// The type a number literal's suffix gives it.
+impl From<NumericSuffix> for Type {
+    fn from(value: NumericSuffix) -> Self {
+        let (_, internal) = PRIMITIVES
+            .iter()
+            .find(|(source, _)| *source == value.type_name())
+            .expect("every suffix names a primitive type");
+        Self::named(internal)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Var(id) => write!(f, "?{}", id.0),
+            Self::Named(name, args) => match name.as_str() {
+                "$Array" => write!(f, "[{}]", args[0]),
+                "$Tuple" if args.len() == 1 => write!(f, "({},)", args[0]),
+                "$Tuple" => write!(f, "({})", concat(args)),
+                "$Function" => {
+                    let (result, params) = args.split_last().expect("functions have a result type");
+                    write!(f, "fn({}): {result}", concat(params))
+                }
+                _ => {
+                    write!(f, "{}", name.strip_prefix('$').unwrap_or(name))?;
+                    if args.is_empty() {
+                        Ok(())
+                    } else {
+                        write!(f, "<{}>", concat(args))
+                    }
+                }
+            },
         }
     }
 }
@@ -73,6 +176,14 @@ impl Type {
         Self::tuple(vec![])
     }
 
+    pub fn int() -> Self {
+        Self::named("$Int")
+    }
+
+    pub fn uint() -> Self {
+        Self::named("$UInt")
+    }
+
     pub fn bool() -> Self {
         Self::named("$Bool")
     }
@@ -96,6 +207,78 @@ impl Type {
     pub fn tuple(of: Vec<Self>) -> Self {
         Self::Named(String::from("$Tuple"), of)
     }
+
+    pub fn function(params: Vec<Self>, result: Self) -> Self {
+        let mut args = params;
+        args.push(result);
+        Self::Named(String::from("$Function"), args)
+    }
+
+    /// Splits a function type into its parameter types and result type.
+    pub fn as_function(&self) -> Option<(&[Self], &Self)> {
+        match self {
+            Self::Named(name, args) if name == "$Function" => {
+                args.split_last().map(|(result, params)| (params, result))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What the unification table knows about a type variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bound {
+    Unknown,
+    /// The variable was introduced by an integer literal, so it can only become an integer type,
+    /// and only a signed one once the literal is negated.
+    Integer {
+        signed: bool,
+    },
+    /// The variable was introduced by a float literal, so it can only become a float type.
+    Float,
+    Known(Type),
+}
+
+impl Bound {
+    /// Whether a variable with this bound can become `ty`.
+    pub fn admits(&self, ty: &Type) -> bool {
+        match self {
+            Self::Unknown | Self::Known(_) => true,
+            Self::Integer { signed } => ty.is_integer() && (!signed || ty.is_signed()),
+            Self::Float => ty.is_float(),
+        }
+    }
+
+    /// The type a variable with this bound becomes if nothing else decides it.
+    pub fn default_type(&self) -> Option<Type> {
+        match self {
+            Self::Unknown | Self::Known(_) => None,
+            Self::Integer { .. } => Some(Type::int()),
+            Self::Float => Some(Type::float()),
+        }
+    }
+}
+
+impl UnifyValue for Bound {
+    type Error = Infallible;
+
+    fn unify_values(a: &Self, b: &Self) -> Result<Self, Self::Error> {
+        Ok(match (a, b) {
+            (Self::Unknown, other) | (other, Self::Unknown) => other.clone(),
+            (Self::Integer { signed: a }, Self::Integer { signed: b }) => {
+                Self::Integer { signed: *a || *b }
+            }
+            (Self::Known(ty), _) | (_, Self::Known(ty)) => ty.clone().into(),
+            // `TypeChecker::unify` doesn't let an integer and a float variable meet
+            (Self::Float, _) | (_, Self::Float) => Self::Float,
+        })
+    }
+}
+
+impl From<Type> for Bound {
+    fn from(value: Type) -> Self {
+        Self::Known(value)
+    }
 }
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
@@ -108,11 +291,11 @@ impl From<u32> for TypeId {
 }
 
 impl UnifyKey for TypeId {
-    type Value = Type;
+    type Value = Bound;
     fn index(&self) -> u32 {
         self.0
     }
-    fn from_index(u: u32) -> TypeId {
+    fn from_index(u: u32) -> Self {
         u.into()
     }
     fn tag() -> &'static str {